@@ -0,0 +1,532 @@
+//! Arithmetic expansion
+//!
+//! Evaluates arithmetic expressions, `$((expression))`, whose parameter expansions, command substitutions
+//! and quote removal have already been performed.
+//!
+//! The expressions use signed 64-bit integer arithmetic, which wraps around on overflow,
+//! and the C-like operators, listed here from the highest precedence to the lowest:
+//!
+//! ```text
+//! ( )                                  grouping
+//! x++ x--                              postfix increment and decrement
+//! ++x --x + - ~ !                      prefix increment and decrement, unary operators
+//! * / %                                multiplicative
+//! + -                                  additive
+//! << >>                                bitwise shifts
+//! < <= > >=                            relational
+//! == !=                                equality
+//! &                                    bitwise AND
+//! ^                                    bitwise XOR
+//! |                                    bitwise OR
+//! &&                                   logical AND
+//! ||                                   logical OR
+//! ? :                                  conditional
+//! = *= /= %= += -= <<= >>= &= ^= |=    assignment
+//! ,                                    comma, whose value is that of the right operand
+//! ```
+//!
+//! Variables can be referenced by their names, without `$`. An unset or empty variable evaluates to zero.
+//!
+//! # References
+//!
+//! - [Arithmetic Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_04)
+//! - [Shell Arithmetic @ Bash Reference Manual](https://www.gnu.org/software/bash/manual/bash.html#Shell-Arithmetic)
+
+use crate::errors::ExpansionError;
+use crate::vars::Variables;
+
+/// A token of an arithmetic expression
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// An integer constant
+    Number(i64),
+    /// A variable name
+    Name(String),
+    /// An operator, or a parenthesis
+    Operator(&'static str),
+}
+
+/// The operators, sorted so that the longer operators come before the shorter ones with the same prefix
+const OPERATORS: [&str; 38] = [
+    "<<=", ">>=", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=", "/=", "%=",
+    "+=", "-=", "&=", "^=", "|=", "(", ")", "+", "-", "~", "!", "*", "/", "%", "<", ">", "&", "^",
+    "|", "?", ":", "=", ",",
+];
+
+/// The assignment operators
+const ASSIGNMENT_OPERATORS: [&str; 11] = [
+    "=", "*=", "/=", "%=", "+=", "-=", "<<=", ">>=", "&=", "^=", "|=",
+];
+
+/// The binary operators from `|` to `*`, grouped by precedence, from the lowest to the highest
+const BINARY_OPERATORS: [&[&str]; 8] = [
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+/// Evaluates the arithmetic `expression`, and returns its value.
+///
+/// The assignments in the expression are made to the `variables`.
+///
+/// # Errors
+/// - Returns fatal [`ExpansionError`] in case the expression is invalid, e.g., `1 +`,
+///   or in case of a division by zero.
+pub fn evaluate(expression: &str, variables: &mut Variables) -> Result<i64, ExpansionError> {
+    let error = |reason: &str| ExpansionError::fatal(format!("{}: {reason}", expression.trim()));
+
+    let tokens = tokenize(expression).map_err(|reason| error(&reason))?;
+    if tokens.is_empty() {
+        return Ok(0);
+    }
+
+    let mut evaluator = Evaluator {
+        tokens,
+        position: 0,
+        variables,
+    };
+    let value = evaluator
+        .expression(true)
+        .map_err(|reason| error(&reason))?;
+    if let Some(token) = evaluator.tokens.get(evaluator.position) {
+        return Err(error(&format!(
+            "syntax error in expression (error token is \"{}\")",
+            token_text(token)
+        )));
+    }
+
+    Ok(value)
+}
+
+/// Splits the arithmetic `expression` into tokens.
+///
+/// # Errors
+/// - Returns the reason in case of an invalid number or an invalid character.
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = expression;
+
+    while let Some(ch) = rest.chars().next() {
+        if ch.is_whitespace() {
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+
+        let len = if ch.is_ascii_alphanumeric() || ch == '_' {
+            let len = rest
+                .find(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')
+                .unwrap_or(rest.len());
+            let text = &rest[..len];
+            match ch.is_ascii_digit() {
+                true => tokens.push(Token::Number(number(text)?)),
+                false => tokens.push(Token::Name(text.to_string())),
+            }
+            len
+        } else {
+            let Some(&op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) else {
+                return Err(format!(
+                    "syntax error: invalid arithmetic operator (error token is \"{rest}\")"
+                ));
+            };
+            tokens.push(Token::Operator(op));
+            op.len()
+        };
+        rest = &rest[len..];
+    }
+
+    Ok(tokens)
+}
+
+/// Parses an integer constant, which is hexadecimal if it starts with `0x` or `0X`,
+/// octal if it starts with `0`, and decimal otherwise.
+///
+/// # Errors
+/// - Returns the reason in case the constant is invalid.
+fn number(text: &str) -> Result<i64, String> {
+    let (digits, radix) =
+        if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            (hex, 16)
+        } else if text.len() > 1 && text.starts_with('0') {
+            (&text[1..], 8)
+        } else {
+            (text, 10)
+        };
+
+    // Constants that are too large wrap around, as the results of the operations do.
+    u64::from_str_radix(digits, radix)
+        .map(|value| value as i64)
+        .map_err(|_| format!("value too great for base (error token is \"{text}\")"))
+}
+
+/// Gets the text of a `token`, as it is shown in error messages.
+fn token_text(token: &Token) -> String {
+    match token {
+        Token::Number(value) => value.to_string(),
+        Token::Name(name) => name.clone(),
+        Token::Operator(op) => op.to_string(),
+    }
+}
+
+/// The state of the evaluation of an expression
+///
+/// The expression is evaluated while it is parsed. The parts that aren't evaluated,
+/// e.g., the right operand of `&&` when the left one is zero, are only parsed,
+/// so their assignments and divisions by zero have no effect.
+struct Evaluator<'a> {
+    /// The tokens of the expression
+    tokens: Vec<Token>,
+    /// The position of the next token
+    position: usize,
+    /// The shell variables, which are referenced and assigned in the expression
+    variables: &'a mut Variables,
+}
+
+impl Evaluator<'_> {
+    /// Gets the next token without consuming it.
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    /// Consumes the next token if it is one of the operators `ops`, and returns the operator.
+    fn next_operator(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(&Token::Operator(op)) if ops.contains(&op) => {
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes the operator `op`.
+    ///
+    /// # Errors
+    /// - Returns the reason in case the next token isn't `op`.
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        match self.next_operator(&[op]) {
+            Some(_) => Ok(()),
+            None => Err(match self.peek() {
+                Some(token) => format!(
+                    "syntax error: `{op}' expected (error token is \"{}\")",
+                    token_text(token)
+                ),
+                None => format!("syntax error: `{op}' expected"),
+            }),
+        }
+    }
+
+    /// Gets the value of the variable `name`, if the expression is being evaluated, or zero otherwise.
+    ///
+    /// # Errors
+    /// - Returns the reason in case the value isn't an integer constant.
+    fn variable(&self, name: &str, evaluate: bool) -> Result<i64, String> {
+        if !evaluate {
+            return Ok(0);
+        }
+
+        let value = match self.variables.get(name).map(str::trim) {
+            None | Some("") => return Ok(0),
+            Some(value) => value,
+        };
+        match value.strip_prefix('-') {
+            Some(digits) => number(digits).map(i64::wrapping_neg),
+            None => number(value.strip_prefix('+').unwrap_or(value)),
+        }
+        .map_err(|_| format!("{name}: value is not a valid number (error token is \"{value}\")"))
+    }
+
+    /// Evaluates a comma-separated list of assignments, whose value is that of the last one.
+    fn expression(&mut self, evaluate: bool) -> Result<i64, String> {
+        let mut value = self.assignment(evaluate)?;
+        while self.next_operator(&[","]).is_some() {
+            value = self.assignment(evaluate)?;
+        }
+
+        Ok(value)
+    }
+
+    /// Assigns the `value` to the variable `name`, if the expression is being evaluated, and returns the value.
+    ///
+    /// # Errors
+    /// - Returns the reason in case the variable is read-only.
+    fn assign(&mut self, name: &str, value: i64, evaluate: bool) -> Result<i64, String> {
+        if evaluate {
+            self.variables.set(name, &value.to_string())?;
+        }
+
+        Ok(value)
+    }
+
+    /// Evaluates an assignment, `name op expression`, or a conditional expression.
+    fn assignment(&mut self, evaluate: bool) -> Result<i64, String> {
+        let assigned = match (self.peek(), self.tokens.get(self.position + 1)) {
+            (Some(Token::Name(name)), Some(Token::Operator(op)))
+                if ASSIGNMENT_OPERATORS.contains(op) =>
+            {
+                Some((name.clone(), *op))
+            }
+            _ => None,
+        };
+        let Some((name, op)) = assigned else {
+            return self.conditional(evaluate);
+        };
+        self.position += 2;
+
+        let right = self.assignment(evaluate)?;
+        let value = match op {
+            "=" => right,
+            _ => {
+                let left = self.variable(&name, evaluate)?;
+                apply(&op[..op.len() - 1], left, right, evaluate)?
+            }
+        };
+
+        self.assign(&name, value, evaluate)
+    }
+
+    /// Evaluates a conditional expression, `condition ? expression : conditional`.
+    ///
+    /// Only the selected branch is evaluated.
+    fn conditional(&mut self, evaluate: bool) -> Result<i64, String> {
+        let condition = self.logical_or(evaluate)?;
+        if self.next_operator(&["?"]).is_none() {
+            return Ok(condition);
+        }
+
+        let then = self.expression(evaluate && condition != 0)?;
+        self.expect(":")?;
+        let otherwise = self.conditional(evaluate && condition == 0)?;
+
+        Ok(if condition != 0 { then } else { otherwise })
+    }
+
+    /// Evaluates a logical OR, whose right operand is evaluated only if the left one is zero.
+    fn logical_or(&mut self, evaluate: bool) -> Result<i64, String> {
+        let mut left = self.logical_and(evaluate)?;
+        while self.next_operator(&["||"]).is_some() {
+            let right = self.logical_and(evaluate && left == 0)?;
+            left = i64::from(left != 0 || right != 0);
+        }
+
+        Ok(left)
+    }
+
+    /// Evaluates a logical AND, whose right operand is evaluated only if the left one is non-zero.
+    fn logical_and(&mut self, evaluate: bool) -> Result<i64, String> {
+        let mut left = self.binary(0, evaluate)?;
+        while self.next_operator(&["&&"]).is_some() {
+            let right = self.binary(0, evaluate && left != 0)?;
+            left = i64::from(left != 0 && right != 0);
+        }
+
+        Ok(left)
+    }
+
+    /// Evaluates the binary operators with the precedence `level` in [`BINARY_OPERATORS`], or higher,
+    /// which are left-associative.
+    fn binary(&mut self, level: usize, evaluate: bool) -> Result<i64, String> {
+        let Some(ops) = BINARY_OPERATORS.get(level) else {
+            return self.unary(evaluate);
+        };
+
+        let mut left = self.binary(level + 1, evaluate)?;
+        while let Some(op) = self.next_operator(ops) {
+            let right = self.binary(level + 1, evaluate)?;
+            left = apply(op, left, right, evaluate)?;
+        }
+
+        Ok(left)
+    }
+
+    /// Evaluates the unary operators, and the prefix increment and decrement operators.
+    fn unary(&mut self, evaluate: bool) -> Result<i64, String> {
+        match self.next_operator(&["++", "--", "+", "-", "~", "!"]) {
+            Some(op @ ("++" | "--")) => {
+                let Some(Token::Name(name)) = self.peek().cloned() else {
+                    return Err(format!("syntax error: operand expected after `{op}'"));
+                };
+                self.position += 1;
+                let value = match op {
+                    "++" => self.variable(&name, evaluate)?.wrapping_add(1),
+                    _ => self.variable(&name, evaluate)?.wrapping_sub(1),
+                };
+                self.assign(&name, value, evaluate)
+            }
+            Some("+") => self.unary(evaluate),
+            Some("-") => Ok(self.unary(evaluate)?.wrapping_neg()),
+            Some("~") => Ok(!self.unary(evaluate)?),
+            Some(_) => Ok(i64::from(self.unary(evaluate)? == 0)),
+            None => self.postfix(evaluate),
+        }
+    }
+
+    /// Evaluates an operand, which can be followed by the postfix increment and decrement operators.
+    fn postfix(&mut self, evaluate: bool) -> Result<i64, String> {
+        let token = self.peek().cloned();
+        self.position += 1;
+
+        match token {
+            Some(Token::Number(value)) => Ok(value),
+            Some(Token::Name(name)) => {
+                let value = self.variable(&name, evaluate)?;
+                match self.next_operator(&["++", "--"]) {
+                    Some("++") => self.assign(&name, value.wrapping_add(1), evaluate)?,
+                    Some(_) => self.assign(&name, value.wrapping_sub(1), evaluate)?,
+                    None => value,
+                };
+                Ok(value)
+            }
+            Some(Token::Operator("(")) => {
+                let value = self.expression(evaluate)?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Some(token) => Err(format!(
+                "syntax error: operand expected (error token is \"{}\")",
+                token_text(&token)
+            )),
+            None => Err("syntax error: operand expected".to_string()),
+        }
+    }
+}
+
+/// Applies the binary operator `op` to the operands.
+///
+/// # Errors
+/// - Returns the reason in case of a division by zero, if the expression is being evaluated.
+fn apply(op: &str, left: i64, right: i64, evaluate: bool) -> Result<i64, String> {
+    let value = match op {
+        "*" => left.wrapping_mul(right),
+        "/" | "%" if right == 0 => {
+            return match evaluate {
+                true => Err("division by 0".to_string()),
+                false => Ok(0),
+            };
+        }
+        "/" => left.wrapping_div(right),
+        "%" => left.wrapping_rem(right),
+        "+" => left.wrapping_add(right),
+        "-" => left.wrapping_sub(right),
+        "<<" => left.wrapping_shl(right as u32),
+        ">>" => left.wrapping_shr(right as u32),
+        "<" => i64::from(left < right),
+        "<=" => i64::from(left <= right),
+        ">" => i64::from(left > right),
+        ">=" => i64::from(left >= right),
+        "==" => i64::from(left == right),
+        "!=" => i64::from(left != right),
+        "&" => left & right,
+        "^" => left ^ right,
+        _ => left | right,
+    };
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+    use crate::vars::Variables;
+
+    /// Evaluates the `expression` with no variables set.
+    fn eval(expression: &str) -> i64 {
+        evaluate(expression, &mut Variables::default()).unwrap()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(7, eval("1 + 2 * 3"));
+        assert_eq!(9, eval("(1 + 2) * 3"));
+        assert_eq!(1, eval("10 - 4 - 5"));
+        assert_eq!(2, eval("16 / 4 / 2"));
+        assert_eq!(-7, eval("-(3 + 4)"));
+        assert_eq!(1, eval("1 + 2 == 3 && 4 > 3"));
+        assert_eq!(6, eval("1 << 2 | 2"));
+        assert_eq!(0, eval("!5"));
+        assert_eq!(-1, eval("~0"));
+        assert_eq!(1, eval("5 & 6 ^ 4 | 1"));
+        assert_eq!(0, eval("   "));
+        assert_eq!(-1, eval("7 % -4 - 4"));
+        assert_eq!(3, eval("1, 2, 3"));
+        assert_eq!(5, eval("(1, 2) + 3"));
+        assert_eq!(2, eval("1 ? 1, 2 : 3"));
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(255, eval("0xff"));
+        assert_eq!(8, eval("010"));
+        assert_eq!(0, eval("0"));
+        assert!(evaluate("08", &mut Variables::default()).is_err());
+        assert_eq!(i64::MIN, eval("9223372036854775807 + 1"));
+    }
+
+    #[test]
+    fn conditional() {
+        assert_eq!(2, eval("0 ? 1 : 2"));
+        assert_eq!(1, eval("5 ? 1 : 2"));
+        assert_eq!(3, eval("0 ? 1 : 0 ? 2 : 3"));
+        assert_eq!(0, eval("1 ? 0 : 1 / 0"));
+        assert_eq!(0, eval("0 && 1 / 0"));
+        assert_eq!(1, eval("1 || 1 / 0"));
+    }
+
+    #[test]
+    fn variables() {
+        let mut variables = Variables::default();
+        variables.set("x", "5").unwrap();
+        variables.set("empty", "").unwrap();
+
+        assert_eq!(6, evaluate("x + 1", &mut variables).unwrap());
+        assert_eq!(0, evaluate("unset + empty", &mut variables).unwrap());
+        assert_eq!(15, evaluate("x *= 3", &mut variables).unwrap());
+        assert_eq!(Some("15"), variables.get("x"));
+        assert_eq!(15, evaluate("x++", &mut variables).unwrap());
+        assert_eq!(17, evaluate("++x", &mut variables).unwrap());
+        assert_eq!(16, evaluate("--x", &mut variables).unwrap());
+        assert_eq!(16, evaluate("x--", &mut variables).unwrap());
+        assert_eq!(Some("15"), variables.get("x"));
+        assert_eq!(3, evaluate("y = z = 3", &mut variables).unwrap());
+        assert_eq!(Some("3"), variables.get("y"));
+        assert_eq!(0, evaluate("0 && (x = 1)", &mut variables).unwrap());
+        assert_eq!(Some("15"), variables.get("x"));
+        assert_eq!(30, evaluate("x <<= 1", &mut variables).unwrap());
+
+        assert_eq!(4, evaluate("y = 1, y += 3", &mut variables).unwrap());
+        assert_eq!(Some("4"), variables.get("y"));
+
+        // A variable that isn't a number is an error only if the expression is evaluated
+        variables.set("x", "abc").unwrap();
+        let err = evaluate("x + 1", &mut variables).unwrap_err();
+        assert_eq!(
+            "x + 1: x: value is not a valid number (error token is \"abc\")",
+            err.reason
+        );
+        assert_eq!(0, evaluate("0 && x++", &mut variables).unwrap());
+        assert_eq!(1, evaluate("1 ? 1 : (x += 1)", &mut variables).unwrap());
+        assert_eq!(Some("abc"), variables.get("x"));
+    }
+
+    #[test]
+    fn errors() {
+        let mut variables = Variables::default();
+        for expression in [
+            "1 / 0", "1 % 0", "1 +", "1 ,", "(1", "1 2", "* 1", "1 ? 2", "++1", "x = ", "1 $ 2",
+            "5 = 1",
+        ] {
+            assert!(
+                evaluate(expression, &mut variables).is_err(),
+                "{expression}"
+            );
+        }
+
+        let err = evaluate("1 / 0", &mut variables).unwrap_err();
+        assert_eq!("1 / 0: division by 0", err.reason);
+        assert!(err.fatal);
+    }
+}
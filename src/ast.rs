@@ -0,0 +1,700 @@
+//! Abstract syntax tree of the shell language
+//!
+//! The tree is produced by [`crate::parse::parse_input`], and it follows the shell grammar:
+//! a [`List`] consists of [`AndOrList`]s, which consist of [`Pipeline`]s, which consist of [`Command`]s.
+//! A [`CompoundCommand`] contains [`List`]s in turn.
+//!
+//! Words keep their quoting and expansion parts, because they are expanded only right before
+//! the command that contains them is run.
+//!
+//! # References
+//!
+//! - [Shell Grammar](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_10)
+//! - [Shell Commands](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09)
+
+use std::fmt::{Display, Formatter};
+
+/// A list is a sequence of one or more AND-OR lists separated by the operators `;`, `&` or newline.
+///
+/// The AND-OR lists are run sequentially, except for those terminated by `&`,
+/// which are run asynchronously, in the background.
+///
+/// # References
+/// - [Asynchronous Lists](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_03_02)
+/// - [Sequential Lists](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_03_04)
+pub type List = Vec<AndOrList>;
+
+/// An AND-OR list is a sequence of one or more pipelines separated by the operators `&&` and `||`.
+///
+/// The operators have equal precedence and are evaluated with left associativity.
+///
+/// # References
+/// - [AND-OR Lists](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_03)
+#[derive(Clone, Debug, PartialEq)]
+pub struct AndOrList {
+    /// The first pipeline, which is always run
+    pub first: Pipeline,
+    /// The rest of the pipelines, each with the operator that precedes it
+    pub rest: Vec<(AndOrOp, Pipeline)>,
+    /// Whether the AND-OR list was terminated by `&`, in which case it is run asynchronously, in the background
+    pub background: bool,
+}
+
+/// The control operator that connects two pipelines of an [`AndOrList`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AndOrOp {
+    /// `&&`: the next pipeline runs only if the previous one succeeded, i.e., returned zero exit status
+    And,
+    /// `||`: the next pipeline runs only if the previous one failed, i.e., returned non-zero exit status
+    Or,
+}
+
+/// A pipeline is a sequence of one or more commands separated by the control operator `|`.
+///
+/// The standard output of each command but the last one is connected through a pipe
+/// to the standard input of the next command.
+///
+/// # References
+/// - [Pipelines](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_02)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pipeline {
+    /// The commands of the pipeline, in order
+    pub commands: Vec<Command>,
+    /// Whether the pipeline is preceded by `!`, which inverts its exit status
+    pub negated: bool,
+}
+
+/// A command, which is an element of a [`Pipeline`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// A simple command
+    Simple(SimpleCommand),
+    /// A compound command, with the redirections that apply to the whole command
+    Compound {
+        command: CompoundCommand,
+        redirections: Vec<Redirection>,
+    },
+    /// A function definition
+    Function(FunctionDefinition),
+}
+
+/// A function definition, `name() compound-command [redirection...]`
+///
+/// Running it stores the function in the shell, so that `name` can be used as a command name afterwards.
+/// The arguments of the command are the positional parameters of the function while its body runs,
+/// and the redirections are applied every time the function is called.
+///
+/// # References
+/// - [Function Definition Command](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_05)
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionDefinition {
+    /// The name of the function
+    pub name: String,
+    /// The body of the function
+    pub body: CompoundCommand,
+    /// The redirections that apply to the body
+    pub redirections: Vec<Redirection>,
+}
+
+/// A compound command, which contains lists of commands, and which starts with a reserved word or `(`
+///
+/// # References
+/// - [Compound Commands](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04)
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompoundCommand {
+    /// An `if` conditional
+    If(IfClause),
+    /// A `for` loop
+    For(ForClause),
+    /// A `while` or an `until` loop
+    While(WhileClause),
+    /// A `case` conditional
+    Case(CaseClause),
+    /// A subshell, `( list )`, whose list is run in a child process, so that it doesn't affect the shell
+    Subshell(List),
+    /// A brace group, `{ list; }`, whose list is run in the current shell
+    BraceGroup(List),
+}
+
+/// An `if` conditional, `if list; then list; [elif list; then list;]... [else list;] fi`
+///
+/// The body of the first branch whose condition has zero exit status is run, or the `else` body,
+/// if there is no such branch. The exit status is that of the body that was run, or zero if none was run.
+///
+/// # References
+/// - [The if Conditional Construct](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04_07)
+#[derive(Clone, Debug, PartialEq)]
+pub struct IfClause {
+    /// The branches of `if` and of each `elif`, in order, as pairs of the condition and the body
+    pub branches: Vec<(List, List)>,
+    /// The body of `else`, if there is one
+    pub else_body: Option<List>,
+}
+
+/// A `for` loop, `for name [in word...]; do list; done`
+///
+/// The words are expanded, and the body is run once for each resulting field, which is assigned
+/// to the variable `name` first. Without `in`, the body is run for each positional parameter instead,
+/// as if `in "$@"` was given. The exit status is that of the last body that was run, or zero if none was run.
+///
+/// # References
+/// - [The for Loop](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04_03)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForClause {
+    /// The name of the variable
+    pub name: String,
+    /// The words after `in`, or `None` if there is no `in`
+    pub words: Option<Vec<Word>>,
+    /// The body of the loop
+    pub body: List,
+}
+
+/// A `while` loop, `while list; do list; done`, or an `until` loop, `until list; do list; done`
+///
+/// The body is run as long as the condition has zero exit status, or, in case of `until`,
+/// as long as it has non-zero exit status. The exit status is that of the last body that was run,
+/// or zero if none was run.
+///
+/// # References
+/// - [The while Loop](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04_09)
+/// - [The until Loop](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04_10)
+#[derive(Clone, Debug, PartialEq)]
+pub struct WhileClause {
+    /// The condition of the loop
+    pub condition: List,
+    /// The body of the loop
+    pub body: List,
+    /// Whether it is an `until` loop, whose condition is negated
+    pub until: bool,
+}
+
+/// A `case` conditional, `case word in [(]pattern[|pattern]...) list;; ... esac`
+///
+/// The word is expanded, and the body of the first item with a pattern that matches it is run.
+/// The patterns are expanded and tried in order. The exit status is that of the body that was run,
+/// or zero if none was run.
+///
+/// # References
+/// - [Case Conditional Construct](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04_05)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseClause {
+    /// The word that is matched against the patterns
+    pub word: Word,
+    /// The items, in order
+    pub items: Vec<CaseItem>,
+}
+
+/// An item of a `case` conditional, whose patterns are separated by `|`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseItem {
+    /// The patterns, which are words
+    pub patterns: Vec<Word>,
+    /// The body, which can be empty
+    pub body: List,
+}
+
+/// A simple command is a sequence of variable assignments, words and redirections.
+///
+/// The first word is the command name, and the rest of the words are its arguments.
+/// A simple command can consist of assignments or redirections only.
+///
+/// # References
+/// - [Simple Commands](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_01)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimpleCommand {
+    /// The variable assignments that precede the command name, e.g., `LANG=C` in `LANG=C sort`
+    pub assignments: Vec<Assignment>,
+    /// The command name followed by its arguments
+    pub words: Vec<Word>,
+    /// The redirections of the command, in the order in which they were given
+    pub redirections: Vec<Redirection>,
+}
+
+/// The operator of a [`Redirection`]
+///
+/// The redirected file descriptor defaults to `0` for the input operators, and to `1` for the output operators.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RedirectionOp {
+    /// `[n]<word`: the target file is opened for reading
+    Input,
+    /// `[n]>word` or `[n]>|word`: the target file is created or truncated, and opened for writing
+    Output,
+    /// `[n]>>word`: the target file is created if it doesn't exist, and opened for appending
+    Append,
+    /// `[n]<>word`: the target file is created if it doesn't exist, and opened for reading and writing
+    ReadWrite,
+    /// `[n]<&word`: the file descriptor is a duplicate of the input file descriptor `word`,
+    /// or it is closed if `word` is `-`
+    DupInput,
+    /// `[n]>&word`: the file descriptor is a duplicate of the output file descriptor `word`,
+    /// or it is closed if `word` is `-`; shell-specific `>&word`, where `word` isn't a number,
+    /// is the same as `&>word`
+    DupOutput,
+    /// Shell-specific `&>word`: both `stdout` and `stderr` are redirected to the target file
+    OutputAll,
+    /// Shell-specific `&>>word`: both `stdout` and `stderr` are appended to the target file
+    AppendAll,
+    /// Shell-specific `[n]<<<word`: the target word itself, followed by a newline, is the input
+    HereString,
+    /// `[n]<<word` or `[n]<<-word`: the lines that follow, up to the delimiter `word`, are the input;
+    /// with `<<-`, leading tab characters are stripped from them
+    HereDocument { strip_tabs: bool },
+}
+
+impl RedirectionOp {
+    /// Gets the file descriptor that is redirected if none is given.
+    pub fn default_fd(&self) -> i32 {
+        match self {
+            Self::Input
+            | Self::ReadWrite
+            | Self::DupInput
+            | Self::HereString
+            | Self::HereDocument { .. } => 0,
+            Self::Output | Self::Append | Self::DupOutput | Self::OutputAll | Self::AppendAll => 1,
+        }
+    }
+}
+
+/// A variable assignment, `name=value`, which is a word that precedes the command name in a simple command
+///
+/// # References
+/// - [Shell Variables](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_05_03)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    /// The name of the variable
+    pub name: String,
+    /// The value, which is expanded before it is assigned
+    pub value: Word,
+}
+
+/// A redirection of the file descriptor `fd`
+///
+/// The redirections of a command are applied in the order in which they were given,
+/// so `>file 2>&1` redirects both `stdout` and `stderr` to `file`,
+/// while `2>&1 >file` redirects `stderr` to the original `stdout`, and only `stdout` to `file`.
+///
+/// In case of multiple redirections of the same file descriptor to files, all target files are created,
+/// but only the last one is used.
+///
+/// # References
+/// - [Redirection](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_07)
+/// - [Redirections @ Bash Reference Manual](https://www.gnu.org/software/bash/manual/bash.html#Redirections)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Redirection {
+    /// The redirected file descriptor
+    pub fd: i32,
+    /// The redirection operator
+    pub op: RedirectionOp,
+    /// The target path, the file descriptor to duplicate, or `-` for closing,
+    /// the input itself, in case of [`RedirectionOp::HereString`],
+    /// or the delimiter, in case of [`RedirectionOp::HereDocument`]
+    pub target: Word,
+    /// The body of a here-document; `None` for the other redirections
+    pub body: Option<Word>,
+}
+
+/// A word, which is a command name, an argument, or a redirection target
+///
+/// A word consists of parts, which keep the information about quoting and expansions.
+/// For example, `a'b c'$?` consists of a literal, a single-quoted part, and a parameter expansion.
+///
+/// # References
+/// - [Quoting](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_02)
+/// - [Word Expansions](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Word {
+    /// The parts of the word, in order
+    pub parts: Vec<WordPart>,
+}
+
+/// A part of a [`Word`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum WordPart {
+    /// Literal text; it is unquoted, unless it is a part of [`WordPart::DoubleQuoted`]
+    Literal(String),
+    /// A character quoted by a backslash, `\`
+    Escaped(char),
+    /// The text enclosed in single quotes, `'`
+    SingleQuoted(String),
+    /// The parts enclosed in double quotes, `"`
+    DoubleQuoted(Vec<WordPart>),
+    /// A parameter expansion, e.g., `$?` or `${HOME:-/}`
+    Parameter(ParameterExpansion),
+    /// A command substitution, `$(program)` or `` `program` ``, which is replaced by the output of the `program`
+    ///
+    /// The `program` is kept as source text, and it is parsed again when it is run.
+    /// In case of the `backquoted` form, the backslashes that quoted `` ` ``, `$` and `\` have already been removed.
+    ///
+    /// # References
+    /// - [Command Substitution](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_03)
+    CommandSubstitution { program: String, backquoted: bool },
+    /// An arithmetic expansion, `$((expression))`, which is replaced by the value of the `expression`
+    ///
+    /// The expression is a word, because its parameter expansions and command substitutions
+    /// are performed before it is evaluated.
+    ///
+    /// # References
+    /// - [Arithmetic Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_04)
+    Arithmetic(Word),
+}
+
+/// A parameter expansion, which is introduced by `$`
+///
+/// The parameter is either a variable, a positional parameter, e.g., `$1`, or a special parameter, e.g., `$?`.
+/// The name can be enclosed in braces, e.g., `${HOME}`, which is required for the modifiers,
+/// and for positional parameters with more than one digit, e.g., `${10}`.
+///
+/// # References
+/// - [Parameter Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_02)
+/// - [Shell Parameter Expansion @ Bash Reference Manual](https://www.gnu.org/software/bash/manual/bash.html#Shell-Parameter-Expansion)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterExpansion {
+    /// The name of the parameter
+    pub name: String,
+    /// What is done with the value of the parameter
+    pub op: ParameterOp,
+    /// Whether the name was enclosed in braces
+    pub braced: bool,
+}
+
+/// The modifier of a [`ParameterExpansion`]
+///
+/// With `check_null`, which is the case when the operator is preceded by a colon, e.g., `${x:-word}`,
+/// a parameter that is set, but null, is treated as if it were unset.
+///
+/// The `word` of a modifier is expanded only if it is used.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterOp {
+    /// `$parameter` or `${parameter}`: the value of the parameter
+    Value,
+    /// `${#parameter}`: the length of the value of the parameter, in characters
+    Length,
+    /// `${parameter:-word}`: the value of the parameter, or the expanded `word` if it is unset
+    UseDefault { check_null: bool, word: Word },
+    /// `${parameter:=word}`: the value of the parameter, or the expanded `word` if it is unset,
+    /// in which case the `word` is also assigned to the parameter
+    AssignDefault { check_null: bool, word: Word },
+    /// `${parameter:?word}`: the value of the parameter, or an error if it is unset,
+    /// with the expanded `word` as the error message
+    Error { check_null: bool, word: Word },
+    /// `${parameter:+word}`: nothing if the parameter is unset, or the expanded `word` otherwise
+    UseAlternative { check_null: bool, word: Word },
+    /// `${parameter%word}`: the value of the parameter without the smallest suffix that matches the pattern `word`
+    RemoveSmallestSuffix(Word),
+    /// `${parameter%%word}`: the value of the parameter without the largest suffix that matches the pattern `word`
+    RemoveLargestSuffix(Word),
+    /// `${parameter#word}`: the value of the parameter without the smallest prefix that matches the pattern `word`
+    RemoveSmallestPrefix(Word),
+    /// `${parameter##word}`: the value of the parameter without the largest prefix that matches the pattern `word`
+    RemoveLargestPrefix(Word),
+}
+
+impl Word {
+    /// Constructs a new unquoted word out of the literal `text`.
+    pub fn literal(text: &str) -> Self {
+        Self {
+            parts: vec![WordPart::Literal(text.to_string())],
+        }
+    }
+
+    /// Gets the text of the word if it consists of unquoted literal text only, e.g., a reserved word.
+    pub fn as_literal(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(text)] => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Checks whether any part of the word is quoted.
+    pub fn is_quoted(&self) -> bool {
+        self.parts.iter().any(|part| {
+            !matches!(
+                part,
+                WordPart::Literal(_)
+                    | WordPart::Parameter(_)
+                    | WordPart::CommandSubstitution { .. }
+                    | WordPart::Arithmetic(_)
+            )
+        })
+    }
+
+    /// Gets the text of the word after quote removal, without performing the expansions,
+    /// which are kept as they were written, e.g., the delimiter of a here-document.
+    pub fn unquoted_text(&self) -> String {
+        fn push_parts(text: &mut String, parts: &[WordPart]) {
+            for part in parts {
+                match part {
+                    WordPart::Literal(literal) | WordPart::SingleQuoted(literal) => {
+                        text.push_str(literal)
+                    }
+                    WordPart::Escaped(ch) => text.push(*ch),
+                    WordPart::DoubleQuoted(parts) => push_parts(text, parts),
+                    WordPart::Parameter(_)
+                    | WordPart::CommandSubstitution { .. }
+                    | WordPart::Arithmetic(_) => text.push_str(&part.to_string()),
+                }
+            }
+        }
+
+        let mut text = String::new();
+        push_parts(&mut text, &self.parts);
+        text
+    }
+}
+
+impl Display for WordPart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Literal(text) => write!(f, "{text}"),
+            Self::Escaped(ch) => write!(f, "\\{ch}"),
+            Self::SingleQuoted(text) => write!(f, "'{text}'"),
+            Self::DoubleQuoted(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    write!(f, "{part}")?;
+                }
+                write!(f, "\"")
+            }
+            Self::Parameter(expansion) => write!(f, "{expansion}"),
+            Self::CommandSubstitution {
+                program,
+                backquoted: false,
+            } => write!(f, "$({program})"),
+            Self::CommandSubstitution {
+                program,
+                backquoted: true,
+            } => write!(f, "`{program}`"),
+            Self::Arithmetic(expression) => write!(f, "$(({expression}))"),
+        }
+    }
+}
+
+impl Display for ParameterExpansion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = &self.name;
+        let (check_null, op, word) = match &self.op {
+            ParameterOp::Value if !self.braced => return write!(f, "${name}"),
+            ParameterOp::Value => return write!(f, "${{{name}}}"),
+            ParameterOp::Length => return write!(f, "${{#{name}}}"),
+            ParameterOp::UseDefault { check_null, word } => (*check_null, "-", word),
+            ParameterOp::AssignDefault { check_null, word } => (*check_null, "=", word),
+            ParameterOp::Error { check_null, word } => (*check_null, "?", word),
+            ParameterOp::UseAlternative { check_null, word } => (*check_null, "+", word),
+            ParameterOp::RemoveSmallestSuffix(word) => (false, "%", word),
+            ParameterOp::RemoveLargestSuffix(word) => (false, "%%", word),
+            ParameterOp::RemoveSmallestPrefix(word) => (false, "#", word),
+            ParameterOp::RemoveLargestPrefix(word) => (false, "##", word),
+        };
+        let colon = if check_null { ":" } else { "" };
+
+        write!(f, "${{{name}{colon}{op}{word}}}")
+    }
+}
+
+impl Display for Word {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for part in &self.parts {
+            write!(f, "{part}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for RedirectionOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Self::Input => "<",
+            Self::Output => ">",
+            Self::Append => ">>",
+            Self::ReadWrite => "<>",
+            Self::DupInput => "<&",
+            Self::DupOutput => ">&",
+            Self::OutputAll => "&>",
+            Self::AppendAll => "&>>",
+            Self::HereString => "<<<",
+            Self::HereDocument { strip_tabs: false } => "<<",
+            Self::HereDocument { strip_tabs: true } => "<<-",
+        };
+
+        write!(f, "{op}")
+    }
+}
+
+impl Display for Redirection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.fd != self.op.default_fd() {
+            write!(f, "{}", self.fd)?;
+        }
+
+        write!(f, "{}{}", self.op, self.target)
+    }
+}
+
+impl Display for SimpleCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let assignments = self.assignments.iter().map(|assign| assign.to_string());
+        let words = self.words.iter().map(|word| word.to_string());
+        let redirections = self.redirections.iter().map(|redir| redir.to_string());
+
+        write!(
+            f,
+            "{}",
+            assignments
+                .chain(words)
+                .chain(redirections)
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    }
+}
+
+impl Display for Assignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Simple(cmd) => write!(f, "{cmd}"),
+            Self::Compound {
+                command,
+                redirections,
+            } => {
+                write!(f, "{command}")?;
+                for redirection in redirections {
+                    write!(f, " {redirection}")?;
+                }
+
+                Ok(())
+            }
+            Self::Function(function) => write!(f, "{function}"),
+        }
+    }
+}
+
+impl Display for FunctionDefinition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}() {}", self.name, self.body)?;
+        for redirection in &self.redirections {
+            write!(f, " {redirection}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for CompoundCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::If(clause) => write!(f, "{clause}"),
+            Self::For(clause) => write!(f, "{clause}"),
+            Self::While(clause) => write!(f, "{clause}"),
+            Self::Case(clause) => write!(f, "{clause}"),
+            Self::Subshell(list) => write!(f, "({})", format_list(list).trim_end_matches(';')),
+            Self::BraceGroup(list) => write!(f, "{{ {} }}", format_list(list)),
+        }
+    }
+}
+
+impl Display for IfClause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, (condition, body)) in self.branches.iter().enumerate() {
+            let keyword = if idx == 0 { "if" } else { "elif" };
+            write!(
+                f,
+                "{keyword} {} then {} ",
+                format_list(condition),
+                format_list(body)
+            )?;
+        }
+        if let Some(body) = &self.else_body {
+            write!(f, "else {} ", format_list(body))?;
+        }
+
+        write!(f, "fi")
+    }
+}
+
+impl Display for ForClause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "for {}", self.name)?;
+        if let Some(words) = &self.words {
+            write!(f, " in")?;
+            for word in words {
+                write!(f, " {word}")?;
+            }
+        }
+
+        write!(f, "; do {} done", format_list(&self.body))
+    }
+}
+
+impl Display for CaseClause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "case {} in ", self.word)?;
+        for item in &self.items {
+            let patterns = item.patterns.iter().map(|pattern| pattern.to_string());
+            write!(f, "{})", patterns.collect::<Vec<_>>().join(" | "))?;
+            match item.body.is_empty() {
+                true => write!(f, " ;; ")?,
+                false => write!(f, " {}; ", format_list(&item.body))?,
+            }
+        }
+
+        write!(f, "esac")
+    }
+}
+
+impl Display for WhileClause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let keyword = if self.until { "until" } else { "while" };
+        write!(
+            f,
+            "{keyword} {} do {} done",
+            format_list(&self.condition),
+            format_list(&self.body)
+        )
+    }
+}
+
+/// Formats the commands of the pipeline separated by `|`, which is how jobs are shown to the user.
+impl Display for Pipeline {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let commands = self.commands.iter().map(|cmd| cmd.to_string());
+
+        if self.negated {
+            write!(f, "! ")?;
+        }
+        write!(f, "{}", commands.collect::<Vec<_>>().join(" | "))
+    }
+}
+
+/// Formats the AND-OR lists of the `list` on a single line, each terminated by `;` or `&`,
+/// e.g., the body of a compound command.
+fn format_list(list: &List) -> String {
+    list.iter()
+        .map(|and_or_list| match and_or_list.background {
+            true => format!("{and_or_list} &"),
+            false => format!("{and_or_list};"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Display for AndOrList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.first)?;
+        for (op, pipeline) in &self.rest {
+            match op {
+                AndOrOp::And => write!(f, " && ")?,
+                AndOrOp::Or => write!(f, " || ")?,
+            }
+            write!(f, "{pipeline}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,28 +1,45 @@
 //! Command handlers
 
-use crate::constants::{Args, COMMANDS};
+use crate::constants::{
+    Args, COMMANDS, FAILED_FLUSH_TO_STDOUT, FAILED_WRITE_TO_STDOUT, SPECIAL_BUILTINS,
+    STATUS_NOT_EXECUTABLE, STATUS_NOT_FOUND, STATUS_USAGE,
+};
+use crate::jobs::{JobFormat, wait_for_job};
+use crate::shell::{Jump, Shell};
+use crate::vars::{Variable, Variables, is_name};
 use std::env;
 use std::fmt::{Display, Formatter};
-use std::path::PathBuf;
+use std::io::{self, ErrorKind, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// The output of a command
 ///
-/// Contains fields `stdout` and `stderr` that hold the respective output data.
-#[derive(Debug)]
+/// Contains fields `stdout` and `stderr` that hold the respective output data,
+/// and the exit status of the command.
+///
+/// Builtins produce their output in this form. External programs write their output directly
+/// to the standard streams they inherit from the shell, so for them, this only holds
+/// the shell's own error messages.
+#[derive(Debug, Default)]
 pub struct Output {
     /// The data that the command wrote to `stdout`
     stdout: Vec<u8>,
     /// The data that the command wrote to `stderr`
     stderr: Vec<u8>,
+    /// The exit status of the command; zero means success
+    status: i32,
 }
 
 impl Output {
-    /// Constructs a new instance, filling the `stdout` and `stderr` fields.
-    fn new(stdout: &[u8], stderr: &[u8]) -> Self {
+    /// Constructs a new instance, filling the `stdout`, `stderr` and `status` fields.
+    pub fn new(stdout: &[u8], stderr: &[u8], status: i32) -> Self {
         Self {
             stdout: stdout.to_owned(),
             stderr: stderr.to_owned(),
+            status,
         }
     }
 
@@ -30,137 +47,666 @@ impl Output {
     pub fn get(self) -> (Vec<u8>, Vec<u8>) {
         (self.stdout, self.stderr)
     }
+
+    /// Gets the exit status of the command.
+    pub fn status(&self) -> i32 {
+        self.status
+    }
 }
 
 impl Display for Output {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Output {{ stdout: {:?}, stderr: {:?} }}",
+            "Output {{ stdout: {:?}, stderr: {:?}, status: {} }}",
             String::from_utf8_lossy(&self.stdout),
-            String::from_utf8_lossy(&self.stderr)
+            String::from_utf8_lossy(&self.stderr),
+            self.status
         )
     }
 }
 
+/// Handler for the `bg` builtin
+///
+/// Resumes the stopped jobs identified by the job IDs in the background, as if they had been started with `&`.
+/// Without an argument, the current job is resumed.
+///
+/// # References
+/// - [bg](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/bg.html)
+pub fn handle_bg(shell: &mut Shell, args: Args) -> Output {
+    if shell.job_control.is_none() {
+        return Output::new(b"", b"bg: no job control\n", 1);
+    }
+
+    let specs = match args.is_empty() {
+        true => vec![None],
+        false => args.iter().map(Some).collect(),
+    };
+
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let mut status = 0;
+
+    for spec in specs {
+        let id = match find_job(shell, spec) {
+            Ok(id) => id,
+            Err(err) => {
+                stderr.push_str(&format!("bg: {err}\n"));
+                status = 1;
+                continue;
+            }
+        };
+        let Some(job) = shell.jobs.get_mut(id) else {
+            continue;
+        };
+
+        if job.is_done() {
+            stderr.push_str("bg: job has terminated\n");
+            status = 1;
+        } else if !job.is_stopped() {
+            stderr.push_str(&format!("bg: job {id} already in background\n"));
+        } else {
+            job.resume();
+            let command = job.command().to_string();
+            shell.jobs.touch(id);
+            stdout.push_str(&format!("[{id}]+ {command} &\n"));
+        }
+    }
+
+    Output::new(stdout.as_bytes(), stderr.as_bytes(), status)
+}
+
+/// Handler for the `break` builtin
+///
+/// Exits from the `n`th enclosing `for`, `while` or `until` loop, where `n` is the argument, one by default.
+///
+/// # References
+/// - [break](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#break)
+pub fn handle_break(shell: &mut Shell, args: Args) -> Output {
+    jump(shell, "break", args, Jump::Break)
+}
+
 /// Handler for the `cd` builtin
-pub fn handle_cd(arg: Args) -> Output {
+///
+/// Sets the variables `PWD` and `OLDPWD`, which the tilde prefixes `~+` and `~-` expand to.
+pub fn handle_cd(shell: &mut Shell, arg: Args) -> Output {
     if !arg.is_empty() {
         let arg = &arg[0];
-        let home = match env::var("HOME") {
-            Ok(val) => val,
-            Err(_) => {
-                return Output::new(b"", b"HOME not found\n");
-            }
-        };
-        let arg = if arg.eq(&"~") { home.as_str() } else { arg };
+        let old_dir = env::current_dir();
 
         if env::set_current_dir(arg).is_err() {
             return Output::new(
                 b"",
                 format!("cd: {arg}: No such file or directory\n").as_bytes(),
+                1,
             );
         }
+
+        // Like other shells, `cd` changes the directory even if `PWD` or `OLDPWD` is read-only
+        if let Ok(old_dir) = old_dir {
+            let _ = shell.variables.set("OLDPWD", &old_dir.to_string_lossy());
+        }
+        if let Ok(dir) = env::current_dir() {
+            let _ = shell.variables.set("PWD", &dir.to_string_lossy());
+        }
     };
 
-    Output::new(b"", b"")
+    Output::new(b"", b"", 0)
+}
+
+/// Handler for the `continue` builtin
+///
+/// Continues with the next iteration of the `n`th enclosing `for`, `while` or `until` loop,
+/// where `n` is the argument, one by default.
+///
+/// # References
+/// - [continue](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#continue)
+pub fn handle_continue(shell: &mut Shell, args: Args) -> Output {
+    jump(shell, "continue", args, Jump::Continue)
 }
 
 /// Handler for the `echo` builtin
-pub fn handle_echo(args: Args) -> Output {
-    Output::new(format!("{}\n", args.join(" ")).as_ref(), b"")
+pub fn handle_echo(_shell: &mut Shell, args: Args) -> Output {
+    Output::new(format!("{}\n", args.join(" ")).as_ref(), b"", 0)
 }
 
 /// Handler for the `exit` builtin
-pub fn handle_exit(arg: Args) -> Output {
+///
+/// Without an argument, the shell exits with the exit status of the last command.
+pub fn handle_exit(shell: &mut Shell, arg: Args) -> Output {
     match arg.is_empty() {
         false => {
             let arg = &arg[0];
             match arg.trim().parse::<i32>() {
                 Ok(exit_code) => std::process::exit(exit_code),
-                Err(_) => Output::new(b"", format!("Invalid exit code: {arg}\n").as_bytes()),
+                Err(_) => Output::new(
+                    b"",
+                    format!("Invalid exit code: {arg}\n").as_bytes(),
+                    STATUS_USAGE,
+                ),
+            }
+        }
+        true => std::process::exit(shell.last_status),
+    }
+}
+
+/// Handler for the `export` builtin
+///
+/// Marks the variables as exported, so that they are passed in the environment of the commands
+/// that the shell runs, and assigns them first in case of the operands of the form `name=value`.
+/// Without an operand, or with the option `-p`, prints the exported variables in a form
+/// that can be read back by the shell.
+///
+/// # References
+/// - [export](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#export)
+pub fn handle_export(shell: &mut Shell, args: Args) -> Output {
+    declare(shell, "export", args, Variables::export, |var| var.exported)
+}
+
+/// Handler for the `fg` builtin
+///
+/// Brings the job identified by the job ID to the foreground, resuming it if it is stopped,
+/// and waits for it. Without an argument, the current job is brought to the foreground.
+///
+/// The command of the job is printed before waiting for it.
+///
+/// # References
+/// - [fg](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/fg.html)
+pub fn handle_fg(shell: &mut Shell, args: Args) -> Output {
+    if shell.job_control.is_none() {
+        return Output::new(b"", b"fg: no job control\n", 1);
+    }
+
+    let id = match find_job(shell, args.first()) {
+        Ok(id) => id,
+        Err(err) => return Output::new(b"", format!("fg: {err}\n").as_bytes(), 1),
+    };
+    let Some(mut job) = shell.jobs.remove(id) else {
+        return Output::new(b"", b"fg: no such job\n", 1);
+    };
+
+    if job.is_done() {
+        return Output::new(b"", b"fg: job has terminated\n", 1);
+    }
+
+    // The job may take over the terminal, so its command must be shown before that.
+    let mut stdout = io::stdout();
+    writeln!(stdout, "{}", job.command()).expect(FAILED_WRITE_TO_STDOUT);
+    stdout.flush().expect(FAILED_FLUSH_TO_STDOUT);
+
+    job.resume();
+    let status = wait_for_job(shell, job);
+
+    Output::new(b"", b"", status)
+}
+
+/// Handler for the `jobs` builtin
+///
+/// Reports the jobs identified by the job IDs, or all jobs without an argument.
+///
+/// Supports the options `-l`, which adds the process group IDs to the report,
+/// and `-p`, which reports only the process group IDs.
+///
+/// The jobs that have terminated are removed from the job table after they are reported.
+///
+/// # References
+/// - [jobs](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/jobs.html)
+pub fn handle_jobs(shell: &mut Shell, args: Args) -> Output {
+    let mut format = JobFormat::Short;
+    let mut operands = args;
+    while let Some((&option, rest)) = operands.split_first() {
+        match option {
+            "-l" => format = JobFormat::Long,
+            "-p" => format = JobFormat::Pid,
+            "--" => {
+                operands = rest;
+                break;
             }
+            _ if option.starts_with('-') && option.len() > 1 => {
+                return Output::new(
+                    b"",
+                    format!(
+                        "jobs: {option}: invalid option\njobs: usage: jobs [-lp] [job_id...]\n"
+                    )
+                    .as_bytes(),
+                    STATUS_USAGE,
+                );
+            }
+            _ => break,
+        }
+        operands = rest;
+    }
+
+    shell.jobs.update();
+
+    let mut stderr = String::new();
+    let mut status = 0;
+    let ids = match operands.is_empty() {
+        true => shell.jobs.ids(),
+        false => operands
+            .iter()
+            .filter_map(|spec| match shell.jobs.find(spec) {
+                Ok(id) => Some(id),
+                Err(err) => {
+                    stderr.push_str(&format!("jobs: {err}\n"));
+                    status = 1;
+                    None
+                }
+            })
+            .collect(),
+    };
+
+    let stdout = shell.jobs.report_all(&ids, format);
+
+    Output::new(stdout.as_bytes(), stderr.as_bytes(), status)
+}
+
+/// Handler for the `local` builtin
+///
+/// Makes the variables local to the function that is running, so that they are restored when it returns,
+/// and assigns them in case of the operands of the form `name=value`. Otherwise, the variables are unset
+/// until they are assigned.
+pub fn handle_local(shell: &mut Shell, args: Args) -> Output {
+    if shell.function_depth == 0 {
+        return Output::new(b"", b"local: can only be used in a function\n", 1);
+    }
+
+    let mut stderr = String::new();
+    let mut status = 0;
+    for &operand in args {
+        let (name, value) = match operand.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (operand, None),
+        };
+        if !is_name(name) {
+            stderr.push_str(&format!("local: `{operand}': not a valid identifier\n"));
+            status = 1;
+            continue;
+        }
+
+        shell.variables.make_local(name);
+        let result = match value {
+            Some(value) => shell.variables.set(name, value),
+            None => shell.variables.unset(name),
+        };
+        if let Err(err) = result {
+            stderr.push_str(&format!("local: {err}\n"));
+            status = 1;
         }
-        true => std::process::exit(0),
     }
+
+    Output::new(b"", stderr.as_bytes(), status)
 }
 
 /// Handler for the `pwd` builtin
-pub fn handle_pwd(_arg: Args) -> Output {
+pub fn handle_pwd(_shell: &mut Shell, _arg: Args) -> Output {
     match env::current_dir() {
-        Ok(pwd) => Output::new(format!("{}\n", pwd.display()).as_bytes(), b""),
-        Err(err) => Output::new(b"", format!("{}\n", err).as_bytes()),
+        Ok(pwd) => Output::new(format!("{}\n", pwd.display()).as_bytes(), b"", 0),
+        Err(err) => Output::new(b"", format!("{}\n", err).as_bytes(), 1),
+    }
+}
+
+/// Handler for the `readonly` builtin
+///
+/// Marks the variables as read-only, so that they can't be assigned or unset anymore,
+/// and assigns them first in case of the operands of the form `name=value`.
+/// Without an operand, or with the option `-p`, prints the read-only variables in a form
+/// that can be read back by the shell.
+///
+/// # References
+/// - [readonly](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#readonly)
+pub fn handle_readonly(shell: &mut Shell, args: Args) -> Output {
+    declare(shell, "readonly", args, Variables::set_readonly, |var| {
+        var.readonly
+    })
+}
+
+/// Handler for the `return` builtin
+///
+/// Returns from the function that is running, with the argument as its exit status, modulo 256.
+/// Without an argument, the exit status is that of the last command.
+///
+/// # References
+/// - [return](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#return)
+pub fn handle_return(shell: &mut Shell, args: Args) -> Output {
+    if shell.function_depth == 0 {
+        return Output::new(b"", b"return: can only `return' from a function\n", 1);
     }
+
+    let status = match args {
+        [] => shell.last_status,
+        [arg] => match arg.parse::<i32>() {
+            Ok(status) => status & 0xff,
+            Err(_) => {
+                return Output::new(
+                    b"",
+                    format!("return: {arg}: numeric argument required\n").as_bytes(),
+                    STATUS_USAGE,
+                );
+            }
+        },
+        _ => return Output::new(b"", b"return: too many arguments\n", 1),
+    };
+
+    shell.jump = Some(Jump::Return(status));
+    Output::new(b"", b"", status)
 }
 
 /// Handler for the `type` builtin
 ///
-/// Searches for executable files using the `PATH` environment variable.
+/// Searches for executable files using the `PATH` variable, unless the name is a function or a builtin.
 ///
 /// Some commands, such as `echo`, can exist as both builtin commands and executable files.
 /// In such cases, the type command identifies them as builtins.
-pub fn handle_type(arg: Args) -> Output {
+pub fn handle_type(shell: &mut Shell, arg: Args) -> Output {
     let mut result = "\n".to_string();
 
     if !arg.is_empty() {
         let arg = arg[0];
-        if COMMANDS.contains(&arg) {
+        if !SPECIAL_BUILTINS.contains(&arg) && shell.functions.contains_key(arg) {
+            result = format!("{arg} is a function\n");
+        } else if COMMANDS.contains(&arg) {
             result = format!("{arg} is a shell builtin\n");
         } else {
-            let paths = get_paths();
-
-            for path in paths {
-                if path.join(arg).exists() {
-                    result = format!("{arg} is {}\n", path.join(arg).display());
-                    return Output::new(result.as_bytes(), b"");
-                }
+            if let Some(path) = find_program(shell, arg).filter(|path| is_executable(path)) {
+                result = format!("{arg} is {}\n", path.display());
+                return Output::new(result.as_bytes(), b"", 0);
             }
 
-            return Output::new(b"", format!("{arg}: not found\n").as_bytes());
+            return Output::new(b"", format!("{arg}: not found\n").as_bytes(), 1);
         }
     };
 
-    Output::new(result.as_bytes(), b"")
+    Output::new(result.as_bytes(), b"", 0)
 }
 
-/// Runs external programs with arguments
+/// Handler for the `unset` builtin
 ///
-/// External programs are located using the `PATH` environment variable.
-pub fn run_program(exec: &str, args: Args) -> Output {
-    // eprintln!("******* run_program() !!!!!!!"); // TODO remove
-    let paths = get_paths();
+/// Unsets the variables, which removes their attributes as well. Read-only variables can't be unset.
+/// The option `-v` is accepted, and it has no effect.
+///
+/// # References
+/// - [unset](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#unset)
+pub fn handle_unset(shell: &mut Shell, args: Args) -> Output {
+    let operands = match args.first() {
+        Some(&"-v" | &"--") => &args[1..],
+        Some(option) if option.starts_with('-') && option.len() > 1 => {
+            return Output::new(
+                b"",
+                format!("unset: {option}: invalid option\nunset: usage: unset [-v] name...\n")
+                    .as_bytes(),
+                STATUS_USAGE,
+            );
+        }
+        _ => args,
+    };
 
-    for path in paths {
-        if path.join(exec).exists() {
-            let output = match Command::new(exec).args(args).output() {
-                Ok(output) => output,
-                Err(err) => {
-                    return Output::new(
-                        b"",
-                        format!(
-                            "{err}: failed to execute command `{} {}'\n",
-                            exec,
-                            args.join(" ")
-                        )
-                        .as_ref(),
-                    );
+    let mut stderr = String::new();
+    let mut status = 0;
+    for &name in operands {
+        let result = match is_name(name) {
+            true => shell.variables.unset(name),
+            false => Err(format!("`{name}': not a valid identifier")),
+        };
+        if let Err(err) = result {
+            stderr.push_str(&format!("unset: {err}\n"));
+            status = 1;
+        }
+    }
+
+    Output::new(b"", stderr.as_bytes(), status)
+}
+
+/// Handler for the `wait` builtin
+///
+/// Waits for the processes identified by the process IDs, or for the jobs identified by the job IDs,
+/// to terminate, and returns the exit status of the last one.
+/// Without an argument, it waits for all jobs to terminate, and returns zero.
+///
+/// The exit status is [`STATUS_NOT_FOUND`] if a process or job is unknown to the shell.
+///
+/// # References
+/// - [wait](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/wait.html)
+pub fn handle_wait(shell: &mut Shell, args: Args) -> Output {
+    if args.is_empty() {
+        for id in shell.jobs.ids() {
+            wait_for_background_job(shell, id, None);
+        }
+        return Output::new(b"", b"", 0);
+    }
+
+    let mut stderr = String::new();
+    let mut status = 0;
+
+    for &operand in args {
+        let pid = operand.parse::<libc::pid_t>().ok().filter(|&pid| pid > 0);
+        let found = match pid {
+            Some(pid) => shell
+                .jobs
+                .find_pid(pid)
+                .ok_or(format!("pid {pid} is not a child of this shell")),
+            None if operand.starts_with('%') => shell.jobs.find(operand),
+            None => {
+                stderr.push_str(&format!("wait: `{operand}': not a pid or valid job spec\n"));
+                status = STATUS_USAGE;
+                continue;
+            }
+        };
+
+        status = match found {
+            Ok(id) => wait_for_background_job(shell, id, pid),
+            Err(err) => {
+                stderr.push_str(&format!("wait: {err}\n"));
+                STATUS_NOT_FOUND
+            }
+        };
+    }
+
+    Output::new(b"", stderr.as_bytes(), status)
+}
+
+/// Waits for the job with the job number `id`, which stays in the background, to terminate or to stop.
+///
+/// A job that terminates is removed from the job table, so the user isn't notified about it.
+///
+/// Returns the exit status of the process with the given `pid`, or of the job if there is no `pid`.
+fn wait_for_background_job(shell: &mut Shell, id: usize, pid: Option<libc::pid_t>) -> i32 {
+    let Some(job) = shell.jobs.get_mut(id) else {
+        return STATUS_NOT_FOUND;
+    };
+
+    job.wait(libc::WUNTRACED);
+    let status = match pid {
+        Some(pid) => job.process_status(pid).unwrap_or(STATUS_NOT_FOUND),
+        None => job.status(),
+    };
+    if job.is_done() {
+        shell.jobs.remove(id);
+    }
+
+    status
+}
+
+/// Shared implementation of the `break` and `continue` builtins, named `builtin`, which make the loops
+/// stop running commands by setting the pending `jump` of the shell.
+///
+/// A count greater than the number of enclosing loops applies to the outermost loop.
+fn jump(shell: &mut Shell, builtin: &str, args: Args, jump: fn(usize) -> Jump) -> Output {
+    let count = match args {
+        [] => 1,
+        [arg] => match arg.parse::<usize>() {
+            Ok(count) if count > 0 => count,
+            _ => {
+                return Output::new(
+                    b"",
+                    format!("{builtin}: {arg}: loop count out of range\n").as_bytes(),
+                    1,
+                );
+            }
+        },
+        _ => {
+            return Output::new(
+                b"",
+                format!("{builtin}: too many arguments\n").as_bytes(),
+                1,
+            );
+        }
+    };
+
+    if shell.loop_depth == 0 {
+        return Output::new(
+            b"",
+            format!("{builtin}: only meaningful in a `for', `while', or `until' loop\n").as_bytes(),
+            0,
+        );
+    }
+
+    shell.jump = Some(jump(count.min(shell.loop_depth)));
+    Output::new(b"", b"", 0)
+}
+
+/// Shared implementation of the `export` and `readonly` builtins, named `builtin`, which `mark` the variables
+/// with an attribute, and which print the variables that are `marked` with it.
+fn declare(
+    shell: &mut Shell,
+    builtin: &str,
+    args: Args,
+    mark: fn(&mut Variables, &str),
+    marked: fn(&Variable) -> bool,
+) -> Output {
+    let (print, operands) = match args.split_first() {
+        Some((&"-p", rest)) => (rest.is_empty(), rest),
+        Some((&"--", rest)) => (false, rest),
+        Some((option, _)) if option.starts_with('-') && option.len() > 1 => {
+            return Output::new(
+                b"",
+                format!(
+                    "{builtin}: {option}: invalid option\n{builtin}: usage: {builtin} [-p] [name[=value]...]\n"
+                )
+                .as_bytes(),
+                STATUS_USAGE,
+            );
+        }
+        _ => (args.is_empty(), args),
+    };
+
+    if print {
+        let mut stdout = String::new();
+        for (name, var) in shell.variables.sorted() {
+            match &var.value {
+                Some(value) if marked(var) => {
+                    stdout.push_str(&format!("{builtin} {name}={}\n", quote(value)))
                 }
-            };
-            return Output::new(&output.stdout, &output.stderr);
+                None if marked(var) => stdout.push_str(&format!("{builtin} {name}\n")),
+                _ => {}
+            }
         }
+        return Output::new(stdout.as_bytes(), b"", 0);
+    }
+
+    let mut stderr = String::new();
+    let mut status = 0;
+    for &operand in operands {
+        let (name, value) = match operand.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (operand, None),
+        };
+        let result = match (is_name(name), value) {
+            (false, _) => Err(format!("`{operand}': not a valid identifier")),
+            (true, Some(value)) => shell.variables.set(name, value),
+            (true, None) => Ok(()),
+        };
+        match result {
+            Ok(()) => mark(&mut shell.variables, name),
+            Err(err) => {
+                stderr.push_str(&format!("{builtin}: {err}\n"));
+                status = 1;
+            }
+        }
+    }
+
+    Output::new(b"", stderr.as_bytes(), status)
+}
+
+/// Quotes the `value` with single quotes, so that it can be read back by the shell.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Runs external programs with arguments, by replacing the current process with them
+///
+/// This is only ever done in a child process of the shell, which has been forked for running the program.
+///
+/// External programs are located using the `PATH` variable,
+/// unless the program name contains a slash, in which case it is used as a path directly.
+/// The environment of the program consists of the exported variables of the `shell`.
+///
+/// The program inherits the standard streams of the process, which may have been redirected,
+/// so its output isn't buffered, but goes directly to the terminal or to the redirection targets.
+/// This also makes interactive programs work.
+///
+/// A file that isn't in an executable format, e.g., a script without a `#!` line, is run as a script
+/// by the current shell binary instead, unless the C library has already run it with `/bin/sh`.
+///
+/// Returns only if the program can't be run, in which case the returned [`Output`]
+/// contains the shell's error message and the exit status, which is [`STATUS_NOT_FOUND`]
+/// if the program can't be found, and [`STATUS_NOT_EXECUTABLE`] if it was found, but couldn't be executed.
+pub fn exec_program(shell: &Shell, exec: &str, args: Args) -> Output {
+    let program = if exec.contains('/') {
+        Some(PathBuf::from(exec)).filter(|path| path.exists())
+    } else {
+        find_program(shell, exec)
+    };
+
+    let Some(program) = program else {
+        return Output::new(
+            b"",
+            format!("{exec}: command not found\n").as_ref(),
+            STATUS_NOT_FOUND,
+        );
+    };
+
+    let mut err = Command::new(&program)
+        .arg0(exec)
+        .args(args)
+        .env_clear()
+        .envs(shell.variables.exported())
+        .exec();
+    if err.raw_os_error() == Some(libc::ENOEXEC)
+        && let Ok(shell_path) = env::current_exe()
+    {
+        err = Command::new(shell_path)
+            .arg(&program)
+            .args(args)
+            .env_clear()
+            .envs(shell.variables.exported())
+            .exec();
     }
+    let status = match err.kind() {
+        ErrorKind::NotFound => STATUS_NOT_FOUND,
+        _ => STATUS_NOT_EXECUTABLE,
+    };
 
-    Output::new(b"", format!("{exec}: command not found\n").as_ref())
+    Output::new(
+        b"",
+        format!(
+            "{err}: failed to execute command `{} {}'\n",
+            exec,
+            args.join(" ")
+        )
+        .as_ref(),
+        status,
+    )
 }
 
 /// A helper function which extracts directories from
-/// the [PATH](https://en.wikipedia.org/wiki/PATH_(variable)) environment variable.
-fn get_paths() -> Vec<PathBuf> {
+/// the [PATH](https://en.wikipedia.org/wiki/PATH_(variable)) variable of the `shell`.
+fn get_paths(shell: &Shell) -> Vec<PathBuf> {
     let key = "PATH";
 
-    let path = match env::var(key) {
-        Ok(val) => val,
-        Err(_) => {
+    let path = match shell.variables.get(key) {
+        Some(val) => val,
+        None => {
             eprintln!("{key} not found");
             return vec![];
         }
@@ -170,3 +716,47 @@ fn get_paths() -> Vec<PathBuf> {
 
     paths.collect()
 }
+
+/// Searches the directories of the `PATH` variable of the `shell` for the external program `name`,
+/// and returns the path of the first executable regular file with that name.
+///
+/// Other entries, such as directories, are skipped. If there is no executable file, the first regular file
+/// is returned, so that the failure to execute it can be reported, or `None` if there is no such file either.
+///
+/// # References
+/// - [Command Search and Execution](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_01_01)
+fn find_program(shell: &Shell, name: &str) -> Option<PathBuf> {
+    let files = get_paths(shell)
+        .into_iter()
+        .map(|path| path.join(name))
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+
+    match files.iter().find(|path| is_executable(path)) {
+        Some(program) => Some(program.clone()),
+        None => files.into_iter().next(),
+    }
+}
+
+/// Checks whether the file at `path` has any execute permission bit set.
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Finds the job identified by the job ID `spec`, or the current job if there is no `spec`,
+/// after updating the states of the jobs, and returns its job number.
+///
+/// # Errors
+/// - Returns the error message in case there is no such job.
+fn find_job(shell: &mut Shell, spec: Option<&&str>) -> Result<usize, String> {
+    shell.jobs.update();
+
+    match spec {
+        Some(spec) => shell.jobs.find(spec),
+        None => shell
+            .jobs
+            .current()
+            .ok_or_else(|| "current: no such job".to_string()),
+    }
+}
@@ -2,28 +2,78 @@
 //!
 //! Constants, global variables and types used throughout the application
 
-use crate::cmd::{handle_cd, handle_echo, handle_exit, handle_pwd, handle_type, Output};
+use crate::cmd::{
+    Output, handle_bg, handle_break, handle_cd, handle_continue, handle_echo, handle_exit,
+    handle_export, handle_fg, handle_jobs, handle_local, handle_pwd, handle_readonly,
+    handle_return, handle_type, handle_unset, handle_wait,
+};
+use crate::shell::Shell;
 use std::sync::OnceLock;
 
 /// Allows debug printouts
 pub static DEBUG: OnceLock<bool> = OnceLock::new();
 
-/// Used for end-to-end testing from within a shell script
-pub static TEST: OnceLock<bool> = OnceLock::new();
-
 /// Number of supported shell commands
-const NUM_CMDS: usize = 5;
+const NUM_CMDS: usize = 16;
 
 /// Supported Shell commands
-pub const COMMANDS: [&str; NUM_CMDS] = ["cd", "echo", "exit", "pwd", "type"];
+pub const COMMANDS: [&str; NUM_CMDS] = [
+    "bg", "break", "cd", "continue", "echo", "exit", "export", "fg", "jobs", "local", "pwd",
+    "readonly", "return", "type", "unset", "wait",
+];
 
 /// Supported Shell command handlers
-pub const HANDLERS: [Handler; NUM_CMDS] =
-    [handle_cd, handle_echo, handle_exit, handle_pwd, handle_type];
+pub const HANDLERS: [Handler; NUM_CMDS] = [
+    handle_bg,
+    handle_break,
+    handle_cd,
+    handle_continue,
+    handle_echo,
+    handle_exit,
+    handle_export,
+    handle_fg,
+    handle_jobs,
+    handle_local,
+    handle_pwd,
+    handle_readonly,
+    handle_return,
+    handle_type,
+    handle_unset,
+    handle_wait,
+];
+
+/// The special builtins, which differ from the other commands in that the variable assignments
+/// that precede them persist in the shell
+///
+/// # References
+/// - [Special Built-In Utilities](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_14)
+pub const SPECIAL_BUILTINS: &[&str] = &[
+    "break", "continue", "exit", "export", "readonly", "return", "unset",
+];
 
 /// The shell prompt
 pub const PROMPT: &[u8] = b"$ ";
 
+/// The shell prompt for continuation lines, e.g., for the body of a here-document
+pub const CONTINUATION_PROMPT: &[u8] = b"> ";
+
+/// The characters at which the results of expansions are split into fields if `IFS` is unset
+pub const DEFAULT_IFS: &str = " \t\n";
+
+/// Exit status of a builtin that was used incorrectly
+pub const STATUS_USAGE: i32 = 2;
+/// Exit status of a command that was found, but could not be executed
+pub const STATUS_NOT_EXECUTABLE: i32 = 126;
+/// Exit status of a command that was not found
+pub const STATUS_NOT_FOUND: i32 = 127;
+/// The exit status of a command terminated by a signal is this value plus the signal number.
+pub const STATUS_SIGNAL_BASE: i32 = 128;
+
+/// The highest file descriptor that can be redirected
+///
+/// The shell keeps its own file descriptors above it.
+pub const MAX_USER_FD: i32 = 9;
+
 /// Error message for invalid input
 pub const INVALID_INPUT_MSG: &str = "invalid input";
 
@@ -42,4 +92,4 @@ pub const FAILED_FLUSH_TO_STDERR: &str = "Flush to stderr failed";
 pub type Args<'a> = &'a [&'a str];
 
 /// Command-handlers' type
-pub type Handler = fn(Args) -> Output;
+pub type Handler = fn(&mut Shell, Args) -> Output;
@@ -21,9 +21,23 @@ use std::fmt::{Display, Formatter};
 /// - `echo test >>> file`
 /// - `echo test &>& file`
 /// - `echo test >&& file`
+///
+/// Input can also be incomplete, e.g., when the body of a here-document hasn't been entered yet,
+/// in which case the user should be prompted for more input.
 #[derive(Debug, PartialEq)]
 pub struct InvalidInputError {
     pub reason: String,
+    pub incomplete: bool,
+}
+
+impl InvalidInputError {
+    /// Constructs a new instance for incomplete input, with the given `reason`.
+    pub fn incomplete(reason: &str) -> Self {
+        Self {
+            reason: reason.to_string(),
+            incomplete: true,
+        }
+    }
 }
 
 impl Error for InvalidInputError {}
@@ -38,6 +52,7 @@ impl From<&str> for InvalidInputError {
     fn from(value: &str) -> Self {
         Self {
             reason: value.to_string(),
+            incomplete: false,
         }
     }
 }
@@ -69,3 +84,45 @@ impl From<String> for OutputError {
         Self { reason: value }
     }
 }
+
+/// Word expansion error
+///
+/// Contains the reason for the error.
+///
+/// # Examples
+/// - `echo ${unset:?}`
+/// - `echo ${1:=value}`
+///
+/// An error can also be fatal, e.g., `${unset:?}`, in which case a non-interactive shell exits.
+#[derive(Debug, PartialEq)]
+pub struct ExpansionError {
+    pub reason: String,
+    pub fatal: bool,
+}
+
+impl ExpansionError {
+    /// Constructs a new instance for a fatal error, with the given `reason`.
+    pub fn fatal(reason: String) -> Self {
+        Self {
+            reason,
+            fatal: true,
+        }
+    }
+}
+
+impl Error for ExpansionError {}
+
+impl Display for ExpansionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl From<String> for ExpansionError {
+    fn from(value: String) -> Self {
+        Self {
+            reason: value,
+            fatal: false,
+        }
+    }
+}
@@ -0,0 +1,1163 @@
+//! Execution of parsed user input
+//!
+//! Runs lists of pipelines of commands, which can be builtins or external programs,
+//! and handles their redirections.
+//!
+//! The words of a command are expanded right before the command is run.
+//!
+//! Redirections are applied directly to the file descriptors of the shell process,
+//! so that external programs inherit them and stream their output.
+//!
+//! # References
+//!
+//! - [Pipelines](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_02)
+//! - [Lists](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_03)
+//! - [Asynchronous Lists](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_03_02)
+//! - [Pipelines @ Bash Reference Manual](https://www.gnu.org/software/bash/manual/bash.html#Pipelines)
+
+use crate::ast::{
+    AndOrList, AndOrOp, Assignment, CaseClause, Command, CompoundCommand, ForClause,
+    FunctionDefinition, IfClause, List, Pipeline, Redirection, RedirectionOp, SimpleCommand,
+    WhileClause, Word, WordPart,
+};
+use crate::cmd::{Output, exec_program};
+use crate::constants::{
+    Args, COMMANDS, DEBUG, FAILED_FLUSH_TO_STDERR, FAILED_FLUSH_TO_STDOUT, FAILED_WRITE_TO_STDERR,
+    HANDLERS, Handler, MAX_USER_FD, SPECIAL_BUILTINS,
+};
+use crate::errors::{ExpansionError, OutputError};
+use crate::expand::{
+    expand_assignment, expand_here_document, expand_pattern, expand_word, expand_words,
+};
+use crate::jobs::{JOB_CONTROL_SIGNALS, Job, JobTable, wait_for_job};
+use crate::pattern;
+use crate::shell::{Jump, Shell};
+use crate::vars::Variable;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::iter::zip;
+use std::os::fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, mem, process};
+
+/// Runs a list of AND-OR lists sequentially.
+///
+/// AND-OR lists terminated by `&` are started in the background as jobs, and the shell doesn't wait for them.
+///
+/// # References
+/// - [Lists](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_03)
+pub fn run_list(shell: &mut Shell, list: List) {
+    for and_or_list in list {
+        if shell.jump.is_some() {
+            break;
+        }
+        match and_or_list.background {
+            true => run_in_background(shell, and_or_list),
+            false => run_and_or_list(shell, and_or_list),
+        }
+    }
+}
+
+/// Runs an AND-OR list.
+///
+/// The first pipeline is always run. Every following pipeline is run only if the exit status
+/// of the previously run pipeline is zero, in case of `&&`, or non-zero, in case of `||`.
+/// Otherwise, it is skipped, and the exit status stays the same.
+pub fn run_and_or_list(shell: &mut Shell, and_or_list: AndOrList) {
+    run_pipeline(shell, and_or_list.first);
+
+    for (op, pipeline) in and_or_list.rest {
+        if shell.jump.is_some() {
+            break;
+        }
+        let run = match op {
+            AndOrOp::And => shell.last_status == 0,
+            AndOrOp::Or => shell.last_status != 0,
+        };
+        if run {
+            run_pipeline(shell, pipeline);
+        }
+    }
+}
+
+/// Runs an AND-OR list asynchronously, as a background job.
+///
+/// A single pipeline is started the same way as in the foreground, except that the shell doesn't wait for it.
+/// Otherwise, the whole AND-OR list is run in a subshell.
+///
+/// An interactive shell prints the job number and the process ID of the job's last process, e.g., `[1] 12345`.
+/// The exit status is zero if the job was started.
+///
+/// # References
+/// - [Asynchronous Lists](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_03_02)
+fn run_in_background(shell: &mut Shell, and_or_list: AndOrList) {
+    let (job, started) = match and_or_list.rest.is_empty() {
+        true => start_pipeline(shell, and_or_list.first, false),
+        false => start_subshell(shell, and_or_list),
+    };
+
+    let pid = job.last_pid();
+    if let Some(pid) = pid {
+        let id = shell.jobs.insert(job);
+        if shell.interactive {
+            eprintln!("[{id}] {pid}");
+        }
+        shell.last_background_pid = Some(pid);
+    }
+
+    shell.last_status = if started { 0 } else { 1 };
+}
+
+/// Runs a pipeline in the foreground.
+///
+/// A pipeline consisting of a single command is run in the current shell process,
+/// which forks only for running an external program, so that the expansions affect the shell itself,
+/// e.g., `${x:=value}`.
+///
+/// Otherwise, every command of the pipeline is run in its own child process, so that all commands
+/// run concurrently, and the shell waits for all of them to complete, as a foreground job.
+/// Builtins can take part in a pipeline at any position.
+///
+/// The exit status of the pipeline is the exit status of its last command, which is inverted if the pipeline
+/// is negated with `!`.
+/// It is stored in the [`Shell`] state, as the value of the special parameter `$?`.
+pub fn run_pipeline(shell: &mut Shell, pipeline: Pipeline) {
+    let negated = pipeline.negated;
+
+    let status = if pipeline.commands.len() == 1 {
+        let cmd = pipeline
+            .commands
+            .into_iter()
+            .next()
+            .expect("Expected a command");
+        run_command(shell, cmd, false)
+    } else {
+        let (job, started) = start_pipeline(shell, pipeline, true);
+        let status = wait_for_job(shell, job);
+        if started { status } else { 1 }
+    };
+
+    shell.last_status = match negated {
+        true => (status == 0).into(),
+        false => status,
+    };
+}
+
+/// Starts every command of the pipeline in its own child process, and returns the resulting job,
+/// which is run in the `foreground` or in the background.
+///
+/// The standard output of each command is connected to the standard input of the next command through a pipe.
+///
+/// Also returns whether all commands were started.
+fn start_pipeline(shell: &mut Shell, pipeline: Pipeline, foreground: bool) -> (Job, bool) {
+    let mut job = Job::new(pipeline.to_string());
+    let last = pipeline.commands.len() - 1;
+    // The reading end of the pipe that connects the previous command to the current one
+    let mut prev_reader: Option<io::PipeReader> = None;
+
+    for (idx, cmd) in pipeline.commands.into_iter().enumerate() {
+        let pipe = if idx < last {
+            match io::pipe() {
+                Ok(pipe) => Some(pipe),
+                Err(err) => {
+                    eprintln!("{err}: Failed to create a pipe");
+                    return (job, false);
+                }
+            }
+        } else {
+            None
+        };
+
+        match fork_child(shell, job.pgid(), foreground) {
+            Err(err) => {
+                eprintln!("{err}: Failed to fork");
+                return (job, false);
+            }
+            Ok(0) => {
+                // The child process
+                if let Some(reader) = prev_reader.take() {
+                    redirect_fd(reader.as_raw_fd(), libc::STDIN_FILENO);
+                }
+                if let Some((reader, writer)) = pipe {
+                    redirect_fd(writer.as_raw_fd(), libc::STDOUT_FILENO);
+                    drop(reader);
+                }
+                let status = run_command(shell, cmd, true);
+                unsafe { libc::_exit(status) };
+            }
+            Ok(pid) => {
+                job.add_process(pid);
+                // The parent keeps only the reading end for the next command,
+                // and closes the writing end, so that the reader can receive EOF.
+                prev_reader = pipe.map(|(reader, _writer)| reader);
+            }
+        }
+    }
+
+    (job, true)
+}
+
+/// Starts the AND-OR list in a subshell, i.e., in a child process, in the background,
+/// and returns the resulting job.
+///
+/// Also returns whether the subshell was started.
+fn start_subshell(shell: &mut Shell, and_or_list: AndOrList) -> (Job, bool) {
+    let mut job = Job::new(and_or_list.to_string());
+
+    match fork_child(shell, 0, false) {
+        Err(err) => {
+            eprintln!("{err}: Failed to fork");
+            (job, false)
+        }
+        Ok(0) => {
+            // The child process
+            run_and_or_list(shell, and_or_list);
+            unsafe { libc::_exit(shell.last_status) };
+        }
+        Ok(pid) => {
+            job.add_process(pid);
+            (job, true)
+        }
+    }
+}
+
+/// Runs the `list` in a subshell, i.e., in a child process, whose standard output is captured through a pipe,
+/// and returns the output, e.g., for a command substitution.
+///
+/// The shell waits for the subshell as a foreground job, and stores its exit status in the [`Shell`] state,
+/// as the value of the special parameter `$?`.
+///
+/// # Errors
+/// - Returns [`io::Error`] in case the pipe can't be created, the subshell can't be forked,
+///   or its output can't be read.
+pub fn capture_output(shell: &mut Shell, list: List) -> io::Result<Vec<u8>> {
+    let command = list
+        .iter()
+        .map(|and_or_list| and_or_list.to_string())
+        .collect::<Vec<_>>()
+        .join("; ");
+    let mut job = Job::new(command);
+    let (mut reader, writer) = io::pipe()?;
+
+    match fork_child(shell, 0, true)? {
+        0 => {
+            // The child process
+            drop(reader);
+            redirect_fd(writer.as_raw_fd(), libc::STDOUT_FILENO);
+            drop(writer);
+            run_list(shell, list);
+            io::stdout().flush().expect(FAILED_FLUSH_TO_STDOUT);
+            unsafe { libc::_exit(shell.last_status) };
+        }
+        pid => {
+            job.add_process(pid);
+            // The writing end must be closed in the parent, so that the reader receives EOF.
+            drop(writer);
+            let mut output = Vec::new();
+            let result = reader.read_to_end(&mut output);
+            shell.last_status = wait_for_job(shell, job);
+            result?;
+
+            Ok(output)
+        }
+    }
+}
+
+/// Forks the shell process, and returns the process ID of the child in the parent, and zero in the child.
+///
+/// With job control, the child is put in the process group `pgid`, or in a new process group if it is zero,
+/// and in case of a `foreground` job, the process group is put in the foreground of the terminal.
+/// Without job control, the standard input of a background job is redirected from `/dev/null`.
+///
+/// The child is a subshell: it restores the default handling of signals, and it has neither job control,
+/// nor jobs of its own.
+///
+/// # Errors
+/// - Returns [`io::Error`] in case the process can't be forked.
+fn fork_child(shell: &mut Shell, pgid: libc::pid_t, foreground: bool) -> io::Result<libc::pid_t> {
+    // Flush everything that's buffered, so that the child doesn't inherit and repeat it.
+    io::stdout().flush().expect(FAILED_FLUSH_TO_STDOUT);
+    io::stderr().flush().expect(FAILED_FLUSH_TO_STDERR);
+
+    // SAFETY: The shell is single-threaded, so the child can safely continue running Rust code.
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => {
+            match shell.job_control.take() {
+                Some(job_control) => {
+                    // Both the parent and the child set the process group, to avoid a race.
+                    let pgid = if pgid == 0 {
+                        unsafe { libc::getpid() }
+                    } else {
+                        pgid
+                    };
+                    unsafe { libc::setpgid(0, pgid) };
+                    if foreground {
+                        job_control.give_terminal_to(pgid);
+                    }
+                }
+                None if !foreground => match File::open("/dev/null") {
+                    Ok(null) => redirect_fd(null.as_raw_fd(), libc::STDIN_FILENO),
+                    Err(err) => eprintln!("{err}: Failed to open '/dev/null'"),
+                },
+                None => {}
+            }
+
+            // Rust ignores `SIGPIPE` by default, but a command writing to a closed pipe should terminate.
+            for signal in JOB_CONTROL_SIGNALS.into_iter().chain([libc::SIGPIPE]) {
+                unsafe { libc::signal(signal, libc::SIG_DFL) };
+            }
+            shell.jobs = JobTable::default();
+
+            Ok(0)
+        }
+        pid => {
+            if shell.job_control.is_some() {
+                let pgid = if pgid == 0 { pid } else { pgid };
+                unsafe { libc::setpgid(pid, pgid) };
+            }
+
+            Ok(pid)
+        }
+    }
+}
+
+/// Duplicates the file descriptor `from` onto the file descriptor `to`.
+///
+/// Used in child processes only, which is why it terminates the process in case of an error.
+fn redirect_fd(from: i32, to: i32) {
+    if unsafe { libc::dup2(from, to) } == -1 {
+        eprintln!(
+            "{}: Failed to duplicate a file descriptor",
+            io::Error::last_os_error()
+        );
+        unsafe { libc::_exit(1) };
+    }
+}
+
+/// Runs a command in the current process.
+///
+/// The current process is `forked` if it is a child process that runs only this command,
+/// e.g., as a part of a pipeline.
+///
+/// Returns the exit status of the command.
+fn run_command(shell: &mut Shell, cmd: Command, forked: bool) -> i32 {
+    match cmd {
+        Command::Simple(cmd) => run_simple_command(shell, cmd, forked),
+        Command::Compound {
+            command,
+            redirections,
+        } => run_redirected(shell, command, &redirections),
+        Command::Function(function) => {
+            shell.functions.insert(function.name.clone(), function);
+            0
+        }
+    }
+}
+
+/// Runs a compound command with the `redirections` applied to it as a whole, and returns its exit status,
+/// which is `1` if the redirections couldn't be applied.
+fn run_redirected(
+    shell: &mut Shell,
+    command: CompoundCommand,
+    redirections: &[Redirection],
+) -> i32 {
+    let saved_fds = match apply_redirections(shell, redirections) {
+        Ok(saved_fds) => saved_fds,
+        Err(err) => {
+            eprintln!("{err}");
+            return 1;
+        }
+    };
+    let status = run_compound_command(shell, command);
+    restore_fds(saved_fds);
+
+    status
+}
+
+/// Calls the shell `function` with the `args` as its positional parameters, and returns its exit status,
+/// which is that of `return`, if the function returned with it.
+///
+/// The positional parameters and the variables that the function made `local` are restored afterwards.
+/// `break` and `continue` apply only to the loops inside the function.
+///
+/// # References
+/// - [Function Definition Command](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_05)
+fn call_function(shell: &mut Shell, function: FunctionDefinition, args: Args) -> i32 {
+    let args = args.iter().map(|arg| arg.to_string()).collect();
+    let positional = mem::replace(&mut shell.positional, args);
+    let loop_depth = mem::take(&mut shell.loop_depth);
+    shell.function_depth += 1;
+    shell.variables.push_scope();
+
+    let mut status = run_redirected(shell, function.body, &function.redirections);
+    if let Some(Jump::Return(value)) = shell.jump {
+        shell.jump = None;
+        status = value;
+    }
+
+    shell.variables.pop_scope();
+    shell.function_depth -= 1;
+    shell.loop_depth = loop_depth;
+    shell.positional = positional;
+
+    status
+}
+
+/// Runs a compound command in the current process, and returns its exit status.
+///
+/// # References
+/// - [Compound Commands](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04)
+fn run_compound_command(shell: &mut Shell, command: CompoundCommand) -> i32 {
+    match command {
+        CompoundCommand::If(clause) => run_if_clause(shell, clause),
+        CompoundCommand::For(clause) => run_for_clause(shell, clause),
+        CompoundCommand::While(clause) => run_while_clause(shell, clause),
+        CompoundCommand::Case(clause) => run_case_clause(shell, clause),
+        CompoundCommand::Subshell(list) => run_subshell(shell, list),
+        CompoundCommand::BraceGroup(list) => {
+            run_list(shell, list);
+            shell.last_status
+        }
+    }
+}
+
+/// Runs the `list` in a subshell, i.e., in a child process, as a foreground job, and returns its exit status.
+///
+/// The subshell starts with a copy of the shell state, so changes to it, e.g., to the variables
+/// or to the working directory, don't affect the shell. The exit status is `1` if the subshell couldn't be forked.
+///
+/// # References
+/// - [Grouping Commands](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_04_01)
+fn run_subshell(shell: &mut Shell, list: List) -> i32 {
+    let command = list
+        .iter()
+        .map(|and_or_list| and_or_list.to_string())
+        .collect::<Vec<_>>()
+        .join("; ");
+    let mut job = Job::new(format!("({command})"));
+
+    match fork_child(shell, 0, true) {
+        Err(err) => {
+            eprintln!("{err}: Failed to fork");
+            1
+        }
+        Ok(0) => {
+            // The child process
+            run_list(shell, list);
+            io::stdout().flush().expect(FAILED_FLUSH_TO_STDOUT);
+            unsafe { libc::_exit(shell.last_status) };
+        }
+        Ok(pid) => {
+            job.add_process(pid);
+            wait_for_job(shell, job)
+        }
+    }
+}
+
+/// Runs a `for` loop, and returns the exit status of the last body that was run, or zero if none was.
+///
+/// The exit status is `1` if the words couldn't be expanded, or if the variable couldn't be assigned.
+fn run_for_clause(shell: &mut Shell, clause: ForClause) -> i32 {
+    let values = match &clause.words {
+        Some(words) => match expand_words(shell, words) {
+            Ok(values) => values,
+            Err(err) => return expansion_failed(shell, err),
+        },
+        None => shell.positional.clone(),
+    };
+
+    let mut status = 0;
+    shell.loop_depth += 1;
+    for value in values {
+        if let Err(err) = shell.variables.set(&clause.name, &value) {
+            eprintln!("{err}");
+            status = 1;
+            break;
+        }
+        run_list(shell, clause.body.clone());
+        status = shell.last_status;
+        if !next_iteration(shell) {
+            break;
+        }
+    }
+    shell.loop_depth -= 1;
+
+    status
+}
+
+/// Prints the expansion error `err`, and returns the exit status `1` of the command that it occurred in.
+///
+/// See [`exit_if_fatal`] for fatal errors.
+fn expansion_failed(shell: &mut Shell, err: ExpansionError) -> i32 {
+    eprintln!("{err}");
+    exit_if_fatal(shell, &err);
+    1
+}
+
+/// Makes a non-interactive shell exit with the exit status `1` in case of a fatal expansion error `err`.
+///
+/// The exit is a pending jump, so the commands that are running are stopped, and their redirections
+/// and variables are restored, before the shell exits.
+///
+/// # References
+/// - [Consequences of Shell Errors](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_08_01)
+fn exit_if_fatal(shell: &mut Shell, err: &ExpansionError) {
+    if err.fatal && !shell.interactive {
+        shell.jump = Some(Jump::Exit(1));
+    }
+}
+
+/// Runs a `while` or an `until` loop, and returns the exit status of the last body that was run,
+/// or zero if none was.
+fn run_while_clause(shell: &mut Shell, clause: WhileClause) -> i32 {
+    let mut status = 0;
+    shell.loop_depth += 1;
+    loop {
+        run_list(shell, clause.condition.clone());
+        // A `break` or `continue` in the condition applies to the loop as well
+        if shell.jump.is_none() {
+            if (shell.last_status == 0) == clause.until {
+                break;
+            }
+            run_list(shell, clause.body.clone());
+            status = shell.last_status;
+        }
+        if !next_iteration(shell) {
+            break;
+        }
+    }
+    shell.loop_depth -= 1;
+
+    status
+}
+
+/// Handles the pending `break` or `continue` of the `shell` at the end of an iteration of a loop,
+/// and returns whether the loop goes on with the next iteration.
+///
+/// A `break` or `continue` that applies to an enclosing loop stays pending, with its count decremented,
+/// and so do a `return` and an exit.
+fn next_iteration(shell: &mut Shell) -> bool {
+    match shell.jump.take() {
+        None | Some(Jump::Continue(1)) => true,
+        Some(Jump::Break(1)) => false,
+        Some(Jump::Break(count)) => {
+            shell.jump = Some(Jump::Break(count - 1));
+            false
+        }
+        Some(Jump::Continue(count)) => {
+            shell.jump = Some(Jump::Continue(count - 1));
+            false
+        }
+        Some(jump @ (Jump::Return(_) | Jump::Exit(_))) => {
+            shell.jump = Some(jump);
+            false
+        }
+    }
+}
+
+/// Runs a `case` conditional, and returns the exit status of the body that was run, or zero if none was.
+///
+/// The patterns are matched the same way as in pathname expansion, except that `/` and a leading `.`
+/// are not special. The exit status is `1` if the word or a pattern couldn't be expanded.
+fn run_case_clause(shell: &mut Shell, clause: CaseClause) -> i32 {
+    let word = match expand_word(shell, &clause.word) {
+        Ok(word) => word,
+        Err(err) => return expansion_failed(shell, err),
+    };
+
+    for item in clause.items {
+        for pattern in &item.patterns {
+            let pattern = match expand_pattern(shell, pattern) {
+                Ok(pattern) => pattern,
+                Err(err) => return expansion_failed(shell, err),
+            };
+            if pattern::matches(&pattern, &word) {
+                if item.body.is_empty() {
+                    return 0;
+                }
+                run_list(shell, item.body);
+                return shell.last_status;
+            }
+        }
+    }
+
+    0
+}
+
+/// Runs an `if` conditional, and returns the exit status of the body that was run, or zero if none was.
+fn run_if_clause(shell: &mut Shell, clause: IfClause) -> i32 {
+    for (condition, body) in clause.branches {
+        run_list(shell, condition);
+        if shell.last_status == 0 {
+            run_list(shell, body);
+            return shell.last_status;
+        }
+    }
+
+    match clause.else_body {
+        Some(body) => {
+            run_list(shell, body);
+            shell.last_status
+        }
+        None => 0,
+    }
+}
+
+/// Runs a simple command, which is either a builtin or an external program, in the current process
+///
+/// The redirections are applied to the standard streams of the process before running the command,
+/// and undone after it completes, so external programs inherit them directly.
+///
+/// An external program replaces the current process if it is `forked`.
+/// Otherwise, the program is run in a new child process, as a foreground job.
+///
+/// The words are expanded first, then the redirection targets are expanded and the redirections are applied,
+/// and then the values of the assignments are expanded, and the variables are assigned.
+///
+/// The assignments persist in the shell if there is no command name, or if the command is a special builtin.
+/// Otherwise, they are exported for the duration of the command only, so that they are a part of the environment
+/// of an external program, and the variables are restored afterwards.
+///
+/// Returns the exit status of the command. It is `1` if the redirections couldn't be applied,
+/// if the words couldn't be expanded, or if a variable couldn't be assigned. Without a command name,
+/// it is the exit status of the last command substitution, or `0` if there is none.
+///
+/// # References
+/// - [Simple Commands](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_01)
+fn run_simple_command(shell: &mut Shell, cmd: SimpleCommand, forked: bool) -> i32 {
+    let command = cmd.to_string();
+    let SimpleCommand {
+        assignments,
+        words,
+        redirections,
+    } = cmd;
+
+    let mut stderr = io::stderr();
+
+    let items = match expand_words(shell, &words) {
+        Ok(items) => items,
+        Err(err) => return expansion_failed(shell, err),
+    };
+
+    let saved_fds = match apply_redirections(shell, &redirections) {
+        Ok(saved_fds) => saved_fds,
+        Err(err) => {
+            eprintln!("{err}");
+            return 1;
+        }
+    };
+    let items = items
+        .iter()
+        .map(|item| item.as_str())
+        .collect::<Vec<&str>>();
+
+    let temporary = items
+        .first()
+        .is_some_and(|name| !SPECIAL_BUILTINS.contains(name));
+    let saved_vars = match temporary {
+        true => assignments
+            .iter()
+            .map(|assignment| {
+                (
+                    assignment.name.as_str(),
+                    shell.variables.save(&assignment.name),
+                )
+            })
+            .collect(),
+        false => Vec::new(),
+    };
+
+    if let Err(err) = assign_variables(shell, &assignments, temporary) {
+        restore_variables(shell, saved_vars);
+        restore_fds(saved_fds);
+        return expansion_failed(shell, err);
+    }
+
+    let values = assignments.iter().map(|assignment| &assignment.value);
+    let mut status = match words.iter().chain(values).any(has_command_substitution) {
+        true => shell.last_status,
+        false => 0,
+    };
+
+    if let Some((cmd, args)) = items.split_first() {
+        // Special builtins are found first, then functions, then the other builtins, and then external programs
+        let function = match SPECIAL_BUILTINS.contains(cmd) {
+            true => None,
+            false => shell.functions.get(*cmd).cloned(),
+        };
+        let output = match (function, get_handlers().get(cmd)) {
+            (Some(function), _) => Output::new(b"", b"", call_function(shell, function, args)),
+            (None, Some(&handler)) => handler(shell, args),
+            (None, None) if forked => exec_program(shell, cmd, args),
+            (None, None) => run_program(shell, command, cmd, args),
+        };
+        status = output.status();
+
+        if DEBUG.get().is_some_and(|&debug| debug) {
+            eprintln!("cmd: {cmd:?}");
+            eprintln!("args: {args:?}");
+            eprintln!("redirections: {redirections:?}");
+            eprintln!("output: {output}");
+            eprintln!();
+        }
+
+        let (stdout_data, stderr_data) = output.get();
+        if let Err(err) = write_stdout(&stdout_data) {
+            eprintln!("{cmd}: {err}: Failed to write to stdout");
+            status = 1;
+        }
+        stderr
+            .write_all(&stderr_data)
+            .expect(FAILED_WRITE_TO_STDERR);
+    }
+
+    stderr.flush().expect(FAILED_FLUSH_TO_STDERR);
+
+    restore_variables(shell, saved_vars);
+    restore_fds(saved_fds);
+
+    status
+}
+
+/// Writes the `data` to the standard output, and flushes it.
+///
+/// Unlike [`io::Stdout`], which silently discards the data if the standard output is closed,
+/// this reports the error, e.g., in case of `echo x >&-`, so that the command fails.
+///
+/// # Errors
+/// - Returns [`io::Error`] in case the standard output is closed, or the data can't be written to it.
+fn write_stdout(data: &[u8]) -> io::Result<()> {
+    if !data.is_empty() && !is_open(libc::STDOUT_FILENO) {
+        return Err(io::Error::from_raw_os_error(libc::EBADF));
+    }
+
+    let mut stdout = io::stdout();
+    stdout.write_all(data)?;
+    stdout.flush()
+}
+
+/// Expands the values of the `assignments`, and assigns them to the variables of the `shell`, in order.
+///
+/// The variables are also exported in case of `export`.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case a value can't be expanded, or a variable is read-only.
+fn assign_variables(
+    shell: &mut Shell,
+    assignments: &[Assignment],
+    export: bool,
+) -> Result<(), ExpansionError> {
+    for assignment in assignments {
+        let value = expand_assignment(shell, &assignment.value)?;
+        shell.variables.set(&assignment.name, &value)?;
+        if export {
+            shell.variables.export(&assignment.name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Restores the variables of the `shell` that were saved before temporary assignments,
+/// in reverse order, so that a variable that was assigned more than once gets its original state back.
+fn restore_variables(shell: &mut Shell, saved_vars: Vec<(&str, Option<Variable>)>) {
+    for (name, var) in saved_vars.into_iter().rev() {
+        shell.variables.restore(name, var);
+    }
+}
+
+/// Checks whether the `word` contains a command substitution that isn't nested in another expansion,
+/// i.e., one that is always run when the word is expanded.
+fn has_command_substitution(word: &Word) -> bool {
+    fn any(parts: &[WordPart]) -> bool {
+        parts.iter().any(|part| match part {
+            WordPart::CommandSubstitution { .. } => true,
+            WordPart::DoubleQuoted(parts) => any(parts),
+            _ => false,
+        })
+    }
+
+    any(&word.parts)
+}
+
+/// Runs an external program with arguments in a new child process, as a foreground job,
+/// described by the `command` text, and waits for it.
+///
+/// Returns the [`Output`] that holds the exit status of the program.
+fn run_program(shell: &mut Shell, command: String, exec: &str, args: Args) -> Output {
+    let mut job = Job::new(command);
+
+    match fork_child(shell, 0, true) {
+        Err(err) => Output::new(b"", format!("{err}: Failed to fork\n").as_bytes(), 1),
+        Ok(0) => {
+            // The child process
+            let output = exec_program(shell, exec, args);
+            let status = output.status();
+            let (_, stderr_data) = output.get();
+            let mut stderr = io::stderr();
+            stderr
+                .write_all(&stderr_data)
+                .expect(FAILED_WRITE_TO_STDERR);
+            stderr.flush().expect(FAILED_FLUSH_TO_STDERR);
+            unsafe { libc::_exit(status) };
+        }
+        Ok(pid) => {
+            job.add_process(pid);
+            let status = wait_for_job(shell, job);
+            Output::new(b"", b"", status)
+        }
+    }
+}
+
+/// A file descriptor of the shell process that was redirected,
+/// together with a copy of the original file descriptor, which is used for restoring it.
+struct SavedFd {
+    /// The redirected file descriptor
+    fd: RawFd,
+    /// A copy of the original file descriptor; `None` if it wasn't open, in which case it is closed on restoring
+    original: Option<OwnedFd>,
+}
+
+/// Applies the `redirections` to the file descriptors of the shell process.
+///
+/// The redirections are applied in the order in which they were given, so `>file 2>&1` redirects
+/// both `stdout` and `stderr` to `file`, while `2>&1 >file` redirects `stderr` to the original `stdout`.
+///
+/// Returns copies of the original file descriptors, which should be restored with [`restore_fds`]
+/// after the command completes.
+///
+/// # Errors
+/// - Returns [`OutputError`] in case a target can't be expanded, a target file can't be opened,
+///   or a file descriptor is invalid, in which case the command must not be run. Redirections that have already been applied are undone.
+///
+/// # References
+/// - [Redirection](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_07)
+/// - [Redirecting Input](https://www.gnu.org/software/bash/manual/bash.html#Redirecting-Input)
+/// - [Redirecting Output](https://www.gnu.org/software/bash/manual/bash.html#Redirecting-Output)
+/// - [Duplicating File Descriptors](https://www.gnu.org/software/bash/manual/bash.html#Duplicating-File-Descriptors)
+fn apply_redirections(
+    shell: &mut Shell,
+    redirections: &[Redirection],
+) -> Result<Vec<SavedFd>, OutputError> {
+    let mut saved_fds = Vec::new();
+
+    for redirection in redirections {
+        let target = match &redirection.body {
+            Some(body) => expand_here_document(shell, body),
+            None => expand_word(shell, &redirection.target),
+        };
+        let target = match target {
+            Ok(target) => target,
+            Err(err) => {
+                restore_fds(saved_fds);
+                exit_if_fatal(shell, &err);
+                return Err(err.reason.into());
+            }
+        };
+        if let Err(reason) =
+            apply_redirection(redirection.fd, redirection.op, &target, &mut saved_fds)
+        {
+            restore_fds(saved_fds);
+            return Err(reason.as_str().into());
+        }
+    }
+
+    Ok(saved_fds)
+}
+
+/// Applies a single redirection of the file descriptor `fd` to the expanded `target`,
+/// and appends copies of the original file descriptors to `saved_fds`.
+///
+/// The target of a here-document is its expanded body.
+fn apply_redirection(
+    fd: RawFd,
+    op: RedirectionOp,
+    target: &str,
+    saved_fds: &mut Vec<SavedFd>,
+) -> Result<(), String> {
+    if !(0..=MAX_USER_FD).contains(&fd) {
+        return Err(format!("{fd}: Bad file descriptor"));
+    }
+
+    let saved_fd = match op {
+        RedirectionOp::DupInput | RedirectionOp::DupOutput if target == "-" => {
+            let saved_fd = close_fd(fd)
+                .map_err(|err| format!("{err}: Failed to close file descriptor {fd}"))?;
+            saved_fds.extend(saved_fd);
+            return Ok(());
+        }
+        RedirectionOp::DupInput | RedirectionOp::DupOutput
+            if !target.is_empty() && target.bytes().all(|byte| byte.is_ascii_digit()) =>
+        {
+            let from = match target.parse() {
+                Ok(from) if from <= MAX_USER_FD && is_open(from) => from,
+                _ => return Err(format!("{target}: Bad file descriptor")),
+            };
+            redirect(from, fd).map_err(|err| {
+                format!("{err}: Failed to duplicate file descriptor {target} as {fd}")
+            })?
+        }
+        // Shell-specific `>&word`, where `word` isn't a number, is the same as `&>word`.
+        RedirectionOp::DupOutput if fd == libc::STDOUT_FILENO => {
+            return apply_redirection(fd, RedirectionOp::OutputAll, target, saved_fds);
+        }
+        RedirectionOp::DupInput | RedirectionOp::DupOutput => {
+            return Err(format!("{target}: ambiguous redirect"));
+        }
+        RedirectionOp::OutputAll | RedirectionOp::AppendAll => {
+            let file = open_target(target, op)?;
+            saved_fds.push(
+                redirect_file(file, libc::STDOUT_FILENO)
+                    .map_err(|err| format!("{err}: Failed to redirect to '{target}'"))?,
+            );
+            redirect(libc::STDOUT_FILENO, libc::STDERR_FILENO)
+                .map_err(|err| format!("{err}: Failed to redirect stderr to '{target}'"))?
+        }
+        _ => {
+            let file = open_target(target, op)?;
+            redirect_file(file, fd).map_err(|err| {
+                format!("{err}: Failed to redirect file descriptor {fd} to '{target}'")
+            })?
+        }
+    };
+    saved_fds.push(saved_fd);
+
+    Ok(())
+}
+
+/// Opens the target file of a redirection according to its [`RedirectionOp`], and returns it.
+///
+/// The input of a here-string or of a here-document is written to a temporary file, which is removed right away,
+/// so that it disappears as soon as it is closed.
+fn open_target(target: &str, op: RedirectionOp) -> Result<File, String> {
+    let mut options = OpenOptions::new();
+    match op {
+        RedirectionOp::HereString => return here_file(&format!("{target}\n")),
+        RedirectionOp::HereDocument { .. } => return here_file(target),
+        RedirectionOp::Input => options.read(true),
+        RedirectionOp::ReadWrite => options.read(true).write(true).create(true).truncate(false),
+        RedirectionOp::Append | RedirectionOp::AppendAll => options.append(true).create(true),
+        _ => options.write(true).create(true).truncate(true),
+    };
+
+    options
+        .open(target)
+        .map_err(|err| format!("{err}: Failed to open the file '{target}'"))
+}
+
+/// Writes the `contents` to an anonymous temporary file, and returns the file, positioned at its start.
+fn here_file(contents: &str) -> Result<File, String> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = env::temp_dir().join(format!(
+        "posix-shell-{}-{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let error = |err: io::Error| format!("{err}: Failed to create a temporary file");
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(error)?;
+    let _ = fs::remove_file(&path);
+    file.write_all(contents.as_bytes()).map_err(error)?;
+    file.seek(SeekFrom::Start(0)).map_err(error)?;
+
+    Ok(file)
+}
+
+/// Redirects the file descriptor `to` of the shell process so that it refers to the same file as `from`.
+///
+/// Returns a copy of the original file descriptor `to`, so that it can be restored later.
+fn redirect(from: RawFd, to: RawFd) -> io::Result<SavedFd> {
+    // The copy is made above the range of file descriptors available to the user,
+    // and it is closed on `exec`, so that external programs don't inherit it.
+    let original = match unsafe { libc::fcntl(to, libc::F_DUPFD_CLOEXEC, 10) } {
+        // `to` isn't open.
+        -1 if io::Error::last_os_error().raw_os_error() == Some(libc::EBADF) => None,
+        -1 => return Err(io::Error::last_os_error()),
+        // SAFETY: `original` is a freshly duplicated file descriptor that nothing else owns.
+        original => Some(unsafe { OwnedFd::from_raw_fd(original) }),
+    };
+
+    if unsafe { libc::dup2(from, to) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(SavedFd { fd: to, original })
+}
+
+/// Redirects the file descriptor `to` of the shell process so that it refers to the opened `file`.
+///
+/// The file may have been opened as `to` itself, if `to` wasn't open, in which case it is kept open.
+///
+/// Returns a copy of the original file descriptor `to`, so that it can be restored later.
+fn redirect_file(file: File, to: RawFd) -> io::Result<SavedFd> {
+    if file.as_raw_fd() != to {
+        return redirect(file.as_raw_fd(), to);
+    }
+
+    let fd = file.into_raw_fd();
+    // Files are opened with the close-on-exec flag, which must be cleared, as it is by `dup2`.
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, 0) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(SavedFd { fd, original: None })
+}
+
+/// Closes the file descriptor `fd` of the shell process.
+///
+/// Returns a copy of the original file descriptor `fd`, so that it can be restored later,
+/// or `None` if `fd` isn't open, in which case there is nothing to close or to restore.
+fn close_fd(fd: RawFd) -> io::Result<Option<SavedFd>> {
+    let original = match unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 10) } {
+        -1 if io::Error::last_os_error().raw_os_error() == Some(libc::EBADF) => return Ok(None),
+        -1 => return Err(io::Error::last_os_error()),
+        // SAFETY: `original` is a freshly duplicated file descriptor that nothing else owns.
+        original => unsafe { OwnedFd::from_raw_fd(original) },
+    };
+
+    if unsafe { libc::close(fd) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(Some(SavedFd {
+        fd,
+        original: Some(original),
+    }))
+}
+
+/// Checks whether the file descriptor `fd` of the shell process is open.
+fn is_open(fd: RawFd) -> bool {
+    unsafe { libc::fcntl(fd, libc::F_GETFD) != -1 }
+}
+
+/// Restores the file descriptors that were redirected by [`apply_redirections`], in reverse order.
+///
+/// A file descriptor that wasn't open originally is closed, unless it has been closed already, e.g., by `n>&-`.
+fn restore_fds(saved_fds: Vec<SavedFd>) {
+    for SavedFd { fd, original } in saved_fds.into_iter().rev() {
+        let result = match original {
+            Some(original) => unsafe { libc::dup2(original.as_raw_fd(), fd) },
+            None => unsafe { libc::close(fd) },
+        };
+        if result == -1 && io::Error::last_os_error().raw_os_error() != Some(libc::EBADF) {
+            eprintln!(
+                "{}: Failed to restore file descriptor {fd}",
+                io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+/// Builds a table of command handlers and returns it
+fn get_handlers<'a>() -> HashMap<&'a str, Handler> {
+    let pairs: [(&str, Handler); COMMANDS.len()] = zip(COMMANDS, HANDLERS)
+        .collect::<Vec<_>>()
+        .try_into()
+        .expect("Failed to convert vector to array");
+
+    HashMap::from(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{close_fd, is_open, restore_fds, run_list};
+    use crate::parse::parse_input;
+    use crate::shell::{Jump, Shell};
+    use std::fs::File;
+    use std::os::fd::AsRawFd;
+
+    /// Runs the `input` in the `shell`, and returns the exit status of its last command.
+    fn run(shell: &mut Shell, input: &str) -> i32 {
+        run_list(shell, parse_input(input).unwrap());
+        shell.last_status
+    }
+
+    #[test]
+    fn close_fd_01() {
+        // A file descriptor that isn't open isn't saved, so it isn't restored either
+        assert!(!is_open(1000));
+        assert!(close_fd(1000).unwrap().is_none());
+
+        let file = File::open("Cargo.toml").unwrap();
+        let fd = file.as_raw_fd();
+        let saved_fd = close_fd(fd).unwrap();
+        assert!(saved_fd.is_some());
+        assert!(!is_open(fd));
+        restore_fds(saved_fd.into_iter().collect());
+        assert!(is_open(fd));
+    }
+
+    #[test]
+    fn prefix_assignments_01() {
+        let mut shell = Shell::default();
+        shell.variables.set("X", "0").unwrap();
+
+        // `wait` is a builtin that isn't special, so the assignments apply only to it
+        assert_eq!(0, run(&mut shell, "X=1 Y=2 wait"));
+        assert_eq!(Some("0"), shell.variables.get("X"));
+        assert_eq!(0, shell.variables.exported().count());
+        assert_eq!(None, shell.variables.get("Y"));
+
+        assert_eq!(0, run(&mut shell, "X=1 X=2 Y=1 Y=2 wait"));
+        assert_eq!(Some("0"), shell.variables.get("X"));
+        assert_eq!(None, shell.variables.get("Y"));
+
+        // `export` is a special builtin, so the assignments persist
+        assert_eq!(0, run(&mut shell, "X=1 export Y; Y=2"));
+        assert_eq!(Some("1"), shell.variables.get("X"));
+        assert_eq!(
+            vec![("Y", "2")],
+            shell.variables.exported().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn prefix_assignments_02() {
+        let mut shell = Shell::default();
+        shell.variables.set("R", "r").unwrap();
+        shell.variables.set_readonly("R");
+
+        // A read-only variable can't be assigned, so the command isn't run
+        assert_eq!(1, run(&mut shell, "R=x X=1 wait"));
+        assert_eq!(Some("r"), shell.variables.get("R"));
+        assert_eq!(None, shell.variables.get("X"));
+
+        // A variable that becomes read-only during the command is restored anyway
+        assert_eq!(0, run(&mut shell, "f() { readonly X; }; X=1 f"));
+        assert_eq!(None, shell.variables.get("X"));
+        assert_eq!(0, run(&mut shell, "X=2"));
+        assert_eq!(Some("2"), shell.variables.get("X"));
+    }
+
+    #[test]
+    fn fatal_expansion_error_01() {
+        // A non-interactive shell exits from the loop and from the function, and runs nothing else
+        let mut shell = Shell::default();
+        let input = "f() { for i in 1 2; do X=$i; : ${unset:?}; done; X=no; }; f; X=after";
+        assert_eq!(1, run(&mut shell, input));
+        assert_eq!(Some(Jump::Exit(1)), shell.jump);
+        assert_eq!(Some("1"), shell.variables.get("X"));
+
+        let mut shell = Shell::default();
+        assert_eq!(1, run(&mut shell, "X=$((1 / 0)) || X=after"));
+        assert_eq!(Some(Jump::Exit(1)), shell.jump);
+        assert_eq!(None, shell.variables.get("X"));
+
+        // An interactive shell only stops running the command
+        let mut shell = Shell {
+            interactive: true,
+            ..Shell::default()
+        };
+        assert_eq!(0, run(&mut shell, ": ${unset:?}; X=after"));
+        assert_eq!(None, shell.jump);
+        assert_eq!(Some("after"), shell.variables.get("X"));
+    }
+}
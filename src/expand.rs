@@ -0,0 +1,674 @@
+//! Word expansion
+//!
+//! Words are expanded right before the command that contains them is run,
+//! so that they see the state of the shell at that moment, e.g., the exit status of the previous pipeline.
+//!
+//! Quote removal is a part of the expansion, because the quotes are kept in the parts of a [`Word`].
+//!
+//! # References
+//!
+//! - [Word Expansions](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06)
+//! - [Tilde Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_01)
+//! - [Parameter Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_02)
+//! - [Command Substitution](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_03)
+//! - [Arithmetic Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_04)
+//! - [Field Splitting](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_05)
+//! - [Special Parameters](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_05_02)
+//! - [Pathname Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_06)
+//! - [Quote Removal](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_07)
+
+use crate::arith;
+use crate::ast::{ParameterExpansion, ParameterOp, Word, WordPart};
+use crate::constants::DEFAULT_IFS;
+use crate::errors::ExpansionError;
+use crate::exec::capture_output;
+use crate::parse::parse_input;
+use crate::pattern;
+use crate::shell::Shell;
+use crate::vars::is_name;
+use std::ffi::{CStr, CString};
+use std::mem;
+
+/// Expands the `words` of a command, e.g., its name and its arguments, into fields.
+///
+/// A tilde prefix at the start of a word is replaced by the corresponding directory.
+/// The results of unquoted parameter expansions, command substitutions and arithmetic expansions are split
+/// into separate fields at the characters of `IFS`, and a word whose expansion is empty and unquoted results
+/// in no field at all.
+///
+/// Finally, the fields that contain unquoted pattern characters are replaced by the sorted pathnames
+/// that match them, unless there are none.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case an expansion fails, e.g., `${unset:?}`,
+///   in which case the command must not be run.
+pub fn expand_words(shell: &mut Shell, words: &[Word]) -> Result<Vec<String>, ExpansionError> {
+    let mut expanded = Vec::new();
+    for word in words {
+        let ifs = shell
+            .variables
+            .get("IFS")
+            .unwrap_or(DEFAULT_IFS)
+            .to_string();
+        let mut fields = Fields::new(Some(ifs));
+        expand_parts(
+            shell,
+            &expand_tilde(shell, &word.parts, false),
+            false,
+            &mut fields,
+        )?;
+        for field in fields.finish() {
+            expanded.extend(expand_pathname(field));
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Expands a single `word` into a single string, e.g., a redirection target.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case an expansion fails.
+pub fn expand_word(shell: &mut Shell, word: &Word) -> Result<String, ExpansionError> {
+    expand_string(shell, &expand_tilde(shell, &word.parts, false))
+}
+
+/// Expands the value of an assignment, e.g., `PATH=~/bin:$PATH`, into a single string.
+///
+/// Unlike in other words, a tilde prefix may also follow any unquoted `:` in the value.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case an expansion fails.
+pub fn expand_assignment(shell: &mut Shell, value: &Word) -> Result<String, ExpansionError> {
+    expand_string(shell, &expand_tilde(shell, &value.parts, true))
+}
+
+/// Expands the `body` of a here-document into a single string.
+///
+/// Unlike in other words, `~` isn't special in the body.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case an expansion fails.
+pub fn expand_here_document(shell: &mut Shell, body: &Word) -> Result<String, ExpansionError> {
+    expand_string(shell, &body.parts)
+}
+
+/// Expands the `parts` of a word into a single string, without tilde expansion.
+///
+/// The fields of `$@`, which are the positional parameters, are joined with spaces.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case an expansion fails.
+fn expand_string(shell: &mut Shell, parts: &[WordPart]) -> Result<String, ExpansionError> {
+    let mut fields = Fields::new(None);
+    expand_parts(shell, parts, false, &mut fields)?;
+    Ok(fields
+        .finish()
+        .into_iter()
+        .map(|field| field.text)
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Expands a `word` into a pattern, e.g., the pattern of a prefix or suffix removal, or of a `case` item.
+///
+/// The quoted characters of the word are escaped with a backslash, so that they match only themselves.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case an expansion fails.
+pub fn expand_pattern(shell: &mut Shell, word: &Word) -> Result<String, ExpansionError> {
+    let mut fields = Fields::new(None);
+    expand_parts(
+        shell,
+        &expand_tilde(shell, &word.parts, false),
+        false,
+        &mut fields,
+    )?;
+    Ok(fields
+        .finish()
+        .into_iter()
+        .map(|field| field.pattern)
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Performs tilde expansion of the `parts` of a word, and returns the resulting parts.
+///
+/// A tilde prefix is an unquoted `~` at the start of the word, followed by the unquoted characters
+/// up to the first `/`, or up to the end of the word. In the value of an `assignment`, a tilde prefix
+/// may also follow any unquoted `:`, and it ends at `:` as well.
+///
+/// The directory that replaces a tilde prefix is quoted, so that it isn't subject to field splitting
+/// or pathname expansion. A tilde prefix without a directory is left unchanged.
+///
+/// # References
+/// - [Tilde Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_01)
+fn expand_tilde(shell: &Shell, parts: &[WordPart], assignment: bool) -> Vec<WordPart> {
+    let mut expanded = Vec::new();
+    // Whether a tilde prefix may start at the beginning of the next part
+    let mut at_start = true;
+
+    for (idx, part) in parts.iter().enumerate() {
+        let WordPart::Literal(text) = part else {
+            expanded.push(part.clone());
+            at_start = false;
+            continue;
+        };
+        let is_last = idx + 1 == parts.len();
+        let mut literal = String::new();
+        let mut rest = text.as_str();
+
+        loop {
+            if at_start && let Some(prefix) = rest.strip_prefix('~') {
+                let end = prefix.find(|ch| ch == '/' || (assignment && ch == ':'));
+                let login = &prefix[..end.unwrap_or(prefix.len())];
+                if (end.is_some() || is_last)
+                    && let Some(directory) = tilde_directory(shell, login)
+                {
+                    if !literal.is_empty() {
+                        expanded.push(WordPart::Literal(mem::take(&mut literal)));
+                    }
+                    expanded.push(WordPart::SingleQuoted(directory));
+                    rest = &prefix[login.len()..];
+                }
+            }
+            match rest.find(':') {
+                Some(colon) if assignment => {
+                    literal.push_str(&rest[..=colon]);
+                    rest = &rest[colon + 1..];
+                    at_start = true;
+                }
+                _ => {
+                    literal.push_str(rest);
+                    break;
+                }
+            }
+        }
+
+        if !literal.is_empty() {
+            expanded.push(WordPart::Literal(literal));
+        }
+        at_start = assignment && text.ends_with(':');
+    }
+
+    expanded
+}
+
+/// Returns the directory that replaces the tilde prefix `~login`, or `None` if there is none.
+///
+/// `~` is replaced by `$HOME`, `~+` by `$PWD`, `~-` by `$OLDPWD`, and `~user` by the home directory of `user`
+/// according to the password database.
+fn tilde_directory(shell: &Shell, login: &str) -> Option<String> {
+    let variable = match login {
+        "" => "HOME",
+        "+" => "PWD",
+        "-" => "OLDPWD",
+        _ => return home_directory(login),
+    };
+
+    shell.variables.get(variable).map(str::to_string)
+}
+
+/// Looks up the home directory of the user with the `login` name in the password database.
+fn home_directory(login: &str) -> Option<String> {
+    let login = CString::new(login).ok()?;
+    let entry = unsafe { libc::getpwnam(login.as_ptr()) };
+    if entry.is_null() {
+        return None;
+    }
+    let directory = unsafe { CStr::from_ptr((*entry).pw_dir) };
+
+    directory.to_str().ok().map(str::to_string)
+}
+
+/// Performs pathname expansion of the `field`, if it contains unquoted pattern characters.
+///
+/// Returns the sorted pathnames that match the pattern, or the field itself, if there are none.
+///
+/// # References
+/// - [Pathname Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_06)
+fn expand_pathname(field: Field) -> Vec<String> {
+    if !pattern::has_special_chars(&field.pattern) {
+        return vec![field.text];
+    }
+
+    match pattern::glob(&field.pattern) {
+        paths if paths.is_empty() => vec![field.text],
+        paths => paths,
+    }
+}
+
+/// A field that a word expands to
+#[derive(Default)]
+struct Field {
+    /// The text of the field
+    text: String,
+    /// The text of the field as a pattern, in which the quoted characters are escaped with a backslash
+    pattern: String,
+}
+
+/// The kind of delimiter that the field splitting of an expansion has seen last
+#[derive(PartialEq)]
+enum Delimiter {
+    /// No delimiter, i.e., the last character belongs to a field, or no character has been seen yet
+    None,
+    /// A sequence of `IFS` whitespace, which ends a field
+    Whitespace,
+    /// An `IFS` character other than whitespace, which ends a field, even an empty one
+    NonWhitespace,
+}
+
+/// The fields that a word expands to
+struct Fields {
+    /// The fields that are complete
+    fields: Vec<Field>,
+    /// The field that is being built
+    current: Field,
+    /// Whether the current field exists, even if it is empty, which is the case after a quoted part
+    started: bool,
+    /// The characters at which the unquoted results of expansions are split into fields,
+    /// or `None` if they aren't split
+    ifs: Option<String>,
+    /// The delimiter that the field splitting has seen last
+    delimiter: Delimiter,
+}
+
+impl Fields {
+    /// Constructs a new instance, which splits the unquoted results of expansions at the characters of `ifs`.
+    ///
+    /// An empty `ifs` disables field splitting.
+    fn new(ifs: Option<String>) -> Self {
+        Self {
+            fields: Vec::new(),
+            current: Field::default(),
+            started: false,
+            ifs: ifs.filter(|ifs| !ifs.is_empty()),
+            delimiter: Delimiter::None,
+        }
+    }
+
+    /// Appends the `text`, which may be `quoted`, to the current field.
+    fn push(&mut self, text: &str, quoted: bool) {
+        self.current.text.push_str(text);
+        match quoted {
+            true => self.current.pattern.push_str(&escape(text)),
+            false => self.current.pattern.push_str(text),
+        }
+        self.started |= quoted || !text.is_empty();
+        self.delimiter = Delimiter::None;
+    }
+
+    /// Appends the unquoted result of an expansion to the current field, splitting it into fields
+    /// at the characters of `IFS`.
+    ///
+    /// A sequence of `IFS` whitespace ends a field, and so does any other `IFS` character together with
+    /// the adjacent `IFS` whitespace, which may result in an empty field, e.g., `a::b` with `IFS=:`.
+    /// `IFS` whitespace at the start of the current field is ignored.
+    ///
+    /// # References
+    /// - [Field Splitting](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_05)
+    fn push_split(&mut self, text: &str) {
+        let Some(ifs) = self.ifs.take() else {
+            return self.push(text, false);
+        };
+
+        for ch in text.chars() {
+            match ch {
+                _ if !ifs.contains(ch) => {
+                    self.current.text.push(ch);
+                    self.current.pattern.push(ch);
+                    self.started = true;
+                    self.delimiter = Delimiter::None;
+                }
+                ' ' | '\t' | '\n' => {
+                    if self.started {
+                        self.end_field();
+                        self.delimiter = Delimiter::Whitespace;
+                    }
+                }
+                _ => {
+                    if self.delimiter != Delimiter::Whitespace {
+                        self.fields.push(mem::take(&mut self.current));
+                        self.started = false;
+                    }
+                    self.delimiter = Delimiter::NonWhitespace;
+                }
+            }
+        }
+
+        self.ifs = Some(ifs);
+    }
+
+    /// Ends the current field, if it exists.
+    fn end_field(&mut self) {
+        if self.started {
+            self.fields.push(mem::take(&mut self.current));
+            self.started = false;
+        }
+    }
+
+    /// Ends the current field, and returns all fields.
+    fn finish(mut self) -> Vec<Field> {
+        self.end_field();
+        self.fields
+    }
+}
+
+/// Expands the `parts` of a word, and appends the result to the `fields`.
+///
+/// The parts are `quoted` if they are enclosed in double quotes.
+fn expand_parts(
+    shell: &mut Shell,
+    parts: &[WordPart],
+    quoted: bool,
+    fields: &mut Fields,
+) -> Result<(), ExpansionError> {
+    for part in parts {
+        match part {
+            WordPart::Literal(text) => fields.push(text, quoted),
+            WordPart::SingleQuoted(text) => fields.push(text, true),
+            WordPart::Escaped(ch) => fields.push(&ch.to_string(), true),
+            WordPart::DoubleQuoted(parts) => {
+                // `"$@"` without positional parameters results in no field at all
+                let only_at = !parts.is_empty() && parts.iter().all(is_at);
+                if !(only_at && shell.positional.is_empty()) {
+                    fields.push("", true);
+                }
+                expand_parts(shell, parts, true, fields)?;
+            }
+            part if quoted && is_at(part) => {
+                // Each positional parameter is a separate field, even an empty one
+                for (idx, value) in shell.positional.iter().enumerate() {
+                    if idx > 0 {
+                        fields.end_field();
+                    }
+                    fields.push(value, true);
+                }
+            }
+            WordPart::Parameter(ParameterExpansion {
+                name,
+                op: ParameterOp::Value,
+                ..
+            }) if !quoted && (name == "@" || name == "*") => {
+                // Each positional parameter is a separate field, which is split further
+                for (idx, value) in shell.positional.iter().enumerate() {
+                    if idx > 0 {
+                        fields.end_field();
+                    }
+                    fields.push_split(value);
+                }
+            }
+            WordPart::Parameter(expansion) => {
+                let value = expand_parameter(shell, expansion)?;
+                match quoted {
+                    true => fields.push(&value, true),
+                    false => fields.push_split(&value),
+                }
+            }
+            WordPart::CommandSubstitution { program, .. } => {
+                let output = command_substitution(shell, program)?;
+                match quoted {
+                    true => fields.push(&output, true),
+                    false => fields.push_split(&output),
+                }
+            }
+            WordPart::Arithmetic(expression) => {
+                let expression = expand_string(shell, &expression.parts)?;
+                let value = arith::evaluate(&expression, &mut shell.variables)?.to_string();
+                match quoted {
+                    true => fields.push(&value, true),
+                    false => fields.push_split(&value),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns whether the `part` is the plain expansion of the special parameter `@`, i.e., `$@` or `${@}`.
+fn is_at(part: &WordPart) -> bool {
+    matches!(
+        part,
+        WordPart::Parameter(ParameterExpansion {
+            name,
+            op: ParameterOp::Value,
+            ..
+        }) if name == "@"
+    )
+}
+
+/// Escapes the characters of `text` that are special in patterns, so that they match only themselves.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '*' | '?' | '[' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+
+    escaped
+}
+
+/// Runs the `program` of a command substitution in a subshell, and returns its output,
+/// without the trailing newlines.
+///
+/// The exit status of the program becomes the value of the special parameter `$?`.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case the program is invalid, or it can't be run.
+///
+/// # References
+/// - [Command Substitution](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_03)
+fn command_substitution(shell: &mut Shell, program: &str) -> Result<String, ExpansionError> {
+    let list = parse_input(program).map_err(|err| err.to_string().trim_end().to_string())?;
+    let output = capture_output(shell, list)
+        .map_err(|err| format!("{err}: Failed to run a command substitution"))?;
+
+    Ok(String::from_utf8_lossy(&output)
+        .trim_end_matches('\n')
+        .to_string())
+}
+
+/// Performs a parameter expansion, and returns its result.
+///
+/// # Errors
+/// - Returns [`ExpansionError`] in case of `${parameter:?word}` with an unset or null parameter,
+///   with the expanded `word` as the message, or a default message if the `word` is empty.
+///   The error is fatal, so a non-interactive shell exits with the exit status `1`.
+/// - Returns [`ExpansionError`] in case of `${parameter:=word}` with a special or a positional parameter,
+///   which can't be assigned.
+///
+/// # References
+/// - [Parameter Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_02)
+fn expand_parameter(
+    shell: &mut Shell,
+    expansion: &ParameterExpansion,
+) -> Result<String, ExpansionError> {
+    let ParameterExpansion { name, op, .. } = expansion;
+    let value = parameter(shell, name);
+    // Whether the parameter is set, and also not null in case of `check_null`
+    let is_set = |check_null: bool| {
+        value
+            .as_deref()
+            .is_some_and(|value| !check_null || !value.is_empty())
+    };
+
+    let expanded = match op {
+        ParameterOp::Value => value.unwrap_or_default(),
+        ParameterOp::Length => value.unwrap_or_default().chars().count().to_string(),
+        ParameterOp::UseDefault { check_null, word } => match is_set(*check_null) {
+            true => value.unwrap_or_default(),
+            false => expand_word(shell, word)?,
+        },
+        ParameterOp::AssignDefault { check_null, word } => match is_set(*check_null) {
+            true => value.unwrap_or_default(),
+            false => {
+                if !is_name(name) {
+                    return Err(format!("${name}: cannot assign in this way").into());
+                }
+                let default = expand_word(shell, word)?;
+                shell.variables.set(name, &default)?;
+                default
+            }
+        },
+        ParameterOp::Error { check_null, word } => match is_set(*check_null) {
+            true => value.unwrap_or_default(),
+            false => {
+                let message = match expand_word(shell, word)? {
+                    message if !message.is_empty() => message,
+                    _ if *check_null => "parameter null or not set".to_string(),
+                    _ => "parameter not set".to_string(),
+                };
+                return Err(ExpansionError::fatal(format!("{name}: {message}")));
+            }
+        },
+        ParameterOp::UseAlternative { check_null, word } => match is_set(*check_null) {
+            true => expand_word(shell, word)?,
+            false => String::new(),
+        },
+        ParameterOp::RemoveSmallestSuffix(word) | ParameterOp::RemoveLargestSuffix(word) => {
+            let pattern = expand_pattern(shell, word)?;
+            let largest = matches!(op, ParameterOp::RemoveLargestSuffix(_));
+            let value = value.unwrap_or_default();
+            pattern::remove_suffix(&pattern, &value, largest).to_string()
+        }
+        ParameterOp::RemoveSmallestPrefix(word) | ParameterOp::RemoveLargestPrefix(word) => {
+            let pattern = expand_pattern(shell, word)?;
+            let largest = matches!(op, ParameterOp::RemoveLargestPrefix(_));
+            let value = value.unwrap_or_default();
+            pattern::remove_prefix(&pattern, &value, largest).to_string()
+        }
+    };
+
+    Ok(expanded)
+}
+
+/// Gets the value of the parameter with the given `name`, or `None` if the parameter isn't set.
+///
+/// The parameter is either a special parameter, a positional parameter, or a shell variable.
+///
+/// # References
+/// - [Positional Parameters](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_05_01)
+/// - [Special Parameters](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_05_02)
+fn parameter(shell: &Shell, name: &str) -> Option<String> {
+    match name {
+        "?" => Some(shell.last_status.to_string()),
+        "$" => Some(shell.pid.to_string()),
+        "!" => shell.last_background_pid.map(|pid| pid.to_string()),
+        "#" => Some(shell.positional.len().to_string()),
+        "@" => Some(shell.positional.join(" ")),
+        "*" => {
+            let separator = match shell.variables.get("IFS") {
+                Some(ifs) => ifs.chars().next().map(String::from).unwrap_or_default(),
+                None => " ".to_string(),
+            };
+            Some(shell.positional.join(&separator))
+        }
+        "0" => Some(shell.name.clone()),
+        _ if name.bytes().all(|byte| byte.is_ascii_digit()) => name
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| shell.positional.get(index.checked_sub(1)?))
+            .cloned(),
+        _ => shell.variables.get(name).map(str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fields, expand_tilde, expand_words};
+    use crate::ast::WordPart;
+    use crate::lex::{Token, tokenize};
+    use crate::shell::Shell;
+
+    #[test]
+    fn expand_tilde_01() {
+        let mut shell = Shell::default();
+        shell.variables.set("HOME", "/home/me").unwrap();
+        shell.variables.set("PWD", "/tmp").unwrap();
+        let literal = |text: &str| WordPart::Literal(text.to_string());
+        let quoted = |text: &str| WordPart::SingleQuoted(text.to_string());
+
+        let parts = vec![literal("~/bin:~+:a~")];
+        let expected = vec![quoted("/home/me"), literal("/bin:~+:a~")];
+        assert_eq!(expected, expand_tilde(&shell, &parts, false));
+
+        let expected = vec![
+            quoted("/home/me"),
+            literal("/bin:"),
+            quoted("/tmp"),
+            literal(":a~"),
+        ];
+        assert_eq!(expected, expand_tilde(&shell, &parts, true));
+
+        let parts = vec![literal("~"), WordPart::DoubleQuoted(vec![literal("/x")])];
+        assert_eq!(parts, expand_tilde(&shell, &parts, false));
+
+        let parts = vec![WordPart::Escaped('~'), literal(":~-")];
+        assert_eq!(parts, expand_tilde(&shell, &parts, true));
+    }
+
+    #[test]
+    fn field_splitting_01() {
+        let split = |ifs: &str, parts: &[(&str, bool)]| {
+            let mut fields = Fields::new(Some(ifs.to_string()));
+            for &(text, expanded) in parts {
+                match expanded {
+                    true => fields.push_split(text),
+                    false => fields.push(text, false),
+                }
+            }
+            fields
+                .finish()
+                .into_iter()
+                .map(|field| field.text)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["a", "b"], split(" \t\n", &[(" a \t b\n", true)]));
+        assert_eq!(
+            vec!["xa", "by"],
+            split(" ", &[("x", false), ("a b", true), ("y", false)])
+        );
+        assert_eq!(vec!["x", "a"], split(" ", &[("x", false), (" a", true)]));
+        assert_eq!(vec!["", "a", "", "b"], split(": ", &[(":a : :b:", true)]));
+        assert_eq!(vec!["a", "c"], split(":", &[("a:", true), ("c", false)]));
+        assert_eq!(vec!["a b"], split("", &[("a b", true)]));
+        assert!(split(" ", &[("  ", true)]).is_empty());
+    }
+
+    #[test]
+    fn positional_parameters_01() {
+        let expand = |input: &str, args: &[&str]| {
+            let mut shell = Shell {
+                positional: args.iter().map(|arg| arg.to_string()).collect(),
+                ..Shell::default()
+            };
+            let words = tokenize(input)
+                .unwrap()
+                .into_iter()
+                .filter_map(|token| match token {
+                    Token::Word(word) => Some(word),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            expand_words(&mut shell, &words).unwrap()
+        };
+
+        assert!(expand(r#""$@""#, &[]).is_empty());
+        assert!(expand(r#""$@" "${@}$@""#, &[]).is_empty());
+        assert_eq!(vec![""], expand(r#""$@""""#, &[]));
+        assert_eq!(vec!["x"], expand(r#""x$@""#, &[]));
+        assert_eq!(vec!["a b"], expand(r#""$@""#, &["a b"]));
+        assert_eq!(vec!["a b", "c"], expand(r#""$@""#, &["a b", "c"]));
+        assert_eq!(vec!["", "x"], expand(r#""$@""#, &["", "x"]));
+        assert_eq!(
+            vec!["<a\tb", " ", "c>"],
+            expand(r#""<$@>""#, &["a\tb", " ", "c"])
+        );
+        assert_eq!(vec!["a", "b", "c"], expand("$@", &["a b", "c"]));
+        assert_eq!(vec!["a b c"], expand(r#""$*""#, &["a b", "c"]));
+    }
+}
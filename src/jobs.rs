@@ -0,0 +1,613 @@
+//! Jobs and job control
+//!
+//! A job is a pipeline, or an AND-OR list, that was started by the shell and is tracked in the job table.
+//! Every process of a job is a child process of the shell.
+//!
+//! Jobs are created by running AND-OR lists asynchronously, in the background, with `&`,
+//! and by stopping foreground jobs, e.g., with `Ctrl-Z`.
+//!
+//! When the shell is interactive, i.e., its standard input is a terminal, job control is enabled:
+//! every job runs in its own process group, and the foreground job owns the terminal.
+//!
+//! # References
+//!
+//! - [Job Control @ Bash Reference Manual](https://www.gnu.org/software/bash/manual/bash.html#Job-Control)
+//! - [Job Control Terms](https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/V1_chap03.html#tag_03_204)
+//! - [Implementing a Job Control Shell @ The GNU C Library](https://www.gnu.org/software/libc/manual/html_node/Implementing-a-Shell.html)
+
+use crate::constants::{STATUS_NOT_FOUND, STATUS_SIGNAL_BASE};
+use crate::shell::Shell;
+use std::ffi::CStr;
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+/// The signals that an interactive shell ignores, and that its child processes reset to their defaults
+pub const JOB_CONTROL_SIGNALS: [libc::c_int; 5] = [
+    libc::SIGINT,
+    libc::SIGQUIT,
+    libc::SIGTSTP,
+    libc::SIGTTIN,
+    libc::SIGTTOU,
+];
+
+/// The state of a process of a job
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProcessState {
+    /// The process is running.
+    Running,
+    /// The process was stopped by the signal.
+    Stopped(libc::c_int),
+    /// The process exited with the exit status.
+    Exited(i32),
+    /// The process was terminated by the signal.
+    Signaled(libc::c_int),
+}
+
+impl ProcessState {
+    /// Converts a status reported by `waitpid` to a process state.
+    fn from_wait_status(status: libc::c_int) -> Self {
+        if libc::WIFSTOPPED(status) {
+            Self::Stopped(libc::WSTOPSIG(status))
+        } else if libc::WIFCONTINUED(status) {
+            Self::Running
+        } else if libc::WIFSIGNALED(status) {
+            Self::Signaled(libc::WTERMSIG(status))
+        } else {
+            Self::Exited(libc::WEXITSTATUS(status))
+        }
+    }
+
+    /// Checks whether the process has terminated.
+    fn is_done(&self) -> bool {
+        matches!(self, Self::Exited(_) | Self::Signaled(_))
+    }
+
+    /// Converts the state to the shell's exit status code.
+    ///
+    /// A process that was stopped or terminated by a signal has the exit status [`STATUS_SIGNAL_BASE`]
+    /// plus the signal number.
+    fn status(&self) -> i32 {
+        match *self {
+            Self::Running => 0,
+            Self::Exited(code) => code,
+            Self::Stopped(signal) | Self::Signaled(signal) => STATUS_SIGNAL_BASE + signal,
+        }
+    }
+}
+
+/// A process of a job
+#[derive(Debug)]
+pub struct Process {
+    /// The process ID
+    pub pid: libc::pid_t,
+    /// The last known state of the process
+    pub state: ProcessState,
+}
+
+/// A job, which consists of one or more processes
+#[derive(Debug)]
+pub struct Job {
+    /// The job number, which is assigned when the job is added to the [`JobTable`]; zero means unassigned
+    id: usize,
+    /// The process group ID, which is the process ID of the first process of the job
+    pgid: libc::pid_t,
+    /// The processes of the job, in the order of the pipeline
+    processes: Vec<Process>,
+    /// The command text of the job, as it is shown to the user
+    command: String,
+    /// Whether the state of the job has changed since the user was last notified about it
+    changed: bool,
+}
+
+impl Job {
+    /// Constructs a new job without any processes, for the given command text.
+    pub fn new(command: String) -> Self {
+        Self {
+            id: 0,
+            pgid: 0,
+            processes: Vec::new(),
+            command,
+            changed: false,
+        }
+    }
+
+    /// Gets the process group ID of the job, which is zero if it doesn't have any processes yet.
+    pub fn pgid(&self) -> libc::pid_t {
+        self.pgid
+    }
+
+    /// Gets the command text of the job.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Gets the process ID of the last process of the job, if there is one.
+    pub fn last_pid(&self) -> Option<libc::pid_t> {
+        self.processes.last().map(|process| process.pid)
+    }
+
+    /// Adds a running process to the job.
+    ///
+    /// The first process of the job is the leader of its process group.
+    pub fn add_process(&mut self, pid: libc::pid_t) {
+        if self.processes.is_empty() {
+            self.pgid = pid;
+        }
+        self.processes.push(Process {
+            pid,
+            state: ProcessState::Running,
+        });
+    }
+
+    /// Checks whether all processes of the job have terminated.
+    pub fn is_done(&self) -> bool {
+        self.processes.iter().all(|process| process.state.is_done())
+    }
+
+    /// Checks whether the job is stopped, i.e., it has a stopped process, and no running ones.
+    pub fn is_stopped(&self) -> bool {
+        !self.is_done()
+            && self
+                .processes
+                .iter()
+                .all(|process| !matches!(process.state, ProcessState::Running))
+    }
+
+    /// Gets the exit status of the job.
+    ///
+    /// If the job is stopped, it is the status of the stopped process;
+    /// otherwise, it is the status of the last process of the job.
+    pub fn status(&self) -> i32 {
+        let stopped = self
+            .processes
+            .iter()
+            .find(|process| matches!(process.state, ProcessState::Stopped(_)));
+
+        match stopped.or(self.processes.last()) {
+            Some(process) => process.state.status(),
+            None => 1,
+        }
+    }
+
+    /// Gets the exit status of the process with the given `pid`, if it is a process of the job.
+    pub fn process_status(&self, pid: libc::pid_t) -> Option<i32> {
+        self.processes
+            .iter()
+            .find(|process| process.pid == pid)
+            .map(|process| process.state.status())
+    }
+
+    /// Checks whether the process with the given `pid` belongs to the job.
+    pub fn has_process(&self, pid: libc::pid_t) -> bool {
+        self.processes.iter().any(|process| process.pid == pid)
+    }
+
+    /// Waits for every process of the job that hasn't terminated yet to change its state,
+    /// and records the new states.
+    ///
+    /// With `WUNTRACED` in `options`, a stopped process counts as a state change,
+    /// so the wait completes when the job stops; with `WNOHANG`, the wait doesn't block.
+    pub fn wait(&mut self, options: libc::c_int) {
+        for process in &mut self.processes {
+            if process.state.is_done() {
+                continue;
+            }
+
+            let mut status = 0;
+            let pid = loop {
+                let pid = unsafe { libc::waitpid(process.pid, &mut status, options) };
+                if pid != -1 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                    break pid;
+                }
+            };
+
+            let state = match pid {
+                0 => continue,
+                // The process doesn't exist anymore, so the shell can't know its exit status.
+                -1 => ProcessState::Exited(STATUS_NOT_FOUND),
+                _ => ProcessState::from_wait_status(status),
+            };
+            if state != process.state {
+                process.state = state;
+                self.changed = true;
+            }
+        }
+    }
+
+    /// Resumes the job by sending `SIGCONT` to its processes, if it is stopped.
+    pub fn resume(&mut self) {
+        if !self.is_stopped() {
+            return;
+        }
+
+        if unsafe { libc::kill(-self.pgid, libc::SIGCONT) } == -1 {
+            for process in &self.processes {
+                unsafe { libc::kill(process.pid, libc::SIGCONT) };
+            }
+        }
+
+        for process in &mut self.processes {
+            if let ProcessState::Stopped(_) = process.state {
+                process.state = ProcessState::Running;
+            }
+        }
+        self.changed = false;
+    }
+
+    /// Describes the state of the job, as in `Running`, `Stopped`, `Done`, `Exit 1` or `Terminated`.
+    fn state_description(&self) -> String {
+        if !self.is_done() {
+            return match self.is_stopped() {
+                true => "Stopped".to_string(),
+                false => "Running".to_string(),
+            };
+        }
+
+        match self.processes.last().map(|process| process.state) {
+            Some(ProcessState::Exited(0)) => "Done".to_string(),
+            Some(ProcessState::Exited(code)) => format!("Exit {code}"),
+            Some(ProcessState::Signaled(signal)) => signal_description(signal),
+            _ => "Done".to_string(),
+        }
+    }
+}
+
+/// Gets the description of the `signal`, e.g., `Terminated` for `SIGTERM`.
+fn signal_description(signal: libc::c_int) -> String {
+    let description = unsafe { libc::strsignal(signal) };
+    if description.is_null() {
+        return format!("Signal {signal}");
+    }
+
+    // SAFETY: `strsignal` returns a valid NUL-terminated string, which is copied right away.
+    unsafe { CStr::from_ptr(description) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The format in which the [`JobTable`] reports a job
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobFormat {
+    /// `[1]+  Running                 sleep 10 &`
+    Short,
+    /// `[1]+ 12345 Running                 sleep 10 &`, i.e., with the process group ID
+    Long,
+    /// `12345`, i.e., only the process group ID
+    Pid,
+}
+
+/// The table of the jobs of the shell
+///
+/// Besides the jobs, it keeps track of the order in which the jobs were started, stopped or resumed,
+/// which determines the current job, `%+`, and the previous job, `%-`.
+#[derive(Debug, Default)]
+pub struct JobTable {
+    /// The jobs, ordered by their job numbers
+    jobs: Vec<Job>,
+    /// The job numbers, from the least to the most recently used job
+    recency: Vec<usize>,
+}
+
+impl JobTable {
+    /// Checks whether the table is empty.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Gets the job numbers of all jobs, in increasing order.
+    pub fn ids(&self) -> Vec<usize> {
+        self.jobs.iter().map(|job| job.id).collect()
+    }
+
+    /// Adds the `job` to the table, which makes it the most recently used job, and returns its job number.
+    ///
+    /// A job that was in the table before, such as a job that was brought to the foreground with `fg`,
+    /// keeps its job number. Otherwise, the job number is one greater than the greatest one in use.
+    pub fn insert(&mut self, mut job: Job) -> usize {
+        if job.id == 0 || self.get(job.id).is_some() {
+            job.id = self.jobs.last().map_or(1, |last| last.id + 1);
+        }
+
+        let id = job.id;
+        let position = self.jobs.partition_point(|other| other.id < id);
+        self.jobs.insert(position, job);
+        self.touch(id);
+
+        id
+    }
+
+    /// Gets the job with the job number `id`.
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    /// Gets the job with the job number `id` for modification.
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Removes the job with the job number `id` from the table, and returns it.
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        self.recency.retain(|&other| other != id);
+        let position = self.jobs.iter().position(|job| job.id == id)?;
+
+        Some(self.jobs.remove(position))
+    }
+
+    /// Makes the job with the job number `id` the most recently used one.
+    pub fn touch(&mut self, id: usize) {
+        self.recency.retain(|&other| other != id);
+        self.recency.push(id);
+    }
+
+    /// Gets the job numbers ordered from the least to the most likely to be the current job.
+    ///
+    /// Stopped jobs take precedence over running ones, and more recently used jobs take precedence
+    /// over less recently used ones.
+    fn ranked(&self) -> Vec<usize> {
+        let mut ranked = self.recency.clone();
+        ranked.sort_by_key(|&id| self.get(id).is_some_and(Job::is_stopped));
+
+        ranked
+    }
+
+    /// Gets the job number of the current job, `%+`, if there is one.
+    pub fn current(&self) -> Option<usize> {
+        self.ranked().last().copied()
+    }
+
+    /// Gets the job number of the previous job, `%-`, if there is one.
+    pub fn previous(&self) -> Option<usize> {
+        self.ranked().iter().rev().nth(1).copied()
+    }
+
+    /// Finds the job with the process with the given `pid`, and returns its job number.
+    pub fn find_pid(&self, pid: libc::pid_t) -> Option<usize> {
+        self.jobs
+            .iter()
+            .find(|job| job.has_process(pid))
+            .map(|job| job.id)
+    }
+
+    /// Finds the job that is identified by the job ID `spec`, and returns its job number.
+    ///
+    /// Supported job IDs are `%%`, `%+` and `%` for the current job, `%-` for the previous job,
+    /// `%n` or `n` for the job number `n`, `%string` for the job whose command begins with `string`,
+    /// and `%?string` for the job whose command contains `string`.
+    ///
+    /// # Errors
+    /// - Returns the error message in case there is no such job, or in case `string` matches more than one job.
+    pub fn find(&self, spec: &str) -> Result<usize, String> {
+        let no_such_job = || format!("{spec}: no such job");
+        let name = spec.strip_prefix('%').unwrap_or(spec);
+
+        let found = match name {
+            "" | "%" | "+" => self.current(),
+            "-" => self.previous(),
+            _ if name.bytes().all(|byte| byte.is_ascii_digit()) => {
+                name.parse().ok().filter(|&id| self.get(id).is_some())
+            }
+            _ if spec.starts_with('%') => {
+                let matches = self
+                    .jobs
+                    .iter()
+                    .filter(|job| match name.strip_prefix('?') {
+                        Some(part) => job.command.contains(part),
+                        None => job.command.starts_with(name),
+                    })
+                    .map(|job| job.id)
+                    .collect::<Vec<_>>();
+                if matches.len() > 1 {
+                    return Err(format!("{spec}: ambiguous job spec"));
+                }
+                matches.first().copied()
+            }
+            _ => None,
+        };
+
+        found.ok_or_else(no_such_job)
+    }
+
+    /// Updates the states of all jobs without blocking.
+    pub fn update(&mut self) {
+        for job in &mut self.jobs {
+            job.wait(libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED);
+        }
+    }
+
+    /// Reports the job with the job number `id` in the given `format`, followed by a newline.
+    pub fn report(&self, id: usize, format: JobFormat) -> String {
+        let Some(job) = self.get(id) else {
+            return String::new();
+        };
+
+        let mark = if Some(id) == self.current() {
+            '+'
+        } else if Some(id) == self.previous() {
+            '-'
+        } else {
+            ' '
+        };
+        let state = job.state_description();
+        let suffix = match job.is_done() || job.is_stopped() {
+            true => "",
+            false => " &",
+        };
+
+        match format {
+            JobFormat::Short => format!("[{id}]{mark}  {state:<24}{}{suffix}\n", job.command),
+            JobFormat::Long => format!(
+                "[{id}]{mark} {} {state:<24}{}{suffix}\n",
+                job.pgid, job.command
+            ),
+            JobFormat::Pid => format!("{}\n", job.pgid),
+        }
+    }
+
+    /// Reports the jobs with the job numbers `ids` in the given `format`.
+    ///
+    /// The user has been notified about the reported jobs, so the ones that have terminated are removed.
+    pub fn report_all(&mut self, ids: &[usize], format: JobFormat) -> String {
+        let report = ids.iter().map(|&id| self.report(id, format)).collect();
+        self.forget_reported(ids);
+
+        report
+    }
+
+    /// Reports the jobs that have terminated or stopped since the user was last notified about them.
+    ///
+    /// This is done before the shell prints its prompt. The jobs that have terminated are removed.
+    pub fn notifications(&mut self) -> String {
+        let ids = self
+            .jobs
+            .iter()
+            .filter(|job| job.changed && (job.is_done() || job.is_stopped()))
+            .map(|job| job.id)
+            .collect::<Vec<_>>();
+
+        self.report_all(&ids, JobFormat::Short)
+    }
+
+    /// Marks the jobs with the job numbers `ids` as reported, and removes the ones that have terminated.
+    fn forget_reported(&mut self, ids: &[usize]) {
+        for &id in ids {
+            let done = match self.get_mut(id) {
+                Some(job) => {
+                    job.changed = false;
+                    job.is_done()
+                }
+                None => false,
+            };
+            if done {
+                self.remove(id);
+            }
+        }
+    }
+}
+
+/// The state of job control of an interactive shell
+pub struct JobControl {
+    /// The controlling terminal, duplicated above the range of file descriptors available to the user,
+    /// so that redirections of the standard streams don't affect it
+    terminal: OwnedFd,
+    /// The process group ID of the shell
+    shell_pgid: libc::pid_t,
+    /// The terminal modes of the shell, which are restored when the shell takes back the terminal
+    tmodes: libc::termios,
+}
+
+impl Debug for JobControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobControl")
+            .field("terminal", &self.terminal)
+            .field("shell_pgid", &self.shell_pgid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JobControl {
+    /// Enables job control, if the standard input of the shell is a terminal.
+    ///
+    /// Waits until the shell is in the foreground, puts the shell in its own process group,
+    /// takes the terminal, and ignores the interactive and job-control signals,
+    /// such as `SIGINT` and `SIGTSTP`, so that they only affect the foreground job.
+    ///
+    /// Returns `None` if the shell isn't interactive, or in case of an error, in which case job control is disabled.
+    pub fn init() -> Option<Self> {
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } == 0 {
+            return None;
+        }
+
+        // Wait until the shell is in the foreground.
+        loop {
+            let pgid = unsafe { libc::getpgrp() };
+            let foreground = unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) };
+            if foreground == -1 {
+                return None;
+            }
+            if foreground == pgid {
+                break;
+            }
+            unsafe { libc::kill(-pgid, libc::SIGTTIN) };
+        }
+
+        for signal in JOB_CONTROL_SIGNALS {
+            unsafe { libc::signal(signal, libc::SIG_IGN) };
+        }
+
+        let shell_pgid = unsafe { libc::getpid() };
+        // This fails if the shell is a session leader, in which case it already leads its process group.
+        unsafe { libc::setpgid(0, 0) };
+        let shell_pgid = match unsafe { libc::getpgrp() } {
+            pgid if pgid == shell_pgid => pgid,
+            _ => return None,
+        };
+
+        let terminal = unsafe { libc::fcntl(libc::STDIN_FILENO, libc::F_DUPFD_CLOEXEC, 10) };
+        if terminal == -1 {
+            return None;
+        }
+        // SAFETY: `terminal` is a freshly duplicated file descriptor that nothing else owns.
+        let terminal = unsafe { OwnedFd::from_raw_fd(terminal) };
+
+        let mut tmodes = MaybeUninit::<libc::termios>::uninit();
+        if unsafe { libc::tcgetattr(terminal.as_raw_fd(), tmodes.as_mut_ptr()) } == -1 {
+            return None;
+        }
+        // SAFETY: `tcgetattr` succeeded, so it has initialized the terminal modes.
+        let tmodes = unsafe { tmodes.assume_init() };
+
+        let job_control = Self {
+            terminal,
+            shell_pgid,
+            tmodes,
+        };
+        job_control.give_terminal_to(shell_pgid);
+
+        Some(job_control)
+    }
+
+    /// Gets the file descriptor of the controlling terminal.
+    pub fn terminal(&self) -> RawFd {
+        self.terminal.as_raw_fd()
+    }
+
+    /// Puts the process group `pgid` in the foreground of the terminal.
+    pub fn give_terminal_to(&self, pgid: libc::pid_t) {
+        unsafe { libc::tcsetpgrp(self.terminal(), pgid) };
+    }
+
+    /// Puts the shell back in the foreground of the terminal, and restores its terminal modes.
+    pub fn take_terminal(&self) {
+        self.give_terminal_to(self.shell_pgid);
+        unsafe { libc::tcsetattr(self.terminal(), libc::TCSADRAIN, &self.tmodes) };
+    }
+}
+
+/// Runs the `job` in the foreground: waits for it to terminate or to stop, and returns its exit status.
+///
+/// With job control, the job gets the terminal for the duration of the wait.
+///
+/// A job that stops is added to the job table of the `shell`, and the user is notified about it.
+pub fn wait_for_job(shell: &mut Shell, mut job: Job) -> i32 {
+    if let Some(job_control) = &shell.job_control {
+        job_control.give_terminal_to(job.pgid);
+    }
+
+    job.wait(libc::WUNTRACED);
+
+    if let Some(job_control) = &shell.job_control {
+        job_control.take_terminal();
+    }
+
+    let status = job.status();
+    if job.is_stopped() {
+        let id = shell.jobs.insert(job);
+        let report = shell.jobs.report_all(&[id], JobFormat::Short);
+        eprint!("\n{report}");
+    }
+
+    status
+}
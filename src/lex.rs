@@ -0,0 +1,1035 @@
+//! Tokenizer for the user input
+//!
+//! Splits the input into tokens: words, I/O numbers, operators and newlines,
+//! which are then parsed by [`crate::parse::parse_input`].
+//!
+//! Quotes are kept in the parts of the [`Word`]s, because quoting affects the later expansions.
+//!
+//! The bodies of here-documents are read here, because they follow the newline after the line
+//! that contains the here-document operator.
+//!
+//! # References
+//!
+//! - [Token Recognition](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_03)
+//! - [Quoting](https://www.gnu.org/software/bash/manual/bash.html#Quoting)
+//! - Enclosing characters in single quotes preserves the literal value of each character within the quotes.
+//!   [Single Quotes](https://www.gnu.org/software/bash/manual/bash.html#Single-Quotes)
+//! - Enclosing characters in double quotes preserves the literal value of each character within the quotes except `\`.
+//!   The backslash retains its special meaning when followed by `\`, `$`, `` ` ``, `"` or newline.
+//!   [Double Quotes](https://www.gnu.org/software/bash/manual/bash.html#Double-Quotes)
+//! - A non-quoted backslash `\` is treated as an escape character.
+//!   It preserves the literal value of the next character.
+//!   [Escape Character](https://www.gnu.org/software/bash/manual/bash.html#Escape-Character)
+//! - The character `$` introduces a parameter expansion, e.g., `$HOME`, `$?` or `${HOME:-/}`.
+//!   It is recognized outside of quotes and inside double quotes.
+//!   [Shell Parameter Expansion](https://www.gnu.org/software/bash/manual/bash.html#Shell-Parameter-Expansion)
+//! - A command substitution, `$(program)` or `` `program` ``, is recognized outside of quotes and inside double quotes.
+//!   Its program is checked for syntax errors right away.
+//!   [Command Substitution](https://www.gnu.org/software/bash/manual/bash.html#Command-Substitution)
+
+use crate::ast::{ParameterExpansion, ParameterOp, Word, WordPart};
+use crate::errors::InvalidInputError;
+use crate::parse::{parse_input, parse_tokens};
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::mem;
+use std::str::Chars;
+
+/// A token of the input
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    /// A word, which can be a command name, an argument, a redirection target, or a reserved word
+    Word(Word),
+    /// A number that immediately precedes a redirection operator, e.g., `2` in `2>file`
+    IoNumber(i32),
+    /// An operator
+    Operator(Operator),
+    /// A here-document operator, `<<` or `<<-`, together with its delimiter and its body
+    HereDocument {
+        /// Whether leading tab characters are stripped from the body lines, which is the case with `<<-`
+        strip_tabs: bool,
+        /// The delimiter, as it was written
+        delimiter: Word,
+        /// The body, which is expanded if no part of the delimiter was quoted
+        body: Word,
+    },
+    /// A newline, which terminates a command like `;` does
+    Newline,
+}
+
+/// An operator, which is either a control operator or a redirection operator
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    /// `&&`
+    AndIf,
+    /// `||`
+    OrIf,
+    /// `;;`
+    DoubleSemicolon,
+    /// `;`
+    Semicolon,
+    /// `&`
+    Ampersand,
+    /// `|`
+    Pipe,
+    /// `(`
+    LeftParen,
+    /// `)`
+    RightParen,
+    /// `<`
+    Less,
+    /// `>`
+    Great,
+    /// `>>`
+    DoubleGreat,
+    /// `<&`
+    LessAnd,
+    /// `>&`
+    GreatAnd,
+    /// `<>`
+    LessGreat,
+    /// `>|`
+    Clobber,
+    /// Shell-specific `<<<`
+    TripleLess,
+    /// Shell-specific `&>`
+    AndGreat,
+    /// Shell-specific `&>>`
+    AndDoubleGreat,
+}
+
+/// The operators, sorted so that the longer operators come before the shorter ones with the same prefix
+///
+/// The here-document operators, `<<` and `<<-`, are handled separately, because they are followed by a delimiter.
+const OPERATORS: [(&str, Operator); 18] = [
+    ("&>>", Operator::AndDoubleGreat),
+    ("<<<", Operator::TripleLess),
+    ("&&", Operator::AndIf),
+    ("||", Operator::OrIf),
+    (";;", Operator::DoubleSemicolon),
+    (">>", Operator::DoubleGreat),
+    ("<&", Operator::LessAnd),
+    (">&", Operator::GreatAnd),
+    ("<>", Operator::LessGreat),
+    (">|", Operator::Clobber),
+    ("&>", Operator::AndGreat),
+    (";", Operator::Semicolon),
+    ("&", Operator::Ampersand),
+    ("|", Operator::Pipe),
+    ("(", Operator::LeftParen),
+    (")", Operator::RightParen),
+    ("<", Operator::Less),
+    (">", Operator::Great),
+];
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (op, _) = OPERATORS
+            .iter()
+            .find(|(_, operator)| operator == self)
+            .expect("Every operator is in the table");
+
+        write!(f, "{op}")
+    }
+}
+
+/// Formats the token the way it is shown in syntax error messages.
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(word) => write!(f, "{word}"),
+            Self::IoNumber(fd) => write!(f, "{fd}"),
+            Self::Operator(op) => write!(f, "{op}"),
+            Self::HereDocument {
+                strip_tabs: false, ..
+            } => write!(f, "<<"),
+            Self::HereDocument {
+                strip_tabs: true, ..
+            } => write!(f, "<<-"),
+            Self::Newline => write!(f, "newline"),
+        }
+    }
+}
+
+/// Error message for an unmatched single quote
+const UNMATCHED_SINGLE_QUOTES: &str = "unmatched single quotes\n";
+/// Error message for an unmatched double quote
+const UNMATCHED_DOUBLE_QUOTES: &str = "unmatched double quotes\n";
+/// Error message for a backslash at the end of input
+const UNMATCHED_ESCAPE: &str = "unmatched escape character\n";
+/// Error message for a parameter expansion without the closing brace
+const UNMATCHED_BRACE: &str = "unmatched brace in parameter expansion\n";
+/// Error message for a command substitution without the closing parenthesis
+const UNMATCHED_PARENTHESIS: &str = "unmatched parenthesis in command substitution\n";
+/// Error message for a backquoted command substitution without the closing backquote
+const UNMATCHED_BACKQUOTE: &str = "unmatched backquote\n";
+/// Error message for an invalid parameter name or modifier in a parameter expansion
+const BAD_SUBSTITUTION: &str = "bad substitution\n";
+
+/// Splits the `input` into tokens.
+///
+/// Blanks separate tokens, and they are otherwise discarded, as are comments, which start with `#`
+/// at the beginning of a word, and escaped newlines.
+///
+/// # Errors
+/// - Returns incomplete [`InvalidInputError`] in case of unmatched quotes, an unterminated expansion,
+///   or a trailing escape character, which more input may complete.
+/// - Returns [`InvalidInputError`] in case a here-document operator isn't followed by a delimiter.
+/// - Returns incomplete [`InvalidInputError`] in case the body of a here-document hasn't been entered yet,
+///   in which case the user should enter more input.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, InvalidInputError> {
+    let mut lexer = Lexer {
+        input: input.chars().peekable(),
+        tokens: Vec::new(),
+        pending_here_docs: Vec::new(),
+        nesting: None,
+    };
+
+    lexer.run()?;
+    lexer.check_here_documents()?;
+
+    Ok(lexer.tokens)
+}
+
+/// The state of the tokenizer
+struct Lexer<'a> {
+    /// The remaining input
+    input: Peekable<Chars<'a>>,
+    /// The tokens that have been recognized so far
+    tokens: Vec<Token>,
+    /// Indices of the here-document tokens whose bodies haven't been read yet
+    pending_here_docs: Vec<usize>,
+    /// The nesting in the program of a command substitution, which ends at the `)` that closes it,
+    /// or `None` if the tokens continue up to the end of input
+    nesting: Option<Nesting>,
+}
+
+impl Lexer<'_> {
+    /// Recognizes all tokens of the input, or of the program of a command substitution,
+    /// up to and including the `)` that closes it.
+    ///
+    /// Returns whether the closing `)` of the command substitution was found.
+    fn run(&mut self) -> Result<bool, InvalidInputError> {
+        while let Some(&ch) = self.input.peek() {
+            match ch {
+                ' ' | '\t' => {
+                    self.input.next();
+                }
+                '\n' => {
+                    self.input.next();
+                    self.push(Token::Newline);
+                    self.read_here_document_bodies()?;
+                }
+                ')' if self.nesting.as_ref().is_some_and(Nesting::closes) => {
+                    self.input.next();
+                    return Ok(true);
+                }
+                '#' => while self.input.next_if(|&ch| ch != '\n').is_some() {},
+                '\\' if self.peek_second() == Some('\n') => {
+                    self.input.next();
+                    self.input.next();
+                }
+                '&' | '|' | ';' | '<' | '>' | '(' | ')' => self.operator()?,
+                _ => {
+                    let word = word(&mut self.input, WordEnd::Delimiter)?;
+                    let token = match word.as_literal() {
+                        Some(text)
+                            if text.bytes().all(|byte| byte.is_ascii_digit())
+                                && matches!(self.input.peek(), Some('<' | '>')) =>
+                        {
+                            Token::IoNumber(text.parse().unwrap_or(i32::MAX))
+                        }
+                        _ => Token::Word(word),
+                    };
+                    self.push(token);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Adds the `token`, and keeps track of the nesting in the program of a command substitution.
+    fn push(&mut self, token: Token) {
+        if let Some(nesting) = &mut self.nesting {
+            nesting.update(&token);
+        }
+        self.tokens.push(token);
+    }
+
+    /// Checks whether the bodies of all here-documents have been read.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the body of a here-document hasn't been entered yet.
+    fn check_here_documents(&self) -> Result<(), InvalidInputError> {
+        let pending = self
+            .pending_here_docs
+            .first()
+            .map(|&index| &self.tokens[index]);
+        if let Some(Token::HereDocument { delimiter, .. }) = pending {
+            return Err(InvalidInputError::incomplete(&format!(
+                "here-document delimited by end of input (wanted `{}')\n",
+                delimiter.unquoted_text()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Peeks at the character after the next one.
+    fn peek_second(&self) -> Option<char> {
+        let mut input = self.input.clone();
+        input.next();
+        input.next()
+    }
+
+    /// Recognizes the longest operator at the current position.
+    ///
+    /// A here-document operator is followed by its delimiter, which is read here, too.
+    fn operator(&mut self) -> Result<(), InvalidInputError> {
+        let rest = self.input.clone().take(3).collect::<String>();
+
+        if rest.starts_with("<<") && !rest.starts_with("<<<") {
+            self.input.next();
+            self.input.next();
+            let strip_tabs = self.input.next_if_eq(&'-').is_some();
+            return self.here_document(strip_tabs);
+        }
+
+        let &(op, operator) = OPERATORS
+            .iter()
+            .find(|(op, _)| rest.starts_with(op))
+            .expect("The first character starts an operator");
+        for _ in 0..op.len() {
+            self.input.next();
+        }
+        self.push(Token::Operator(operator));
+
+        Ok(())
+    }
+
+    /// Reads the delimiter of a here-document, which follows `<<` or `<<-`,
+    /// and adds the here-document, whose body is read after the next newline.
+    ///
+    /// # Errors
+    /// - Returns [`InvalidInputError`] in case the delimiter is missing.
+    fn here_document(&mut self, strip_tabs: bool) -> Result<(), InvalidInputError> {
+        while self.input.next_if(|&ch| ch == ' ' || ch == '\t').is_some() {}
+
+        let delimiter = match self.input.peek() {
+            None | Some('\n' | '&' | '|' | ';' | '<' | '>' | '(' | ')') => {
+                let token = match self.input.peek() {
+                    None | Some('\n') => "newline".to_string(),
+                    Some(ch) => ch.to_string(),
+                };
+                return Err(format!("syntax error near unexpected token `{token}'\n")
+                    .as_str()
+                    .into());
+            }
+            Some(_) => word(&mut self.input, WordEnd::Delimiter)?,
+        };
+
+        self.pending_here_docs.push(self.tokens.len());
+        self.push(Token::HereDocument {
+            strip_tabs,
+            delimiter,
+            body: Word::default(),
+        });
+
+        Ok(())
+    }
+
+    /// Reads the bodies of the pending here-documents.
+    ///
+    /// This is done after a newline, because the body of a here-document consists of the lines that follow
+    /// the line with the here-document operator, up to a line that consists of the delimiter only.
+    ///
+    /// With `<<-`, leading tab characters are stripped from the body lines and from the delimiter line.
+    ///
+    /// If no part of the delimiter was quoted, the body is treated as if it were in double quotes,
+    /// except that double quotes aren't special, so a backslash quotes only the characters `\`, `$` and `` ` ``.
+    /// Otherwise, the body isn't expanded.
+    ///
+    /// A here-document whose delimiter line hasn't been entered yet stays pending.
+    ///
+    /// # Errors
+    /// - Returns [`InvalidInputError`] in case of an invalid or unterminated parameter expansion in a body.
+    fn read_here_document_bodies(&mut self) -> Result<(), InvalidInputError> {
+        let pending = mem::take(&mut self.pending_here_docs);
+
+        for (idx, &index) in pending.iter().enumerate() {
+            let Token::HereDocument {
+                strip_tabs,
+                delimiter,
+                body,
+            } = &mut self.tokens[index]
+            else {
+                continue;
+            };
+            let wanted = delimiter.unquoted_text();
+
+            let mut text = String::new();
+            let mut found = false;
+            while self.input.peek().is_some() {
+                let mut line = String::new();
+                let mut ended = true;
+                for ch in self.input.by_ref() {
+                    if ch == '\n' {
+                        ended = false;
+                        break;
+                    }
+                    line.push(ch);
+                }
+
+                let line = match strip_tabs {
+                    true => line.trim_start_matches('\t'),
+                    false => line.as_str(),
+                };
+                if line == wanted {
+                    found = true;
+                    break;
+                }
+                if ended {
+                    break;
+                }
+
+                text.push_str(line);
+                text.push('\n');
+            }
+
+            if !found {
+                self.pending_here_docs.extend(&pending[idx..]);
+                return Ok(());
+            }
+
+            *body = match delimiter.is_quoted() {
+                true => Word {
+                    parts: vec![WordPart::SingleQuoted(text)],
+                },
+                false => Word {
+                    // The body is complete, so more input can't complete an unterminated expansion in it.
+                    parts: vec![WordPart::DoubleQuoted(
+                        here_document_parts(&text)
+                            .map_err(|err| InvalidInputError::from(err.reason.as_str()))?,
+                    )],
+                },
+            };
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a word ends
+#[derive(Clone, Copy, PartialEq)]
+enum WordEnd {
+    /// At an unquoted blank, newline, or at the start of an operator, which is the case for ordinary words
+    Delimiter,
+    /// At an unquoted `}`, which isn't consumed, in case of the word of a parameter expansion modifier
+    Brace,
+    /// Like [`WordEnd::Brace`], but in case of a parameter expansion in double quotes,
+    /// where single quotes aren't special, and a backslash quotes only `\`, `$`, `` ` ``, `"` and `}`
+    QuotedBrace,
+    /// At the end of input, in case of the expression of an arithmetic expansion
+    EndOfInput,
+}
+
+/// Recognizes a word at the current position of the `input`, which ends according to `end`.
+///
+/// # Errors
+/// - Returns incomplete [`InvalidInputError`] in case of unmatched quotes or a trailing escape character.
+/// - Returns [`InvalidInputError`] in case of an invalid or unterminated expansion.
+fn word(input: &mut Peekable<Chars>, end: WordEnd) -> Result<Word, InvalidInputError> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+
+    loop {
+        let Some(&ch) = input.peek() else {
+            match end {
+                WordEnd::Brace | WordEnd::QuotedBrace => {
+                    return Err(InvalidInputError::incomplete(UNMATCHED_BRACE));
+                }
+                _ => break,
+            }
+        };
+        match ch {
+            '}' if matches!(end, WordEnd::Brace | WordEnd::QuotedBrace) => break,
+            ' ' | '\t' | '\n' | '&' | '|' | ';' | '<' | '>' | '(' | ')'
+                if end == WordEnd::Delimiter =>
+            {
+                break;
+            }
+            '\'' if end != WordEnd::QuotedBrace => {
+                input.next();
+                let mut text = String::new();
+                loop {
+                    match input.next() {
+                        Some('\'') => break,
+                        Some(ch) => text.push(ch),
+                        None => return Err(InvalidInputError::incomplete(UNMATCHED_SINGLE_QUOTES)),
+                    }
+                }
+                push_literal(&mut parts, &mut literal);
+                parts.push(WordPart::SingleQuoted(text));
+            }
+            '"' => {
+                input.next();
+                let quoted = double_quoted(input)?;
+                push_literal(&mut parts, &mut literal);
+                parts.push(WordPart::DoubleQuoted(quoted));
+            }
+            '\\' if end == WordEnd::QuotedBrace => {
+                input.next();
+                match input.next() {
+                    Some('\n') => {}
+                    Some(ch @ ('\\' | '$' | '`' | '"' | '}')) => {
+                        push_literal(&mut parts, &mut literal);
+                        parts.push(WordPart::Escaped(ch));
+                    }
+                    Some(ch) => {
+                        literal.push('\\');
+                        literal.push(ch);
+                    }
+                    None => return Err(InvalidInputError::incomplete(UNMATCHED_ESCAPE)),
+                }
+            }
+            '\\' => {
+                input.next();
+                match input.next() {
+                    // An escaped newline is a line continuation, which is removed.
+                    Some('\n') => {}
+                    Some(ch) => {
+                        push_literal(&mut parts, &mut literal);
+                        parts.push(WordPart::Escaped(ch));
+                    }
+                    None => return Err(InvalidInputError::incomplete(UNMATCHED_ESCAPE)),
+                }
+            }
+            '$' => {
+                input.next();
+                match expansion(input, end == WordEnd::QuotedBrace)? {
+                    Some(part) => {
+                        push_literal(&mut parts, &mut literal);
+                        parts.push(part);
+                    }
+                    None => literal.push(ch),
+                }
+            }
+            '`' => {
+                input.next();
+                let program = backquoted(input, false)?;
+                push_literal(&mut parts, &mut literal);
+                parts.push(program);
+            }
+            _ => {
+                input.next();
+                literal.push(ch);
+            }
+        }
+    }
+
+    push_literal(&mut parts, &mut literal);
+
+    Ok(Word { parts })
+}
+
+/// Recognizes the parts of a double-quoted string, whose opening `"` has already been consumed,
+/// up to and including the closing `"`.
+///
+/// # Errors
+/// - Returns incomplete [`InvalidInputError`] in case the closing `"` is missing.
+/// - Returns [`InvalidInputError`] in case of an invalid or unterminated parameter expansion.
+fn double_quoted(input: &mut Peekable<Chars>) -> Result<Vec<WordPart>, InvalidInputError> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+
+    loop {
+        match input.next() {
+            Some('"') => break,
+            Some('\\') => match input.next() {
+                Some('\n') => {}
+                Some(ch @ ('\\' | '$' | '`' | '"')) => {
+                    push_literal(&mut parts, &mut literal);
+                    parts.push(WordPart::Escaped(ch));
+                }
+                Some(ch) => {
+                    literal.push('\\');
+                    literal.push(ch);
+                }
+                None => return Err(InvalidInputError::incomplete(UNMATCHED_ESCAPE)),
+            },
+            Some('$') => match expansion(input, true)? {
+                Some(part) => {
+                    push_literal(&mut parts, &mut literal);
+                    parts.push(part);
+                }
+                None => literal.push('$'),
+            },
+            Some('`') => {
+                let program = backquoted(input, true)?;
+                push_literal(&mut parts, &mut literal);
+                parts.push(program);
+            }
+            Some(ch) => literal.push(ch),
+            None => return Err(InvalidInputError::incomplete(UNMATCHED_DOUBLE_QUOTES)),
+        }
+    }
+
+    push_literal(&mut parts, &mut literal);
+
+    Ok(parts)
+}
+
+/// Recognizes an expansion after `$`, which has already been consumed:
+/// an arithmetic expansion, `$((expression))`, a command substitution, `$(program)`, or a parameter expansion.
+///
+/// `$((` starts a command substitution instead of an arithmetic expansion if the parentheses
+/// don't end with `))`, e.g., `$((cd dir); ls)`.
+///
+/// Returns `None` if `$` isn't followed by an expansion, in which case it is literal.
+///
+/// The expansion is `in_double_quotes` in a double-quoted string, or in the body of a here-document.
+///
+/// # Errors
+/// - Returns [`InvalidInputError`] in case of an invalid or unterminated expansion.
+fn expansion(
+    input: &mut Peekable<Chars>,
+    in_double_quotes: bool,
+) -> Result<Option<WordPart>, InvalidInputError> {
+    if input.next_if_eq(&'(').is_some() {
+        if input.peek() == Some(&'(')
+            && let Some(part) = arithmetic(input)?
+        {
+            return Ok(Some(part));
+        }
+        return command_substitution(input).map(Some);
+    }
+
+    parameter(input, in_double_quotes)
+}
+
+/// Recognizes an arithmetic expansion, whose opening `$(` has already been consumed, and which continues
+/// with `(`, up to and including the closing `))`.
+///
+/// The expression is a word, whose parameter expansions and command substitutions are performed
+/// before it is evaluated.
+///
+/// Returns `None`, without consuming anything, if the parentheses don't end with `))`.
+///
+/// # Errors
+/// - Returns [`InvalidInputError`] in case of an invalid expansion in the expression.
+fn arithmetic(input: &mut Peekable<Chars>) -> Result<Option<WordPart>, InvalidInputError> {
+    let mut rest = input.clone();
+    rest.next();
+    let mut expression = String::new();
+    let mut depth = 0;
+
+    loop {
+        let Some(ch) = rest.next() else {
+            return Ok(None);
+        };
+        match ch {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ')' if rest.next_if_eq(&')').is_some() => break,
+            ')' => return Ok(None),
+            _ => {}
+        }
+        expression.push(ch);
+    }
+    *input = rest;
+
+    let expression = word(&mut expression.chars().peekable(), WordEnd::EndOfInput)?;
+
+    Ok(Some(WordPart::Arithmetic(expression)))
+}
+
+/// Recognizes a command substitution, whose opening `$(` has already been consumed,
+/// up to and including the closing `)`.
+///
+/// The program is split into tokens in a single pass, which ends at the `)` that neither closes a `(`
+/// nor ends a pattern of a `case` clause, so a `)` that is quoted, escaped, in a comment, in a here-document,
+/// or in a nested expansion doesn't end the program either. The tokens are then parsed.
+///
+/// # Errors
+/// - Returns incomplete [`InvalidInputError`] in case the closing `)` is missing.
+/// - Returns [`InvalidInputError`] in case the program is invalid.
+fn command_substitution(input: &mut Peekable<Chars>) -> Result<WordPart, InvalidInputError> {
+    let start = input.clone();
+    let mut lexer = Lexer {
+        input: input.clone(),
+        tokens: Vec::new(),
+        pending_here_docs: Vec::new(),
+        nesting: Some(Nesting::new()),
+    };
+
+    if !lexer.run()? {
+        return Err(InvalidInputError::incomplete(UNMATCHED_PARENTHESIS));
+    }
+    lexer.check_here_documents()?;
+
+    // The program is the text up to the closing `)`, as it was written.
+    let consumed = start.clone().count() - lexer.input.clone().count();
+    let program = start.take(consumed - 1).collect();
+    *input = lexer.input;
+
+    if let Err(err) = parse_tokens(lexer.tokens) {
+        return Err(err.reason.as_str().into());
+    }
+
+    Ok(WordPart::CommandSubstitution {
+        program,
+        backquoted: false,
+    })
+}
+
+/// Where the tokens of a `case` clause are
+#[derive(Clone, Copy, PartialEq)]
+enum CaseState {
+    /// Right after `case`, where the word that is matched follows
+    Word,
+    /// After the word that is matched, where `in` follows
+    In,
+    /// Where a pattern follows, which ends with `)`
+    Pattern,
+    /// In the body of an item, which ends with `;;`
+    Body,
+}
+
+/// The nesting of parentheses and `case` clauses in the program of a command substitution,
+/// which determines the `)` that closes the command substitution
+struct Nesting {
+    /// The number of `(` that haven't been closed yet
+    depth: usize,
+    /// The states of the `case` clauses that haven't ended yet, with the innermost one last
+    cases: Vec<CaseState>,
+    /// Whether the next token is at the start of a command, where reserved words are recognized
+    command_start: bool,
+}
+
+impl Nesting {
+    /// Constructs a new instance for the start of the program.
+    fn new() -> Self {
+        Self {
+            depth: 0,
+            cases: Vec::new(),
+            command_start: true,
+        }
+    }
+
+    /// Checks whether a `)` closes the command substitution, i.e., it neither closes a `(` nor ends a pattern.
+    fn closes(&self) -> bool {
+        self.depth == 0 && self.cases.last() != Some(&CaseState::Pattern)
+    }
+
+    /// Updates the nesting with the next `token` of the program.
+    fn update(&mut self, token: &Token) {
+        let state = self.cases.last().copied();
+        let command_start = mem::replace(&mut self.command_start, false);
+
+        match token {
+            Token::Word(word) => match (state, word.as_literal()) {
+                (Some(CaseState::Word), _) => self.set_case_state(CaseState::In),
+                (Some(CaseState::In), Some("in")) => self.set_case_state(CaseState::Pattern),
+                (Some(CaseState::Pattern), Some("esac")) => {
+                    self.cases.pop();
+                }
+                (Some(CaseState::Pattern), _) => {}
+                (Some(CaseState::Body), Some("esac")) if command_start => {
+                    self.cases.pop();
+                }
+                (_, Some("case")) if command_start => self.cases.push(CaseState::Word),
+                (
+                    _,
+                    Some("!" | "{" | "do" | "elif" | "else" | "if" | "then" | "until" | "while"),
+                ) if command_start => self.command_start = true,
+                _ => {}
+            },
+            Token::Operator(Operator::LeftParen) if state != Some(CaseState::Pattern) => {
+                self.depth += 1;
+            }
+            Token::Operator(Operator::RightParen) if state == Some(CaseState::Pattern) => {
+                self.set_case_state(CaseState::Body);
+            }
+            Token::Operator(Operator::RightParen) => self.depth = self.depth.saturating_sub(1),
+            Token::Operator(Operator::DoubleSemicolon) if state == Some(CaseState::Body) => {
+                self.set_case_state(CaseState::Pattern);
+            }
+            _ => {}
+        }
+
+        if matches!(
+            token,
+            Token::Newline
+                | Token::Operator(
+                    Operator::AndIf
+                        | Operator::OrIf
+                        | Operator::DoubleSemicolon
+                        | Operator::Semicolon
+                        | Operator::Ampersand
+                        | Operator::Pipe
+                        | Operator::LeftParen
+                        | Operator::RightParen
+                )
+        ) {
+            self.command_start = true;
+        }
+    }
+
+    /// Sets the state of the innermost `case` clause.
+    fn set_case_state(&mut self, state: CaseState) {
+        if let Some(last) = self.cases.last_mut() {
+            *last = state;
+        }
+    }
+}
+
+/// Recognizes a backquoted command substitution, whose opening `` ` `` has already been consumed,
+/// up to and including the closing `` ` ``.
+///
+/// Inside the backquotes, a backslash retains its special meaning only when it is followed by
+/// `$`, `` ` `` or `\`, or by `"` when the command substitution is `in_double_quotes`.
+///
+/// # Errors
+/// - Returns incomplete [`InvalidInputError`] in case the closing `` ` `` is missing.
+/// - Returns [`InvalidInputError`] in case the program is invalid.
+fn backquoted(
+    input: &mut Peekable<Chars>,
+    in_double_quotes: bool,
+) -> Result<WordPart, InvalidInputError> {
+    let mut program = String::new();
+
+    loop {
+        match input.next() {
+            Some('`') => break,
+            Some('\\') => match input.next() {
+                Some(ch @ ('$' | '`' | '\\')) => program.push(ch),
+                Some('"') if in_double_quotes => program.push('"'),
+                Some(ch) => {
+                    program.push('\\');
+                    program.push(ch);
+                }
+                None => return Err(InvalidInputError::incomplete(UNMATCHED_ESCAPE)),
+            },
+            Some(ch) => program.push(ch),
+            None => return Err(InvalidInputError::incomplete(UNMATCHED_BACKQUOTE)),
+        }
+    }
+
+    check_program(&program)?;
+
+    Ok(WordPart::CommandSubstitution {
+        program,
+        backquoted: true,
+    })
+}
+
+/// Checks whether the `program` of a command substitution is valid, so that syntax errors are reported
+/// when the input is parsed, and not when the command substitution is run.
+///
+/// # Errors
+/// - Returns [`InvalidInputError`] in case the program is invalid or incomplete.
+fn check_program(program: &str) -> Result<(), InvalidInputError> {
+    match parse_input(program) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.reason.as_str().into()),
+    }
+}
+
+/// Recognizes a parameter expansion after `$`, which has already been consumed.
+///
+/// Without braces, the parameter name is the longest valid variable name, or a single character
+/// in case of a special or a positional parameter, so `$10` is `$1` followed by `0`.
+///
+/// Returns `None` if `$` isn't followed by a parameter, in which case it is literal.
+///
+/// # Errors
+/// - Returns [`InvalidInputError`] in case of an invalid or unterminated parameter expansion in braces.
+fn parameter(
+    input: &mut Peekable<Chars>,
+    in_double_quotes: bool,
+) -> Result<Option<WordPart>, InvalidInputError> {
+    let name = match input.peek() {
+        Some('{') => {
+            input.next();
+            return braced_parameter(input, in_double_quotes)
+                .map(|expansion| Some(WordPart::Parameter(expansion)));
+        }
+        Some(&ch) if is_special_parameter(ch) || ch.is_ascii_digit() => {
+            input.next();
+            ch.to_string()
+        }
+        Some(&ch) if ch == '_' || ch.is_ascii_alphabetic() => name(input),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(WordPart::Parameter(ParameterExpansion {
+        name,
+        op: ParameterOp::Value,
+        braced: false,
+    })))
+}
+
+/// Recognizes a parameter expansion in braces, whose opening `${` has already been consumed,
+/// up to and including the closing `}`.
+///
+/// The word of a modifier follows the quoting rules of double quotes if the expansion is `in_double_quotes`,
+/// so that `"${unset:-'a'}"` expands to `'a'`.
+///
+/// # Errors
+/// - Returns [`InvalidInputError`] in case of an invalid parameter name or modifier.
+/// - Returns incomplete [`InvalidInputError`] in case of a missing `}`.
+fn braced_parameter(
+    input: &mut Peekable<Chars>,
+    in_double_quotes: bool,
+) -> Result<ParameterExpansion, InvalidInputError> {
+    let end = match in_double_quotes {
+        true => WordEnd::QuotedBrace,
+        false => WordEnd::Brace,
+    };
+
+    // `${#}` is the number of positional parameters, while `${#parameter}` is the length of the parameter.
+    let mut rest = input.clone();
+    if rest.next() == Some('#') && rest.peek().is_some_and(|&ch| ch != '}' && ch != ':') {
+        input.next();
+        let name = braced_name(input)?;
+        return match input.next() {
+            Some('}') => Ok(ParameterExpansion {
+                name,
+                op: ParameterOp::Length,
+                braced: true,
+            }),
+            Some(_) => Err(BAD_SUBSTITUTION.into()),
+            None => Err(InvalidInputError::incomplete(UNMATCHED_BRACE)),
+        };
+    }
+
+    let name = braced_name(input)?;
+
+    let check_null = input.next_if_eq(&':').is_some();
+    let op = match input.next() {
+        Some('}') if !check_null => ParameterOp::Value,
+        Some(op @ ('-' | '=' | '?' | '+')) => {
+            let word = word(input, end)?;
+            input.next();
+            match op {
+                '-' => ParameterOp::UseDefault { check_null, word },
+                '=' => ParameterOp::AssignDefault { check_null, word },
+                '?' => ParameterOp::Error { check_null, word },
+                _ => ParameterOp::UseAlternative { check_null, word },
+            }
+        }
+        Some(op @ ('%' | '#')) if !check_null => {
+            let largest = input.next_if_eq(&op).is_some();
+            let word = word(input, end)?;
+            input.next();
+            match (op, largest) {
+                ('%', false) => ParameterOp::RemoveSmallestSuffix(word),
+                ('%', true) => ParameterOp::RemoveLargestSuffix(word),
+                (_, false) => ParameterOp::RemoveSmallestPrefix(word),
+                (_, true) => ParameterOp::RemoveLargestPrefix(word),
+            }
+        }
+        Some(_) => return Err(BAD_SUBSTITUTION.into()),
+        None => return Err(InvalidInputError::incomplete(UNMATCHED_BRACE)),
+    };
+
+    Ok(ParameterExpansion {
+        name,
+        op,
+        braced: true,
+    })
+}
+
+/// Recognizes the parameter name in a parameter expansion in braces,
+/// which can be a positional parameter with more than one digit.
+///
+/// # Errors
+/// - Returns [`InvalidInputError`] in case there is no valid name.
+fn braced_name(input: &mut Peekable<Chars>) -> Result<String, InvalidInputError> {
+    match input.peek() {
+        Some(&ch) if is_special_parameter(ch) => {
+            input.next();
+            Ok(ch.to_string())
+        }
+        Some(ch) if ch.is_ascii_digit() => {
+            let mut name = String::new();
+            while let Some(ch) = input.next_if(char::is_ascii_digit) {
+                name.push(ch);
+            }
+            Ok(name)
+        }
+        Some(&ch) if ch == '_' || ch.is_ascii_alphabetic() => Ok(name(input)),
+        Some(_) => Err(BAD_SUBSTITUTION.into()),
+        None => Err(InvalidInputError::incomplete(UNMATCHED_BRACE)),
+    }
+}
+
+/// Recognizes the longest variable name at the current position of the `input`.
+fn name(input: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+    while let Some(ch) = input.next_if(|&ch| ch == '_' || ch.is_ascii_alphanumeric()) {
+        name.push(ch);
+    }
+
+    name
+}
+
+/// Checks whether `ch` is the name of a special parameter, other than `0`.
+///
+/// # References
+/// - [Special Parameters](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_05_02)
+fn is_special_parameter(ch: char) -> bool {
+    matches!(ch, '?' | '$' | '!' | '#' | '@' | '*')
+}
+
+/// Moves the accumulated `literal` text, if any, into the `parts`.
+fn push_literal(parts: &mut Vec<WordPart>, literal: &mut String) {
+    if !literal.is_empty() {
+        parts.push(WordPart::Literal(mem::take(literal)));
+    }
+}
+
+/// Recognizes the parts of the body of a here-document whose delimiter wasn't quoted.
+///
+/// The body is expanded like a double-quoted string, so it may contain parameter expansions,
+/// command substitutions, `$(program)` and `` `program` ``, arithmetic expansions, and escaped newlines.
+///
+/// # Errors
+/// - Returns [`InvalidInputError`] in case of an invalid or unterminated expansion.
+fn here_document_parts(text: &str) -> Result<Vec<WordPart>, InvalidInputError> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            // An escaped newline is a line continuation, which is removed.
+            '\\' if chars.next_if_eq(&'\n').is_some() => {}
+            '\\' => match chars.next_if(|&next| matches!(next, '\\' | '$' | '`')) {
+                Some(next) => {
+                    push_literal(&mut parts, &mut literal);
+                    parts.push(WordPart::Escaped(next));
+                }
+                None => literal.push(ch),
+            },
+            '`' => {
+                let program = backquoted(&mut chars, false)?;
+                push_literal(&mut parts, &mut literal);
+                parts.push(program);
+            }
+            '$' => match expansion(&mut chars, true)? {
+                Some(part) => {
+                    push_literal(&mut parts, &mut literal);
+                    parts.push(part);
+                }
+                None => literal.push(ch),
+            },
+            _ => literal.push(ch),
+        }
+    }
+
+    push_literal(&mut parts, &mut literal);
+
+    Ok(parts)
+}
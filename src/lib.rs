@@ -1,9 +1,16 @@
 //! A POSIX-Compliant Shell (CLI) Library
 
+pub mod arith;
+pub mod ast;
 pub mod cmd;
 pub mod constants;
 pub mod errors;
-#[macro_use]
-pub mod macros;
+pub mod exec;
+pub mod expand;
+pub mod jobs;
+pub mod lex;
 pub mod parse;
+pub mod pattern;
 pub mod repl;
+pub mod shell;
+pub mod vars;
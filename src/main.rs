@@ -1,7 +1,31 @@
 //! A POSIX-Compliant Shell (CLI) Implementation in Rust
+//!
+//! Without arguments, or with the option `-s`, the shell reads commands from the standard input.
+//! With the option `-c`, it runs the command string that follows. Otherwise, the first argument is the path
+//! of a script to run. The remaining arguments are the positional parameters.
 
-use posix_shell::repl::repl;
+use posix_shell::constants::STATUS_USAGE;
+use posix_shell::repl::{Invocation, repl, run_command_string, run_script};
+use std::{env, process};
 
 fn main() {
-    repl();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let invocation = match Invocation::parse(&args) {
+        Ok(invocation) => invocation,
+        Err(err) => {
+            eprint!("{err}");
+            process::exit(STATUS_USAGE);
+        }
+    };
+
+    match invocation {
+        Invocation::Stdin { args } => process::exit(repl(args)),
+        Invocation::CommandString {
+            command,
+            name,
+            args,
+        } => process::exit(run_command_string(&command, name, args)),
+        Invocation::Script { path, args } => process::exit(run_script(&path, &args)),
+    }
 }
@@ -1,1101 +1,795 @@
 //! Parser for the user input
 //!
+//! A recursive-descent parser, which follows the shell grammar, and which produces
+//! the abstract syntax tree out of the tokens of the input.
+//!
+//! The grammar that is currently supported, where `linebreak` is a sequence of zero or more newlines:
+//!
+//! ```text
+//! list          : linebreak (and_or separator linebreak)* [and_or] linebreak
+//! separator     : ';' | '&' | newline
+//! and_or        : pipeline (('&&' | '||') linebreak pipeline)*
+//! pipeline      : ['!'] command ('|' linebreak command)*
+//! command       : simple_command
+//!               | compound_command redirection*
+//!               | function_definition
+//! compound_command: if_clause | for_clause | while_clause | case_clause | subshell | brace_group
+//! if_clause     : 'if' compound_list 'then' compound_list
+//!                 ('elif' compound_list 'then' compound_list)* ['else' compound_list] 'fi'
+//! for_clause    : 'for' NAME [';'] linebreak do_group
+//!               | 'for' NAME linebreak 'in' WORD* (';' | newline) linebreak do_group
+//! while_clause  : ('while' | 'until') compound_list do_group
+//! do_group      : 'do' compound_list 'done'
+//! subshell      : '(' compound_list ')'
+//! brace_group   : '{' compound_list '}'
+//! case_clause   : 'case' WORD linebreak 'in' linebreak (case_item ';;' linebreak)* [case_item] 'esac'
+//! case_item     : ['('] WORD ('|' WORD)* ')' (compound_list | linebreak)
+//! compound_list : linebreak (and_or separator linebreak)* and_or [separator] linebreak
+//! function_definition: NAME '(' ')' linebreak compound_command redirection*
+//! simple_command: (ASSIGNMENT_WORD | redirection)* (WORD | redirection)*
+//! redirection   : [IO_NUMBER] redirection_operator WORD
+//!               | [IO_NUMBER] here_document
+//! ```
+//!
+//! Reserved words, such as `if` and `fi`, are recognized only where a command can start,
+//! and only if they are unquoted. The exceptions are `in` and `do` in a `for` loop,
+//! which are recognized after the name of the variable, and after the words, respectively,
+//! and `in` and `esac` in a `case` conditional, which are recognized after the word, and where a pattern can start.
+//!
 //! # References
 //!
+//! - [Shell Grammar](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_10)
 //! - [Bash Reference Manual](https://www.gnu.org/software/bash/manual/html_node/)
-//! - [Quoting](https://www.gnu.org/software/bash/manual/bash.html#Quoting)
-//! - Enclosing characters in single quotes preserves the literal value of each character within the quotes.
-//!   [Single Quotes](https://www.gnu.org/software/bash/manual/bash.html#Single-Quotes)
-//! - Enclosing characters in double quotes preserves the literal value of each character within the quotes except `\`.
-//!   The backslash retains its special meaning when followed by `\`, `$`, `"` or newline.
-//!   [Double Quotes](https://www.gnu.org/software/bash/manual/bash.html#Double-Quotes)
-//! - A non-quoted backslash `\` is treated as an escape character.
-//!   It preserves the literal value of the next character.
-//!   [Escape Character](https://www.gnu.org/software/bash/manual/bash.html#Escape-Character)
+//! - [Redirecting Input](https://www.gnu.org/software/bash/manual/bash.html#Redirecting-Input)
 //! - [Redirecting Output](https://www.gnu.org/software/bash/manual/bash.html#Redirecting-Output)
 //! - [Appending Redirected Output](https://www.gnu.org/software/bash/manual/bash.html#Appending-Redirected-Output)
 
+use crate::ast::{
+    AndOrList, AndOrOp, Assignment, CaseClause, CaseItem, Command, CompoundCommand, ForClause,
+    FunctionDefinition, IfClause, List, Pipeline, Redirection, RedirectionOp, SimpleCommand,
+    WhileClause, Word, WordPart,
+};
 use crate::constants::DEBUG;
 use crate::errors::InvalidInputError;
-use std::cmp::PartialEq;
-use std::fmt::{Display, Formatter};
+use crate::lex::{Operator, Token, tokenize};
+use crate::vars::is_name;
 use std::iter::Peekable;
-use std::path::PathBuf;
-use std::str::Chars;
-
-/// Finite state machine that changes state depending on quoting and escaping
-#[derive(Debug)]
-enum Fsm {
-    /// No quotes are active
-    Unquoted,
-    /// Single quote is active
-    Single,
-    /// Double quote is active
-    Double,
-    /// No quotes are active, escape is active
-    UnquotedEscape,
-    /// Double quote is active, escape is active
-    DoubleEscape,
-}
+use std::vec;
 
-impl Display for Fsm {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let reason = match self {
-            Fsm::Single => "unmatched single quotes\n",
-            Fsm::Double => "unmatched double quotes\n",
-            Fsm::UnquotedEscape | Fsm::DoubleEscape => "unmatched escape character\n",
-            state => panic!("finishing in the '{state:?}' state is not an error"),
-        };
+/// The reserved words, which start or end compound commands
+const RESERVED_WORDS: &[&str] = &[
+    "!", "case", "do", "done", "elif", "else", "esac", "fi", "for", "if", "in", "then", "until",
+    "while", "{", "}",
+];
 
-        write!(f, "{reason}")
-    }
-}
+/// Error message for input that ends inside a compound command
+const UNEXPECTED_END: &str = "syntax error: unexpected end of file\n";
 
-// TODO
-// /// Type of redirection
-// ///
-// /// This is effectively treated as a mini FSM embedded inside the main FSM.
-// #[derive(Clone, Debug, PartialEq)]
-// enum RedirectionKind {
-//     None,
-//     Stdout,
-//     Stderr,
-//     AppendStdout,
-//     AppendStderr,
-//     CombinedStdout,
-//     CombinedStderr,
-//     AppendCombinedStdout,
-//     AppendCombinedStderr,
-// }
-//
-// /// Type of redirection containing the target, if any
-// ///
-// /// This is effectively treated as a mini FSM embedded inside the main FSM.
-// ///
-// /// This allows for multiple redirections, not only of different kinds, but even of the same kind, too.
-// ///
-// /// In case the same kind is repeated, only the last one takes effect, meaning, only the last target
-// /// will be used for redirection, but all the previous ones need to be evaluated, too.
-// ///
-// /// For example, `cat src > target1 > target2` should write `stdout` (contents of `src`) to `target2` only,
-// /// but it should also create `target1` if it didn't exist.
-// ///
-// /// That's how `bash` works, for example, but **not** how `zsh` works, for example.
-// ///
-// /// To make the distinction clear, `zsh` would write the contents of `src` to both `target1` **and** `target2`.
-// #[derive(Clone, Debug)]
-// pub struct Redirection {
-//     kind: RedirectionKind,
-//     targets: Option<Vec<String>>,
-// }
-//
-// impl Redirection {
-//     /// Creates a new [`Redirection`] in default state, which is "no redirection".
-//     fn new() -> Self {
-//         Self {
-//             kind: RedirectionKind::None,
-//             targets: None,
-//         }
-//     }
-//
-//     /// Adds a new `target` to the list of targets for this kind of redirection
-//     fn add_target(&mut self, target: String) {
-//         if self.kind != RedirectionKind::None {
-//             if self.targets.is_none() {
-//                 self.targets = Some(Vec::new());
-//             }
-//             self.targets.clone().unwrap().push(target);
-//         }
-//     }
-// }
-//
-// pub struct Redirections(Vec<Redirection>);
-//
-// impl Redirections {
-//     fn new() -> Self {
-//         Self { 0: vec![] }
-//     }
-//
-//     fn update(&mut self, new_redir: Redirection) {
-//         for redir in self.0.iter_mut() {
-//             if redir.kind == new_redir.kind {
-//                 redir.add_target(new_redir.t);
-//             }
-//         }
-//     }
-// }
-
-/// A helper enum/FSM for redirection target
+/// Parses user input and returns the [`List`] of commands that it contains.
 ///
-/// This is effectively treated as a mini FSM embedded inside the main FSM.
-///
-/// It's used in unquoted parts of input, i.e, in the [`Fsm::Unquoted`] state.
-///
-/// It is not public by design.
+/// # Errors
+/// - Returns [`InvalidInputError`] in case of unmatched quotes or a syntax error.
+/// - Returns incomplete [`InvalidInputError`] in case the input is incomplete, e.g., the body
+///   of a here-document hasn't been entered yet, or a compound command hasn't been terminated yet,
+///   in which case the user should enter more input.
 ///
-/// Contains the following variants:
-/// - None
-/// - Stdout
-/// - Stderr
-#[derive(Debug, PartialEq)]
-enum RedirectionFsm {
-    None,
-    Stdout,
-    Stderr,
-    CombinedStdout, // TODO: Needed?
-    CombinedStderr, // TODO: Needed?
+/// # References
+/// - [Quoting](https://www.gnu.org/software/bash/manual/bash.html#Quoting)
+/// - [Pipelines](https://www.gnu.org/software/bash/manual/bash.html#Pipelines)
+/// - [Lists of Commands](https://www.gnu.org/software/bash/manual/bash.html#Lists)
+pub fn parse_input(input: &str) -> Result<List, InvalidInputError> {
+    parse_tokens(tokenize(input)?)
 }
 
-/// Trait for working with [`Stdout`] or [`Stderr`]
+/// Parses the `tokens` of the input, and returns the [`List`] of commands that they make up.
 ///
-/// This is particularly useful for multiple redirections.
-trait StdOutErr {
-    /// Constructs a new instance, filling the `kind` and `paths` fields.
-    fn new() -> Self;
-
-    /// Updates [`Self`] with a new `path`.
-    fn add_path(&mut self, path: PathBuf);
-
-    // TODO
-    // /// Updates [`Self`] with new `kind` and with a new `path`.
-    // ///
-    // /// `kind` cannot be [`RedirectionKind::None`], because once
-    // /// the user has decided to redirect, there's no going back.
-    // fn update(&mut self, kind: RedirectionKind, path: PathBuf);
-}
+/// # Errors
+/// - Returns [`InvalidInputError`] in case of a syntax error.
+/// - Returns incomplete [`InvalidInputError`] in case the tokens end before the input is complete.
+pub fn parse_tokens(tokens: Vec<Token>) -> Result<List, InvalidInputError> {
+    if DEBUG.get().is_some_and(|&debug| debug) {
+        eprintln!("tokens: {tokens:?}");
+    }
 
-/// The mode of redirection
-///
-/// Contains the following variants:
-/// - None
-/// - Overwrite
-/// - Append
-///
-/// Can be used for both `stdout` and `stderr`.
-#[derive(Clone, Debug, PartialEq)]
-pub enum RedirectionMode {
-    /// No redirection
-    None,
-    /// Standard redirection that overwrites the target path
-    Overwrite,
-    /// A redirection kind that appends to the target path
-    Append,
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+    };
+
+    parser.list()
 }
 
-/// The redirection to `stdout`
-///
-/// Contains kind of redirection, as [`RedirectionMode`], and the target file paths.
-///
-/// In case of multiple redirections, only the last one applies.
-///
-/// Nevertheless, all previous targets (paths) should be cleared.
-#[derive(Clone, Debug, PartialEq)]
-pub struct Stdout {
-    pub kind: RedirectionMode,
-    pub paths: Vec<PathBuf>,
+/// The state of the parser, which consumes the tokens of the input
+struct Parser {
+    /// The remaining tokens
+    tokens: Peekable<vec::IntoIter<Token>>,
 }
 
-impl StdOutErr for Stdout {
-    fn new() -> Self {
-        Self {
-            kind: RedirectionMode::None,
-            paths: Vec::new(),
+impl Parser {
+    /// Parses a list of AND-OR lists, which are separated by `;`, `&` or newlines, up to the end of input.
+    fn list(&mut self) -> Result<List, InvalidInputError> {
+        let mut list = List::new();
+
+        loop {
+            self.linebreak();
+            if self.tokens.peek().is_none() {
+                break;
+            }
+
+            let mut and_or_list = self.and_or_list()?;
+            and_or_list.background = self.separator()?;
+            list.push(and_or_list);
         }
+
+        Ok(list)
     }
 
-    fn add_path(&mut self, path: PathBuf) {
-        self.paths.push(path);
+    /// Parses a list inside a compound command, which ends right before one of the reserved words
+    /// in `terminators`, where a command would otherwise start.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before a terminator.
+    /// - Returns [`InvalidInputError`] in case the list is empty, e.g., `if then`.
+    fn compound_list(&mut self, terminators: &[&str]) -> Result<List, InvalidInputError> {
+        let list = self.list_until(terminators, None)?;
+        if list.is_empty() {
+            return Err(self.unexpected());
+        }
+
+        Ok(list)
     }
 
-    // TODO
-    // fn update(&mut self, kind: RedirectionKind, path: PathBuf) {
-    //     assert_ne!(kind, RedirectionKind::None);
-    //     self.kind = kind;
-    //     if self.paths.is_none() {
-    //         self.paths = Some(Vec::new());
-    //     }
-    //     self.paths.clone().unwrap().push(path);
-    // }
-}
+    /// Parses a possibly empty list inside a compound command, which ends right before one of the reserved words
+    /// in `terminators`, or right before the operator `end`, e.g., `;;` after the body of a `case_item`,
+    /// or `)` after the list of a subshell.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before a terminator.
+    fn list_until(
+        &mut self,
+        terminators: &[&str],
+        end: Option<Operator>,
+    ) -> Result<List, InvalidInputError> {
+        let mut list = List::new();
+
+        loop {
+            self.linebreak();
+            match self.peek_reserved() {
+                Some(word) if terminators.contains(&word) => break,
+                _ if end.is_some_and(|op| self.tokens.peek() == Some(&Token::Operator(op))) => {
+                    break;
+                }
+                _ if self.tokens.peek().is_none() => {
+                    return Err(InvalidInputError::incomplete(UNEXPECTED_END));
+                }
+                _ => {}
+            }
 
-/// The redirection to `stderr`
-///
-/// Contains kind of redirection, as [`RedirectionMode`], and the target file paths.
-///
-/// In case of multiple redirections, only the last one applies.
-///
-/// Nevertheless, all previous targets (paths) should be cleared.
-#[derive(Clone, Debug, PartialEq)]
-pub struct Stderr {
-    pub kind: RedirectionMode,
-    pub paths: Vec<PathBuf>,
-}
+            let mut and_or_list = self.and_or_list()?;
+            and_or_list.background = self.separator()?;
+            list.push(and_or_list);
+        }
+
+        Ok(list)
+    }
 
-impl StdOutErr for Stderr {
-    fn new() -> Self {
-        Self {
-            kind: RedirectionMode::None,
-            paths: Vec::new(),
+    /// Consumes the separator that terminates an AND-OR list, if there is one, and returns whether it is `&`.
+    ///
+    /// # Errors
+    /// - Returns [`InvalidInputError`] in case the next token isn't a separator, e.g., `cmd )`.
+    fn separator(&mut self) -> Result<bool, InvalidInputError> {
+        // `;;` terminates the body of a `case_item`, and `)` terminates the list of a subshell,
+        // so they are left for the compound command, and they are a syntax error anywhere else
+        if let Some(Token::Operator(Operator::DoubleSemicolon | Operator::RightParen)) =
+            self.tokens.peek()
+        {
+            return Ok(false);
+        }
+
+        match self.tokens.next() {
+            None | Some(Token::Newline) | Some(Token::Operator(Operator::Semicolon)) => Ok(false),
+            Some(Token::Operator(Operator::Ampersand)) => Ok(true),
+            Some(token) => Err(syntax_error(&token)),
         }
     }
 
-    fn add_path(&mut self, path: PathBuf) {
-        self.paths.push(path);
-        // TODO
-        // if self.paths.is_none() {
-        //     self.paths = Some(Vec::new());
-        // }
-        // self.paths.clone().unwrap().push(path);
-    }
-
-    // TODO
-    // fn update(&mut self, kind: RedirectionKind, path: PathBuf) {
-    //     assert_ne!(kind, RedirectionKind::None);
-    //     self.kind = kind;
-    //     if self.paths.is_none() {
-    //         self.paths = Some(Vec::new());
-    //     }
-    //     self.paths.clone().unwrap().push(path);
-    // }
-}
+    /// Skips newlines.
+    fn linebreak(&mut self) {
+        while self.tokens.next_if_eq(&Token::Newline).is_some() {}
+    }
 
-/// Contains the `stdout` and `stderr` redirections.
-#[derive(Debug, PartialEq)]
-pub struct Redirections {
-    /// A redirection to `stdout`
-    pub stdout: Stdout,
-    /// A redirection to `stderr`
-    pub stderr: Stderr,
-}
+    /// Parses an AND-OR list, which consists of pipelines separated by `&&` or `||`.
+    ///
+    /// A newline can follow an operator, and the input is incomplete if it ends after an operator.
+    fn and_or_list(&mut self) -> Result<AndOrList, InvalidInputError> {
+        let first = self.pipeline()?;
+        let mut rest = Vec::new();
+
+        loop {
+            let op = match self.tokens.peek() {
+                Some(Token::Operator(Operator::AndIf)) => AndOrOp::And,
+                Some(Token::Operator(Operator::OrIf)) => AndOrOp::Or,
+                _ => break,
+            };
+            self.tokens.next();
+            self.linebreak();
+            self.expect_more()?;
+            rest.push((op, self.pipeline()?));
+        }
 
-impl Redirections {
-    /// Constructs a new instance from the existing `stdout` and `stderr` instances.
-    fn from(stdout: Stdout, stderr: Stderr) -> Self {
-        Self { stdout, stderr }
-    }
-
-    // /// Constructs a new instance, filling the `stdout` and `stderr` fields.
-    // fn new() -> Self {
-    //     Self {
-    //         stdout: Stdout::new(),
-    //         stderr: Stderr::new(),
-    //     }
-    // }
-
-    // fn new() -> Self {
-    //     Self {
-    //         stdout: Stdout {
-    //             kind: RedirectionKind::None,
-    //             targets: None,
-    //         },
-    //         stderr: Stderr {
-    //             kind: RedirectionKind::None,
-    //             targets: None,
-    //         },
-    //     }
-    // }
-
-    // /// TODO docs
-    // fn update(&mut self, mut field: impl StdOutErr, kind: RedirectionKind, target: PathBuf) {
-    //     field.update(kind, target);
-    // }
-    // fn update<T: StdOutErr>(&mut self, mut field: T, kind: RedirectionKind, target: PathBuf) {
-    //     field.update(kind, target);
-    // }
-}
+        Ok(AndOrList {
+            first,
+            rest,
+            background: false,
+        })
+    }
 
-// impl Redirections {
-//     /// Gets the `stdout` and `stderr` target (path) fields for reading.
-//     pub fn get(self) -> (Stdout, Stderr) {
-//         (self.stdout, self.stderr)
-//     }
-// }
-
-// pub struct Redirections2 {
-//     // kind: RedirectionKind2,
-//     stdout: Vec<u8>,
-//     stderr: Vec<u8>,
-//     stdout_targets: Option<Vec<String>>,
-//     stderr_targets: Option<Vec<String>>,
-// }
-
-// impl Redirect { TODO remove
-//     /// Creates a new [`Redirect`] from an existing one, with the new `target` value
-//     fn from(self, target: String) -> Self {
-//         match self {
-//             Redirect::None => Redirect::None,
-//             Redirect::Stdout(_) => Redirect::Stdout(target),
-//             Redirect::Stderr(_) => Redirect::Stderr(target),
-//             Redirect::AppendStdout(_) => Redirect::AppendStdout(target),
-//             Redirect::AppendStderr(_) => Redirect::AppendStderr(target),
-//             Redirect::CombinedStdout(_) => Redirect::CombinedStdout(target),
-//             Redirect::CombinedStderr(_) => Redirect::CombinedStderr(target),
-//             Redirect::AppendCombinedStdout(_) => Redirect::AppendCombinedStdout(target),
-//             Redirect::AppendCombinedStderr(_) => Redirect::AppendCombinedStderr(target),
-//         }
-//     }
-// }
-
-/// Parses user input and returns parsed items, together with [`Redirections`].
-///
-/// # Errors
-/// - [`InvalidInputError`]
-///
-/// # References
-/// - [Field Splitting](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_06_05)
-/// - https://doc.rust-lang.org/std/primitive.char.html#method.is_ascii_whitespace
-/// - [Quoting](https://www.gnu.org/software/bash/manual/bash.html#Quoting)
-/// - [Redirecting Output](https://www.gnu.org/software/bash/manual/bash.html#Redirecting-Output)
-pub fn parse_input(input: &str) -> Result<(Vec<String>, Redirections), InvalidInputError> {
-    // An item can be more than a single word if it was quoted in the input.
-    // Conversely, two or more words from the input can be merged into a single word (item)
-    // if they were separated only by a matching pair of quotes in the input.
-    // Escaping with backslash, `\`, is also supported.
-
-    // Quoted text (single or double quotes) should keep all its whitespace characters,
-    // but unquoted text should not, unless escaped.
-    // Unquoted text should compress several consecutive whitespace characters into a single space, unless escaped.
-
-    let mut input = input.chars().peekable();
-
-    let mut items: Vec<String> = Vec::new();
-    let mut item = String::new();
-
-    // Redirection targets
-    // let mut redirections = Redirections::new();
-    // let mut redirection = Redirection::new(); todo
-    // let mut redir_kind = RedirectionKind::None;
-    // let mut redirection = false;
-    let mut redirection = RedirectionFsm::None;
-    let mut stdout = Stdout::new();
-    let mut stderr = Stderr::new();
-
-    let mut state = Fsm::Unquoted;
-
-    // redirections.update(stdout, RedirectionKind::Append, PathBuf::new());
-
-    while let Some(ch) = input.next() {
-        match state {
-            Fsm::Unquoted => match ch {
-                ' ' | '\t' | '\n' => {
-                    // eprintln!("#### 1 ch = '{ch}', item = '{item}'"); // todo rem
-                    if !item.is_empty() {
-                        // eprintln!("  #### 2 ch = '{ch}', item = '{item}'"); // todo rem
-                        match redirection {
-                            RedirectionFsm::None => items.push(item.to_string()),
-                            RedirectionFsm::Stdout => stdout.add_path(PathBuf::from(item.clone())),
-                            RedirectionFsm::Stderr => stderr.add_path(PathBuf::from(item.clone())),
-                            // todo remove:
-                            RedirectionFsm::CombinedStdout | RedirectionFsm::CombinedStderr => {
-                                break; // todo ?
-                                // stdout.add_path(PathBuf::from(item.clone()));
-                                // stderr.add_path(PathBuf::from(item.clone()));
-                            }
-                        }
-                        // redirection = Redirection::None;
-
-                        // if !redirection {
-                        //     items.push(item.to_string());
-                        // }
-
-                        // if stdout.kind.eq(&RedirectionKind::None)
-                        //     && stderr.kind.eq(&RedirectionKind::None)
-
-                        // else if stdout.kind.ne(&RedirectionKind::None) {
-                        // }
-                        // else {
-                        // // redirections.push(redirect.from(item.clone())); todo
-                        // let mut redirection = Redirection::new();
-                        // redirection.add_target(item.clone());
-                        // redirections.push(redirection.clone());
-                        // // redirection = Redirection::new();
-                        // redir_kind = RedirectionKind::None;
-                        // }
-
-                        item.clear();
-                        redirection = RedirectionFsm::None;
-                    }
-                    // redirection = RedirectionFsm::None;
-                    // redirection = false;
-                }
-                '\'' => {
-                    state = Fsm::Single;
-                    redirection = RedirectionFsm::None;
-                    // redirection = false;
-                }
-                '"' => {
-                    state = Fsm::Double;
-                    redirection = RedirectionFsm::None;
-                    // redirection = false;
-                }
-                '\\' => {
-                    item.push(ch);
-                    state = Fsm::UnquotedEscape;
-                    redirection = RedirectionFsm::None;
-                    // redirection = false;
-                }
-                '>' => {
-                    handle_closing_angle_bracket_unquoted(
-                        // &mut input,
-                        &mut items,
-                        &mut item,
-                        // redirection,
-                        &mut redirection,
-                        &mut stdout,
-                        &mut stderr,
-                    )?;
-                    // redirection = true;
-                }
-                '&' => {
-                    handle_ampersand_unquoted(
-                        &mut input,
-                        &mut item,
-                        &mut redirection,
-                        &mut stdout,
-                        &mut stderr,
-                    )?;
-                    // redirection = RedirectionFsm::None;
-                    // redirection = false;
-                }
-                _ => {
-                    item.push(ch);
-                    // redirection = RedirectionFsm::None;
-                    // redirection = false;
-                }
-            },
-            Fsm::Single => match ch {
-                '\'' => state = Fsm::Unquoted,
-                '"' => {
-                    item.push(ch);
-                }
-                _ => item.push(ch),
-            },
-            Fsm::Double => match ch {
-                '\'' => {
-                    item.push(ch);
-                }
-                '"' => {
-                    state = Fsm::Unquoted;
-                }
-                '\\' => {
-                    item.push(ch);
-                    state = Fsm::DoubleEscape;
-                }
-                _ => {
-                    item.push(ch);
+    /// Parses a pipeline, which consists of commands separated by `|`, and which can be negated with `!`.
+    ///
+    /// A newline can follow the operator, and the input is incomplete if it ends after the operator.
+    fn pipeline(&mut self) -> Result<Pipeline, InvalidInputError> {
+        let negated = self.peek_reserved() == Some("!");
+        if negated {
+            self.tokens.next();
+        }
+        let mut commands = vec![self.command()?];
+
+        while self
+            .tokens
+            .next_if_eq(&Token::Operator(Operator::Pipe))
+            .is_some()
+        {
+            self.linebreak();
+            self.expect_more()?;
+            commands.push(self.command()?);
+        }
+
+        Ok(Pipeline { commands, negated })
+    }
+
+    /// Parses a command, which is either a compound command, followed by its redirections, a function definition,
+    /// or a simple command.
+    ///
+    /// A simple command that consists of a single word, and that is followed by `(`, is the name of a function
+    /// that is being defined.
+    ///
+    /// # Errors
+    /// - Returns [`InvalidInputError`] in case the command starts with a reserved word that can't start
+    ///   a command, e.g., `fi`.
+    fn command(&mut self) -> Result<Command, InvalidInputError> {
+        if self.peek_reserved().is_none()
+            && self.tokens.peek() != Some(&Token::Operator(Operator::LeftParen))
+        {
+            let cmd = self.simple_command()?;
+            return match self.tokens.peek() {
+                Some(Token::Operator(Operator::LeftParen)) => {
+                    self.function_definition(cmd).map(Command::Function)
                 }
-            },
-            Fsm::UnquotedEscape => {
-                item.pop();
-                item.push(ch);
-                state = Fsm::Unquoted;
+                _ => Ok(Command::Simple(cmd)),
+            };
+        }
+
+        let command = self.compound_command()?;
+        let redirections = self.redirections()?;
+
+        Ok(Command::Compound {
+            command,
+            redirections,
+        })
+    }
+
+    /// Parses a compound command, which starts with the next token.
+    ///
+    /// A subshell or a brace group is parsed right here, because it consists of a list only.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input has ended.
+    /// - Returns [`InvalidInputError`] in case the next token doesn't start a compound command.
+    fn compound_command(&mut self) -> Result<CompoundCommand, InvalidInputError> {
+        let command = match self.peek_reserved() {
+            Some("if") => CompoundCommand::If(self.if_clause()?),
+            Some("for") => CompoundCommand::For(self.for_clause()?),
+            Some("while" | "until") => CompoundCommand::While(self.while_clause()?),
+            Some("case") => CompoundCommand::Case(self.case_clause()?),
+            Some("{") => {
+                self.tokens.next();
+                let list = self.compound_list(&["}"])?;
+                self.tokens.next();
+                CompoundCommand::BraceGroup(list)
             }
-            Fsm::DoubleEscape => match ch {
-                '\'' => {
-                    item.push(ch);
-                    state = Fsm::Double;
+            None if self.tokens.peek() == Some(&Token::Operator(Operator::LeftParen)) => {
+                self.tokens.next();
+                let list = self.list_until(&[], Some(Operator::RightParen))?;
+                if list.is_empty() {
+                    return Err(self.unexpected());
                 }
-                '"' => {
-                    item.pop();
-                    item.push(ch);
-                    state = Fsm::Double;
-                }
-                '\\' => {
-                    item.pop();
-                    item.push(ch);
-                    state = Fsm::Double;
-                }
-                '$' | '`' | '\n' => {
-                    item.pop();
-                    item.push(ch);
-                    state = Fsm::Double;
-                }
-                _ => {
-                    item.push(ch);
-                    state = Fsm::Double;
-                }
-            },
+                self.tokens.next();
+                CompoundCommand::Subshell(list)
+            }
+            _ if self.tokens.peek().is_none() => {
+                return Err(InvalidInputError::incomplete(UNEXPECTED_END));
+            }
+            _ => return Err(self.unexpected()),
+        };
+
+        Ok(command)
+    }
+
+    /// Parses the redirections that follow a compound command.
+    fn redirections(&mut self) -> Result<Vec<Redirection>, InvalidInputError> {
+        let mut redirections = Vec::new();
+        while self.at_redirection() {
+            redirections.push(self.redirection()?);
         }
-        if DEBUG.get().is_some_and(|&debug| debug) {
-            eprintln!("{ch} -> {state:?}, {redirection:?}, {stdout:?}, {stderr:?}\t{item}");
+
+        Ok(redirections)
+    }
+
+    /// Parses a function definition, whose name is the only word of `cmd`, and whose `(` is the next token.
+    ///
+    /// # Errors
+    /// - Returns [`InvalidInputError`] in case `cmd` isn't a valid name, e.g., `echo a()`, or `(` isn't followed
+    ///   by `)`.
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before the body.
+    /// - Returns [`InvalidInputError`] in case the body isn't a compound command.
+    fn function_definition(
+        &mut self,
+        cmd: SimpleCommand,
+    ) -> Result<FunctionDefinition, InvalidInputError> {
+        let name = match (
+            cmd.assignments.is_empty(),
+            cmd.redirections.is_empty(),
+            &cmd.words[..],
+        ) {
+            (true, true, [word]) => word.as_literal().filter(|name| is_name(name)),
+            _ => None,
+        };
+        let Some(name) = name.map(str::to_string) else {
+            return Err(self.unexpected());
+        };
+
+        // `(`
+        self.tokens.next();
+        match self.next_token()? {
+            Token::Operator(Operator::RightParen) => {}
+            token => return Err(syntax_error(&token)),
         }
+        self.linebreak();
+        let body = self.compound_command()?;
+        let redirections = self.redirections()?;
+
+        Ok(FunctionDefinition {
+            name,
+            body,
+            redirections,
+        })
     }
-    // eprintln!("#### 3 item = '{item}'"); // todo rem
-    if !item.is_empty() {
-        // eprintln!("#### 4 item = '{item}'"); // todo rem
-        match redirection {
-            RedirectionFsm::None => items.push(item.to_string()),
-            RedirectionFsm::Stdout => stdout.add_path(PathBuf::from(item.clone())),
-            RedirectionFsm::Stderr => stderr.add_path(PathBuf::from(item.clone())),
-            RedirectionFsm::CombinedStdout | RedirectionFsm::CombinedStderr => {
-                // todo rem
-                stdout.add_path(PathBuf::from(item.clone()));
-                stderr.add_path(PathBuf::from(item.clone()));
+
+    /// Parses an `if` conditional, whose `if` is the next token.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before `fi`.
+    /// - Returns [`InvalidInputError`] in case a condition or a body is empty.
+    fn if_clause(&mut self) -> Result<IfClause, InvalidInputError> {
+        let mut branches = Vec::new();
+        let mut else_body = None;
+
+        loop {
+            // `if` or `elif`
+            self.tokens.next();
+            let condition = self.compound_list(&["then"])?;
+            self.tokens.next();
+            let body = self.compound_list(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+
+            match self.peek_reserved() {
+                Some("elif") => {}
+                Some("else") => {
+                    self.tokens.next();
+                    else_body = Some(self.compound_list(&["fi"])?);
+                    break;
+                }
+                _ => break,
             }
         }
+        // `fi`
+        self.tokens.next();
+
+        Ok(IfClause {
+            branches,
+            else_body,
+        })
     }
 
-    eprintln!("@ -> {state:?}, {redirection:?}, {stdout:?}, {stderr:?}\t{item}"); // todo rem
+    /// Parses a `for` loop, whose `for` is the next token.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before `done`.
+    /// - Returns [`InvalidInputError`] in case the name isn't a valid name, or `do` is missing.
+    fn for_clause(&mut self) -> Result<ForClause, InvalidInputError> {
+        // `for`
+        self.tokens.next();
+        let name = match self.next_token()? {
+            Token::Word(word) => match word.as_literal() {
+                Some(name) if is_name(name) => name.to_string(),
+                _ => return Err(syntax_error(&Token::Word(word))),
+            },
+            token => return Err(syntax_error(&token)),
+        };
 
-    let redirections = Redirections::from(stdout, stderr);
+        let mut words = None;
+        if self
+            .tokens
+            .next_if_eq(&Token::Operator(Operator::Semicolon))
+            .is_none()
+        {
+            self.linebreak();
+            if self.peek_reserved() == Some("in") {
+                self.tokens.next();
+                let mut list = Vec::new();
+                while let Some(Token::Word(word)) =
+                    self.tokens.next_if(|token| matches!(token, Token::Word(_)))
+                {
+                    list.push(word);
+                }
+                match self.next_token()? {
+                    Token::Newline | Token::Operator(Operator::Semicolon) => {}
+                    token => return Err(syntax_error(&token)),
+                }
+                words = Some(list);
+            }
+        }
+        let body = self.do_group()?;
 
-    match state {
-        Fsm::Unquoted => Ok((items, redirections)),
-        other => Err(InvalidInputError {
-            reason: other.to_string(),
-        }),
+        Ok(ForClause { name, words, body })
     }
-}
 
-/// Handles the received `>` character in the [`Fsm::Unquoted`] state.
-///
-/// This character is used for output redirection.
-///
-/// Only updates the [`RedirectionMode`] of the appropriate redirection output, `stdout` or `stderr`,
-/// to [`RedirectionMode::Overwrite`] or to [`RedirectionMode::Append`].
-///
-/// It doesn't append the target path, because that is yet to be parsed outside of this function.
-///
-/// # Errors
-/// - Returns [`InvalidInputError`] in case of three or more consecutive `>`, i.e., `>>>`.
-fn handle_closing_angle_bracket_unquoted(
-    items: &mut Vec<String>,
-    item: &mut String,
-    redirection: &mut RedirectionFsm,
-    stdout: &mut Stdout,
-    stderr: &mut Stderr,
-) -> Result<(), InvalidInputError> {
-    match *redirection {
-        RedirectionFsm::None => {
-            if (*item).eq("1") {
-                // `1>`
-                item.clear();
-                *redirection = RedirectionFsm::Stdout;
-                stdout.kind = RedirectionMode::Overwrite;
-            } else if (*item).eq("2") {
-                // `2>`
-                item.clear();
-                *redirection = RedirectionFsm::Stderr;
-                stderr.kind = RedirectionMode::Overwrite;
-            } else {
-                // `>`
-                if !item.is_empty() {
-                    items.push(item.to_string());
-                    item.clear();
+    /// Parses a `while` or an `until` loop, whose `while` or `until` is the next token.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before `done`.
+    /// - Returns [`InvalidInputError`] in case the condition or the body is empty.
+    fn while_clause(&mut self) -> Result<WhileClause, InvalidInputError> {
+        let until = self.peek_reserved() == Some("until");
+        self.tokens.next();
+        let condition = self.compound_list(&["do"])?;
+        let body = self.do_group()?;
+
+        Ok(WhileClause {
+            condition,
+            body,
+            until,
+        })
+    }
+
+    /// Parses the body of a loop, `do list done`, which can be preceded by newlines.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before `done`.
+    /// - Returns [`InvalidInputError`] in case the next token isn't `do`, or the body is empty.
+    fn do_group(&mut self) -> Result<List, InvalidInputError> {
+        self.linebreak();
+        self.expect_reserved("do")?;
+        let body = self.compound_list(&["done"])?;
+        // `done`
+        self.tokens.next();
+
+        Ok(body)
+    }
+
+    /// Parses a `case` conditional, whose `case` is the next token.
+    ///
+    /// The body of the last item doesn't have to be terminated by `;;`.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before `esac`.
+    /// - Returns [`InvalidInputError`] in case `in` is missing, or an item is invalid, e.g., `a b)` or `a|)`.
+    fn case_clause(&mut self) -> Result<CaseClause, InvalidInputError> {
+        // `case`
+        self.tokens.next();
+        let word = match self.next_token()? {
+            Token::Word(word) => word,
+            token => return Err(syntax_error(&token)),
+        };
+        self.linebreak();
+        self.expect_reserved("in")?;
+
+        let mut items = Vec::new();
+        loop {
+            self.linebreak();
+            if self.peek_reserved() == Some("esac") {
+                self.tokens.next();
+                break;
+            }
+
+            self.tokens
+                .next_if_eq(&Token::Operator(Operator::LeftParen));
+            let mut patterns = Vec::new();
+            loop {
+                match self.next_token()? {
+                    Token::Word(word) => patterns.push(word),
+                    token => return Err(syntax_error(&token)),
+                }
+                match self.next_token()? {
+                    Token::Operator(Operator::Pipe) => {}
+                    Token::Operator(Operator::RightParen) => break,
+                    token => return Err(syntax_error(&token)),
                 }
-                *redirection = RedirectionFsm::Stdout;
-                stdout.kind = RedirectionMode::Overwrite;
             }
-        }
-        RedirectionFsm::Stdout => {
-            if stdout.kind == RedirectionMode::Overwrite {
-                // `1>>` or `>>`
-                stdout.kind = RedirectionMode::Append;
-            } else {
-                // `>>>`
-                return Err("shell: syntax error near unexpected token `>'\n".into());
+
+            let body = self.list_until(&["esac"], Some(Operator::DoubleSemicolon))?;
+            items.push(CaseItem { patterns, body });
+
+            // Either `;;` or `esac` follows the body
+            if self
+                .tokens
+                .next_if_eq(&Token::Operator(Operator::DoubleSemicolon))
+                .is_none()
+            {
+                self.tokens.next();
+                break;
             }
         }
-        RedirectionFsm::Stderr => {
-            if stderr.kind == RedirectionMode::Overwrite {
-                // `2>>`
-                stderr.kind = RedirectionMode::Append;
-            } else {
-                // `>>>`
-                return Err("shell: syntax error near unexpected token `>'\n".into());
+
+        Ok(CaseClause { word, items })
+    }
+
+    /// Consumes the next token, which has to be the reserved word `word`.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input ends before `word`.
+    /// - Returns [`InvalidInputError`] in case the next token is another one.
+    fn expect_reserved(&mut self, word: &str) -> Result<(), InvalidInputError> {
+        match self.peek_reserved() {
+            Some(reserved) if reserved == word => {
+                self.tokens.next();
+                Ok(())
             }
+            _ if self.tokens.peek().is_none() => Err(InvalidInputError::incomplete(UNEXPECTED_END)),
+            _ => Err(self.unexpected()),
         }
-        _ => {} // todo rem
     }
 
-    Ok(())
-}
+    /// Consumes the next token inside a compound command.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] in case the input has ended.
+    fn next_token(&mut self) -> Result<Token, InvalidInputError> {
+        self.tokens
+            .next()
+            .ok_or_else(|| InvalidInputError::incomplete(UNEXPECTED_END))
+    }
 
-/// Handles the received `&` character in the [`Fsm::Unquoted`] state.
-///
-/// Supports shell-specific `&>word` and shell-specific `>&word`, which redirect both
-/// `stdout` and `stderr` to the file whose name is the expansion of `word`.
-///
-/// Unimplemented: `&` on its own is used for background operation, and `&&` as logical AND.
-///
-/// # References
-/// - [Redirecting Output](https://www.gnu.org/software/bash/manual/bash.html#Redirecting-Output)
-///
-/// # Errors
-/// - Returns [`InvalidInputError`] in case of `&>&` or `>&&`.
-/// - Returns [`InvalidInputError`] in case of unimplemented cases, i.e., ` & ` (`bg`) and ` && ` (`AND`).
-fn handle_ampersand_unquoted(
-    input: &mut Peekable<Chars>,
-    item: &mut str,
-    redirection: &mut RedirectionFsm,
-    stdout: &mut Stdout,
-    stderr: &mut Stderr,
-) -> Result<(), InvalidInputError> {
-    let next_peeked = input.peek().unwrap_or(&' ');
-
-    if item.is_empty() && next_peeked.is_whitespace() {
-        // Unimplemented: Background operation
-        return Err("shell: unimplemented `&'\n".into());
-    } else if next_peeked.eq(&'&') {
-        return if *redirection != RedirectionFsm::None {
-            // `>&&`
-            Err("shell: syntax error near unexpected token `&'\n".into())
-        } else {
-            // Unimplemented: The logical AND operator
-            Err("shell: unimplemented `&&'\n".into())
+    /// Returns the next token if it is a reserved word, without consuming it.
+    ///
+    /// It is only called where a command can start, which is where reserved words are recognized,
+    /// where `in` or `do` are expected in a `for` loop, and where `in` or `esac` are expected
+    /// in a `case` conditional.
+    fn peek_reserved(&mut self) -> Option<&'static str> {
+        let Some(Token::Word(word)) = self.tokens.peek() else {
+            return None;
         };
+        let text = word.as_literal()?;
+
+        RESERVED_WORDS
+            .iter()
+            .find(|&&reserved| reserved == text)
+            .copied()
+    }
+
+    /// Checks whether the next token starts a redirection.
+    fn at_redirection(&mut self) -> bool {
+        match self.tokens.peek() {
+            Some(Token::IoNumber(_) | Token::HereDocument { .. }) => true,
+            Some(Token::Operator(op)) => redirection_op(*op).is_some(),
+            _ => false,
+        }
     }
 
-    match *redirection {
-        RedirectionFsm::None => {
-            if next_peeked.eq(&'>') {
-                // shell-specific `&>word` or `&> word`
-                *redirection = RedirectionFsm::Stdout;
-                stdout.kind = RedirectionMode::Overwrite;
-                stderr.kind = RedirectionMode::None;
-                let next = input.next();
-                if next.is_some() {
-                    if let Some(next_next_peeked) = input.peek() {
-                        if next_next_peeked.eq(&'>') {
-                            // shell-specific `&>>`
-                            stdout.kind = RedirectionMode::Append;
-                            let next = input.next();
-                            if next.is_some() && input.peek().is_none() {
-                                // `&>> ` or `>>&\t` or `&>>\n`
-                                return Err(
-                                    "shell: syntax error near unexpected token `newline'\n".into(),
-                                );
-                            }
-                        } else if next_next_peeked.eq(&'&') {
-                            // `&>&`
-                            return Err("shell: syntax error near unexpected token `&'\n".into());
+    /// Parses a simple command, which consists of words and redirections, in any order.
+    ///
+    /// The words that precede the command name, and that are assignments, e.g., `name=value`,
+    /// are the assignments of the command.
+    ///
+    /// # Errors
+    /// - Returns [`InvalidInputError`] in case the command is empty, e.g., `| cmd`, `cmd | | cmd`, `; cmd`
+    ///   or `cmd && ;`.
+    fn simple_command(&mut self) -> Result<SimpleCommand, InvalidInputError> {
+        let mut cmd = SimpleCommand::default();
+
+        loop {
+            match self.tokens.peek() {
+                Some(Token::Word(_)) => {
+                    if let Some(Token::Word(word)) = self.tokens.next() {
+                        match cmd.words.is_empty() {
+                            true => match assignment(word) {
+                                Ok(assignment) => cmd.assignments.push(assignment),
+                                Err(word) => cmd.words.push(word),
+                            },
+                            false => cmd.words.push(word),
                         }
-                    } else {
-                        // `&> ` or `>&\t` or `&>\n`
-                        return Err("shell: syntax error near unexpected token `newline'\n".into());
                     }
                 }
+                Some(Token::IoNumber(_) | Token::HereDocument { .. }) => {
+                    cmd.redirections.push(self.redirection()?);
+                }
+                Some(Token::Operator(op)) if redirection_op(*op).is_some() => {
+                    cmd.redirections.push(self.redirection()?);
+                }
+                _ => break,
             }
         }
-        RedirectionFsm::Stdout => {
-            if next_peeked.eq(&'2') {
-                // `1>&2` or `&>2`
-
-                // if stdout.paths.is_empty() {
-                //     *redirection = RedirectionFsm::None;
-                //     stdout.kind = RedirectionMode::None;
-                //     stderr.kind = RedirectionMode::None;
-                // } else {
-                *redirection = RedirectionFsm::Stderr;
-                stderr.kind = stdout.kind.clone();
-                stdout.kind = RedirectionMode::None;
-                // }
-                input.next();
-
-                // stderr.paths.push(PathBuf::new());
-            }
+
+        if cmd.assignments.is_empty() && cmd.words.is_empty() && cmd.redirections.is_empty() {
+            return Err(self.unexpected());
         }
-        RedirectionFsm::Stderr => {
-            if next_peeked.eq(&'1') {
-                // `2>&1`
-                // if stderr.paths.is_empty() {
-                //     *redirection = RedirectionFsm::None;
-                //     stdout.kind = RedirectionMode::None;
-                //     stderr.kind = RedirectionMode::None;
-                // } else {
-                *redirection = RedirectionFsm::Stdout;
-                stdout.kind = stderr.kind.clone();
-                stderr.kind = RedirectionMode::None;
-                // }
-                input.next();
+
+        Ok(cmd)
+    }
+
+    /// Parses a redirection, which is an optional I/O number, followed by a redirection operator and its target,
+    /// or by a here-document.
+    ///
+    /// # Errors
+    /// - Returns [`InvalidInputError`] in case the target is missing, e.g., `cmd > | cmd` or `cmd > > file`.
+    fn redirection(&mut self) -> Result<Redirection, InvalidInputError> {
+        let fd = match self.tokens.peek() {
+            Some(&Token::IoNumber(fd)) => {
+                self.tokens.next();
+                Some(fd)
             }
-        }
-        _ => {} // todo rem
-    }
-
-    // *redirection = RedirectionFsm::None; // todo ?
-
-    Ok(())
-
-    /*
-        let next_peeked = input.peek().unwrap_or(&' ');
-        // let mut next: Option<char> = None; todo
-
-        if next_peeked.eq(&'>') {
-            // shell-specific `&>word` or `&> word`
-            stdout.kind = RedirectionMode::Overwrite;
-            stderr.kind = RedirectionMode::Overwrite;
-            *redirection = RedirectionFsm::CombinedStdout;
-            let next = input.next();
-            if next.is_some() {
-                let next_next_peeked = input.peek().unwrap_or(&' ');
-                if next_next_peeked.eq(&'>') {
-                    // shell-specific `&>>`
-                    stdout.kind = RedirectionMode::Append;
-                    stderr.kind = RedirectionMode::Append;
-                    input.next();
-                    return Ok(());
-                } else if next_next_peeked.eq(&'&') {
-                    // `&>&`
-                    return Err("shell: syntax error near unexpected token `&'\n".into());
-                }
+            _ => None,
+        };
+
+        let op = match self.tokens.next() {
+            Some(Token::HereDocument {
+                strip_tabs,
+                delimiter,
+                body,
+            }) => {
+                let op = RedirectionOp::HereDocument { strip_tabs };
+                return Ok(Redirection {
+                    fd: fd.unwrap_or(op.default_fd()),
+                    op,
+                    target: delimiter,
+                    body: Some(body),
+                });
             }
-        } else if item.is_empty() && next_peeked.is_whitespace() {
-            // Unimplemented: Background operation
-            return Err("shell: unimplemented `&'\n".into());
-        } else if next_peeked.eq(&'&') {
-            // Unimplemented: The logical AND operator
-            return Err("shell: unimplemented `&&'\n".into());
+            Some(Token::Operator(op)) => match redirection_op(op) {
+                Some(op) => op,
+                None => return Err(syntax_error(&Token::Operator(op))),
+            },
+            token => return Err(syntax_error_at(token.as_ref())),
+        };
+
+        match self.tokens.next() {
+            Some(Token::Word(target)) => Ok(Redirection {
+                fd: fd.unwrap_or(op.default_fd()),
+                op,
+                target,
+                body: None,
+            }),
+            token => Err(syntax_error_at(token.as_ref())),
         }
+    }
+
+    /// Checks whether there are more tokens, e.g., after an operator that has to be followed by a command.
+    ///
+    /// # Errors
+    /// - Returns incomplete [`InvalidInputError`] at the end of input, which more input may complete.
+    fn expect_more(&mut self) -> Result<(), InvalidInputError> {
+        match self.tokens.peek() {
+            Some(_) => Ok(()),
+            None => Err(InvalidInputError::incomplete(UNEXPECTED_END)),
+        }
+    }
 
-        Ok(())
-    */
+    /// Constructs the error for a syntax error near the next token, which is unexpected.
+    fn unexpected(&mut self) -> InvalidInputError {
+        syntax_error_at(self.tokens.peek())
+    }
 }
 
-// fn handle_closing_angle_bracket_unquoted(
-//     items: &mut Vec<String>,
-//     item: &mut String,
-//     redirection: &mut Redirection,
-//     stdout: &mut Stdout,
-//     stderr: &mut Stderr,
-// ) -> Result<(), InvalidInputError> {
-//     if (*item).eq("1") {
-//         // item.clear(); todo
-//         match redirection {
-//             Redirection::None => {
-//                 // `1>`
-//                 stdout.kind = RedirectionMode::Overwrite;
-//             }
-//             Redirection::Stdout => {
-//                 match stdout.kind {
-//                     RedirectionMode::None => panic!("redirection mode should not be none"),
-//                     RedirectionMode::Overwrite => {
-//                         // `1>>`
-//                         stdout.kind = RedirectionMode::Append;
-//                     }
-//                     RedirectionMode::Append => {
-//                         // `>>>`
-//                         return Err("shell: syntax error near unexpected token `>'\n".into());
-//                     }
-//                 }
-//             }
-//             Redirection::Stderr => panic!("redirection should not be stderr"),
-//             _ => {} // todo remove
-//         }
-//     } else if (*item).eq("2") {
-//         // item.clear(); todo
-//         match redirection {
-//             Redirection::None => {
-//                 // `2>`
-//                 stderr.kind = RedirectionMode::Overwrite;
-//             }
-//             Redirection::Stdout => panic!("redirection should not be stdout"),
-//             Redirection::Stderr => {
-//                 match stderr.kind {
-//                     RedirectionMode::None => panic!("redirection mode should not be none"),
-//                     RedirectionMode::Overwrite => {
-//                         // `2>>`
-//                         stderr.kind = RedirectionMode::Append;
-//                     }
-//                     RedirectionMode::Append => {
-//                         // `>>>`
-//                         return Err("shell: syntax error near unexpected token `>'\n".into());
-//                     }
-//                 }
-//             }
-//             _ => {} // todo remove
-//         }
-//     } else {
-//         // `>`
-//         if !item.is_empty() {
-//             items.push(item.to_string());
-//             item.clear();
-//         }
-//         match redirection {
-//             Redirection::None => {
-//                 // `>`
-//                 stdout.kind = RedirectionMode::Overwrite;
-//             }
-//             Redirection::Stdout => {
-//                 match stdout.kind {
-//                     RedirectionMode::None => panic!("redirection mode should not be none"),
-//                     RedirectionMode::Overwrite => {
-//                         // `>>`
-//                         stdout.kind = RedirectionMode::Append;
-//                     }
-//                     RedirectionMode::Append => {
-//                         // `>>>`
-//                         return Err("shell: syntax error near unexpected token `>'\n".into());
-//                     }
-//                 }
-//             }
-//             Redirection::Stderr => panic!("redirection should not be stderr"),
-//             _ => {} // todo remove
-//         }
-//     }
-//
-//     Ok(())
-// }
-
-// fn handle_closing_angle_bracket_unquoted(
-//     input: &mut Peekable<Chars>,
-//     items: &mut Vec<String>,
-//     item: &mut String,
-//     redirection: &mut Redirection,
-//     stdout: &mut Stdout,
-//     stderr: &mut Stderr,
-//     // redirect: &mut Redirect, todo
-// ) -> Result<(), InvalidInputError> {
-//     let next_peeked = input.peek().unwrap_or(&' ');
-//     let mut next: Option<char> = None;
-//
-//     if (*item).eq("1") {
-//         // `1>`
-//         item.clear();
-//         if next_peeked.eq(&'>') {
-//             // `1>>`
-//             stdout.kind = RedirectionMode::Append;
-//             next = input.next();
-//         } else {
-//             stdout.kind = RedirectionMode::Overwrite;
-//         }
-//         *redirection = Redirection::Stdout;
-//         // stdout.update(RedirectionKind::Overwrite, PathBuf::new()); todo
-//     } else if (*item).eq("2") {
-//         // `2>`
-//         item.clear();
-//         if next_peeked.eq(&'>') {
-//             // `2>>`
-//             stderr.kind = RedirectionMode::Append;
-//             next = input.next();
-//         } else {
-//             stderr.kind = RedirectionMode::Overwrite;
-//         }
-//         *redirection = Redirection::Stderr;
-//         // stderr.update(RedirectionKind::Overwrite, PathBuf::new()); todo
-//     } else {
-//         // `>`
-//         if !item.is_empty() {
-//             items.push(item.to_string());
-//             item.clear();
-//         }
-//         if next_peeked.eq(&'>') {
-//             // `>>`
-//             stdout.kind = RedirectionMode::Append;
-//             next = input.next();
-//         } else {
-//             stdout.kind = RedirectionMode::Overwrite;
-//         }
-//         *redirection = Redirection::Stdout;
-//         // stdout.update(RedirectionKind::Overwrite, PathBuf::new()); todo
-//     }
-//
-//     // if next.is_some() {
-//     //     let next_next_peeked = input.peek().unwrap_or(&' ');
-//     //     // TODO: Handle `>&` here? And `>&2`? Also `>&&`? Try in handle_ampersand_unquoted().
-//     //     if next_next_peeked.eq(&'>') {
-//     //         // `>>>`
-//     //         return Err("shell: syntax error near unexpected token `>'\n".into());
-//     //     }
-//     // }
-//
-//     match input.peek() {
-//         Some('>') => return Err("shell: syntax error near unexpected token `>'\n".into()), // `>>>`
-//         Some('&') => {
-//             let next = input.next();
-//             let next_next_peeked = input.peek().unwrap_or(&' ');
-//
-//             // shell-specific `>&word` or `>& word`
-//             stdout.kind = RedirectionMode::Overwrite;
-//             stderr.kind = RedirectionMode::Overwrite;
-//             *redirection = Redirection::CombinedStdout;
-//             let next = input.next();
-//         }
-//         Some(..) => {}
-//         None => {}
-//     }
-//
-//     // ORIGINAL:
-//     // match redirect {
-//     //     Redirect::None => {
-//     //         if (*item).eq("1") {
-//     //             // `1>`
-//     //             item.clear();
-//     //             *redirect = Redirect::Stdout(String::new());
-//     //         } else if (*item).eq("2") {
-//     //             // `2>`
-//     //             item.clear();
-//     //             *redirect = Redirect::Stderr(String::new());
-//     //         } else {
-//     //             // `>`
-//     //             if !item.is_empty() {
-//     //                 items.push(item.to_string());
-//     //                 item.clear();
-//     //             }
-//     //             *redirect = Redirect::Stdout(String::new());
-//     //         }
-//     //     }
-//     //     Redirect::Stdout(trg) => *redirect = Redirect::AppendStdout(trg.to_owned()),
-//     //     Redirect::Stderr(trg) => *redirect = Redirect::AppendStderr(trg.to_owned()),
-//     //     Redirect::CombinedStdout(trg) => *redirect = Redirect::AppendCombinedStdout(trg.to_owned()),
-//     //     Redirect::AppendStdout(_)
-//     //     | Redirect::AppendStderr(_)
-//     //     | Redirect::AppendCombinedStdout(_) => {
-//     //         return Err("shell: syntax error near unexpected token `>'\n".into());
-//     //     }
-//     //     _ => todo!(),
-//     // }
-//
-//     Ok(())
-// }
-
-/*
-/// Handles the received `&` character in the [`Fsm::Unquoted`] state.
-///
-/// Supports shell-specific `&>word` and shell-specific `>&word`, which redirect both
-/// `stdout` and `stderr` to the file whose name is the expansion of `word`.
-///
-/// Unimplemented: `&` on its own is used for background operation, and `&&` as logical AND.
+/// Converts the `word` into an assignment if it is one, i.e., if it starts with an unquoted valid name
+/// followed by `=`. Otherwise, returns the `word` itself.
 ///
 /// # References
-/// - [Redirecting Output](https://www.gnu.org/software/bash/manual/bash.html#Redirecting-Output)
-///
-/// # Errors
-/// - Returns [`InvalidInputError`] in case of `&>&` or `>&&`.
-/// - Returns [`InvalidInputError`] in case of unimplemented cases, i.e., ` & ` (`bg`) and ` && ` (`AND`).
-fn handle_ampersand_unquoted(
-    input: &mut Peekable<Chars>,
-    item: &mut str,
-    redirection: &mut Redirection,
-    stdout: &mut Stdout,
-    stderr: &mut Stderr,
-) -> Result<(), InvalidInputError> {
-    let next_peeked = input.peek().unwrap_or(&' ');
-    // let mut next: Option<char> = None; todo
-
-    if next_peeked.eq(&'>') {
-        // shell-specific `&>word` or `&> word`
-        stdout.kind = RedirectionMode::Overwrite;
-        stderr.kind = RedirectionMode::Overwrite;
-        *redirection = Redirection::CombinedStdout;
-        let next = input.next();
-        if next.is_some() {
-            let next_next_peeked = input.peek().unwrap_or(&' ');
-            if next_next_peeked.eq(&'>') {
-                // shell-specific `&>>`
-                stdout.kind = RedirectionMode::Append;
-                stderr.kind = RedirectionMode::Append;
-                input.next();
-                return Ok(());
-            } else if next_next_peeked.eq(&'&') {
-                // `&>&`
-                return Err("shell: syntax error near unexpected token `&'\n".into());
-            }
-        }
-    } else if item.is_empty() && next_peeked.is_whitespace() {
-        // Unimplemented: Background operation
-        return Err("shell: unimplemented `&'\n".into());
-    } else if next_peeked.eq(&'&') {
-        // Unimplemented: The logical AND operator
-        return Err("shell: unimplemented `&&'\n".into());
-    }
-
-    // match redirection {
-    //     Redirection::None => {}
-    //     Redirection::Stdout => {
-    //         if next_peeked.eq(&'2') {
-    //             // `1>&2` or `>&2`
-    //             *redirection = Redirection::Stderr;
-    //             stdout.kind = RedirectionKind::Overwrite;
-    //             stderr.kind = RedirectionKind::Overwrite;
-    //             // *redirection = Redirection::CombinedStderr; todo
-    //             input.next();
-    //         } else {
-    //             // shell-specific `>&word` or `>& word`
-    //             *redirection = Redirection::Stdout;
-    //             stdout.kind = RedirectionKind::Overwrite;
-    //             stderr.kind = RedirectionKind::Overwrite;
-    //             // *redirection = Redirection::CombinedStdout; todo
-    //         }
-    //     }
-    //     Redirection::Stderr => {
-    //         if next_peeked.eq(&'1') {
-    //             // `2>&1`
-    //             *redirection = Redirection::Stdout;
-    //             stdout.kind = RedirectionKind::Overwrite;
-    //             stderr.kind = RedirectionKind::Overwrite;
-    //             input.next();
-    //         }
-    //     }
-    //     Redirection::CombinedStdout => {}
-    //     Redirection::CombinedStderr => {}
-    // }
-
-    /*
-        let next = input.peek().unwrap_or(&' ');
-
-        match redirect {
-            Redirect::None => {
-                // Unimplemented: `&` on its own is used for background operation, and `&&` as logical AND.
-                if next.eq(&'>') {
-                    // shell-specific `&>word` or `&> word`
-                    *redirect = Redirect::CombinedStdout(String::new());
-                    input.next();
-                } else if item.is_empty() && next.is_whitespace() {
-                    // Unimplemented: Background operation
-                    return Err("shell: unimplemented `&'\n".into());
-                } else if next.eq(&'&') {
-                    // Unimplemented: The logical AND operator
-                    return Err("shell: unimplemented `&&'\n".into());
-                }
-            }
-            Redirect::Stdout(trg) => {
-                if next.eq(&'2') {
-                    // `1>&2` or `>&2`
-                    *redirect = Redirect::Stderr(trg.to_owned());
-                    input.next();
-                } else {
-                    // shell-specific `>&word` or `>& word`
-                    *redirect = Redirect::CombinedStdout(trg.to_owned());
-                }
-            }
-            Redirect::Stderr(trg) => {
-                if next.eq(&'1') {
-                    // `2>&1`
-                    *redirect = Redirect::Stdout(trg.to_owned());
-                    input.next();
-                }
-            }
-            Redirect::AppendStdout(trg) => {
-                if next.eq(&'2') {
-                    // `1>>&2` or `>>&2`
-                    *redirect = Redirect::AppendStderr(trg.to_owned());
-                    input.next();
-                }
-            }
-            Redirect::AppendStderr(trg) => {
-                if next.eq(&'1') {
-                    // `2>>&1`
-                    *redirect = Redirect::AppendStdout(trg.to_owned());
-                    input.next();
-                }
-            }
-            Redirect::CombinedStdout(_) | Redirect::AppendCombinedStdout(_) => {
-                return Err("shell: syntax error near unexpected token `&'\n".into());
-            }
-            _ => todo!(),
+/// - [Simple Commands](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_09_01)
+fn assignment(word: Word) -> Result<Assignment, Word> {
+    let Some(WordPart::Literal(text)) = word.parts.first() else {
+        return Err(word);
+    };
+    let Some((name, value)) = text.split_once('=') else {
+        return Err(word);
+    };
+    if !is_name(name) {
+        return Err(word);
+    }
+
+    let (name, value) = (name.to_string(), value.to_string());
+    let mut parts = word.parts;
+    match value.is_empty() {
+        true => {
+            parts.remove(0);
         }
-    */
+        false => parts[0] = WordPart::Literal(value),
+    }
+
+    Ok(Assignment {
+        name,
+        value: Word { parts },
+    })
+}
 
-    Ok(())
+/// Converts an operator token into the redirection operator, if it is one.
+fn redirection_op(op: Operator) -> Option<RedirectionOp> {
+    let op = match op {
+        Operator::Less => RedirectionOp::Input,
+        Operator::Great | Operator::Clobber => RedirectionOp::Output,
+        Operator::DoubleGreat => RedirectionOp::Append,
+        Operator::LessGreat => RedirectionOp::ReadWrite,
+        Operator::LessAnd => RedirectionOp::DupInput,
+        Operator::GreatAnd => RedirectionOp::DupOutput,
+        Operator::AndGreat => RedirectionOp::OutputAll,
+        Operator::AndDoubleGreat => RedirectionOp::AppendAll,
+        Operator::TripleLess => RedirectionOp::HereString,
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+/// Constructs the error for a syntax error near the unexpected `token`, or near the end of input if it is `None`.
+fn syntax_error_at(token: Option<&Token>) -> InvalidInputError {
+    syntax_error(token.unwrap_or(&Token::Newline))
+}
+
+/// Constructs the error for a syntax error near the unexpected `token`.
+fn syntax_error(token: &Token) -> InvalidInputError {
+    format!("syntax error near unexpected token `{token}'\n")
+        .as_str()
+        .into()
 }
-*/
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_input, RedirectionMode, Redirections, StdOutErr, Stderr, Stdout};
+    use super::parse_input;
+    use crate::ast::{
+        AndOrList, AndOrOp, Command, CompoundCommand, ParameterExpansion, ParameterOp, Pipeline,
+        RedirectionOp, SimpleCommand, Word, WordPart,
+    };
     use crate::errors::InvalidInputError;
-    use std::path::PathBuf;
+
+    /// Gets the words of the simple command at the `index` in the `pipeline`, after quote removal.
+    fn items(pipeline: &Pipeline, index: usize) -> Vec<String> {
+        let Command::Simple(cmd) = &pipeline.commands[index] else {
+            panic!("Expected a simple command");
+        };
+        cmd.words.iter().map(|word| word.unquoted_text()).collect()
+    }
+
+    /// Gets the redirections of the simple command at the `index` in the `pipeline`,
+    /// with their targets, or bodies, after quote removal.
+    fn redirections(pipeline: &Pipeline, index: usize) -> Vec<(i32, RedirectionOp, String)> {
+        let Command::Simple(cmd) = &pipeline.commands[index] else {
+            panic!("Expected a simple command");
+        };
+        cmd.redirections
+            .iter()
+            .map(|redir| {
+                let target = redir.body.as_ref().unwrap_or(&redir.target);
+                (redir.fd, redir.op, target.unquoted_text())
+            })
+            .collect()
+    }
+
+    /// Constructs an unbraced parameter expansion of the parameter `name`.
+    fn parameter(name: &str) -> WordPart {
+        WordPart::Parameter(ParameterExpansion {
+            name: name.to_string(),
+            op: ParameterOp::Value,
+            braced: false,
+        })
+    }
 
     #[test]
     fn single_quotes_01() {
@@ -1106,12 +800,12 @@ mod tests {
             r#"world"#.to_string(),
         ];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0);
+        assert_eq!(expected, items(&result[0].first, 0));
 
         input = r#"echo 'hello world'"#;
         expected = vec!["echo".to_string(), r#"hello world"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0);
+        assert_eq!(expected, items(&result[0].first, 0));
 
         input = r#"echo 'shell     example' 'test''script' world''hello"#;
         expected = vec![
@@ -1121,7 +815,7 @@ mod tests {
             r#"worldhello"#.to_string(),
         ];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0);
+        assert_eq!(expected, items(&result[0].first, 0));
     }
 
     #[test]
@@ -1129,12 +823,12 @@ mod tests {
         let mut input = r#"echo '"'"#;
         let mut expected = vec![r#"""#.to_string()];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo '""'"#;
         expected = vec![r#""""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
     }
 
     #[test]
@@ -1142,12 +836,12 @@ mod tests {
         let mut input = r#"echo "'""#;
         let mut expected = vec![r#"'"#.to_string()];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "''""#;
         expected = vec![r#"''"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
     }
 
     #[test]
@@ -1159,7 +853,7 @@ mod tests {
             r#"bar"#.to_string(),
         ];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0);
+        assert_eq!(expected, items(&result[0].first, 0));
 
         input = r#"echo "bar"   "shell's"   "foo""#;
         expected = vec![
@@ -1169,17 +863,17 @@ mod tests {
             r#"foo"#.to_string(),
         ];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0);
+        assert_eq!(expected, items(&result[0].first, 0));
 
         input = r#"echo "shell hello""#;
         expected = vec!["echo".to_string(), r#"shell hello"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0);
+        assert_eq!(expected, items(&result[0].first, 0));
 
         input = r#"echo "hello   script"  "world""shell""#;
         expected = vec![r#"hello   script"#.to_string(), r#"worldshell"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "world"  "example's"  hello""script"#;
         expected = vec![
@@ -1188,7 +882,7 @@ mod tests {
             r#"helloscript"#.to_string(),
         ];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo  'hello   world'  'hi''there'  "and""again"  "Hello,   world.""#;
         expected = vec![
@@ -1198,7 +892,7 @@ mod tests {
             r#"Hello,   world."#.to_string(),
         ];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"   echo  hi   there,   'hello   world'  'hi''"there"'  "and""again"  "Hello   world,   it's   me"   bye   bye."#;
         expected = vec![
@@ -1212,7 +906,7 @@ mod tests {
             r#"bye."#.to_string(),
         ];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
     }
 
     #[test]
@@ -1220,22 +914,22 @@ mod tests {
         let mut input = r#"echo \\"#;
         let mut expected = vec!["echo".to_string(), r#"\"#.to_string()];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0);
+        assert_eq!(expected, items(&result[0].first, 0));
 
         input = r#"echo '\'"#;
         expected = vec![r#"\"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo '\\'"#;
         expected = vec![r#"\\"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "\\""#;
         expected = vec![r#"\"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
     }
 
     #[test]
@@ -1243,47 +937,47 @@ mod tests {
         let mut input = r#"echo \'"#;
         let mut expected = vec![r#"'"#.to_string()];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo \""#;
         expected = vec![r#"""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "\"""#;
         expected = vec![r#"""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo '\"'"#;
         expected = vec![r#"\""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo '"\""'"#;
         expected = vec![r#""\"""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "\'""#;
         expected = vec![r#"\'"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "\\'""#;
         expected = vec![r#"\'"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "\\\"""#;
         expected = vec![r#"\""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo \   test"#;
         expected = vec![r#" "#.to_string(), r#"test"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
     }
 
     #[test]
@@ -1291,22 +985,22 @@ mod tests {
         let mut input = r#"echo "before\   after""#;
         let mut expected = vec![r#"before\   after"#.to_string()];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo script\ \ \ \ \ \ shell"#;
         expected = vec![r#"script      shell"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo \'\"shell world\"\'"#;
         expected = vec![r#"'"shell"#.to_string(), r#"world"'"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo \"\'shell world\'\""#;
         expected = vec![r#""'shell"#.to_string(), r#"world'""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
     }
 
     #[test]
@@ -1314,37 +1008,37 @@ mod tests {
         let mut input = r#"echo "\\n""#;
         let mut expected = vec![r#"\n"#.to_string()];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo example\ntest"#;
         expected = vec![r#"examplentest"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo 'example\ntest'"#;
         expected = vec![r#"example\ntest"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "example\ntest""#;
         expected = vec![r#"example\ntest"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo example\\ntest"#;
         expected = vec![r#"example\ntest"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo 'example\\ntest'"#;
         expected = vec![r#"example\\ntest"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "example\\ntest""#;
         expected = vec![r#"example\ntest"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
     }
 
     #[test]
@@ -1352,37 +1046,37 @@ mod tests {
         let mut input = r#"echo example\"testhello\"shell"#;
         let mut expected = vec![r#"example"testhello"shell"#.to_string()];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo 'example\"testhello\"shell'"#;
         expected = vec![r#"example\"testhello\"shell"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo 'shell\\\nscript'"#;
         expected = vec![r#"shell\\\nscript"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo 'test\\nscript'"#;
         expected = vec![r#"test\\nscript"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo 'hello\"worldexample\"test'"#;
         expected = vec![r#"hello\"worldexample\"test"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "hello'script'\\n'world""#;
         expected = vec![r#"hello'script'\n'world"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "hello\"insidequotes"script\""#;
         expected = vec![r#"hello"insidequotesscript""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
     }
 
     #[test]
@@ -1390,170 +1084,1165 @@ mod tests {
         let mut input = r#"echo "world'hello'\\'example""#;
         let mut expected = vec![r#"world'hello'\'example"#.to_string()];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "world\"insidequotes"hello\""#;
         expected = vec![r#"world"insidequoteshello""#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
 
         input = r#"echo "mixed\"quote'test'\\""#;
         expected = vec![r#"mixed"quote'test'\"#.to_string()];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+    }
+
+    /// Creates the expected redirection of the file descriptor `fd` to the `target`.
+    fn redirection(fd: i32, op: RedirectionOp, target: &str) -> (i32, RedirectionOp, String) {
+        (fd, op, target.to_string())
     }
 
     #[test]
     fn redirection_01a() {
         let mut input = r#"echo test 1> target_file"#;
         let expected = vec![r#"test"#.to_string()];
-        let mut stdout = Stdout {
-            kind: RedirectionMode::Overwrite,
-            paths: vec![PathBuf::from("target_file")],
-        };
-        let mut stderr = Stderr::new();
-        let mut exp_redir = Redirections::from(stdout, stderr);
+        let mut exp_redir = vec![redirection(1, RedirectionOp::Output, "target_file")];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
         input = r#"echo test > target_file"#;
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
         input = r#"echo test>target_file"#;
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
+
+        input = r#"echo test >| target_file"#;
+        result = parse_input(input).unwrap();
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
         input = r#"echo test 2> target_file"#;
-        stdout = Stdout::new();
-        stderr = Stderr {
-            kind: RedirectionMode::Overwrite,
-            paths: vec![PathBuf::from("target_file")],
-        };
-        exp_redir = Redirections::from(stdout, stderr);
+        exp_redir = vec![redirection(2, RedirectionOp::Output, "target_file")];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
     }
 
     #[test]
     fn redirection_01b() {
         let mut input = r#"echo test 1>2"#;
         let expected = vec![r#"test"#.to_string()];
-        let mut stdout = Stdout {
-            kind: RedirectionMode::Overwrite,
-            paths: vec![PathBuf::from("2")],
-        };
-        let mut stderr = Stderr::new();
-        let mut exp_redir = Redirections::from(stdout, stderr);
+        let mut exp_redir = vec![redirection(1, RedirectionOp::Output, "2")];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
         input = r#"echo test >2"#;
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
         input = r#"echo test 1>>2"#;
-        stdout = Stdout {
-            kind: RedirectionMode::Append,
-            paths: vec![PathBuf::from("2")],
-        };
-        stderr = Stderr::new();
-        exp_redir = Redirections::from(stdout, stderr);
+        exp_redir = vec![redirection(1, RedirectionOp::Append, "2")];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
         input = r#"echo test>>2"#;
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
     }
 
-    // TODO: See repl::handle_redirections(), where this is used. I don't think that's the issue.
     #[test]
     fn redirection_02a() {
         let mut input = r#"echo test 1>&2"#;
-        let mut expected = vec![r#"test"#.to_string()];
-        let mut stdout = Stdout::new();
-        let mut stderr = Stderr {
-            kind: RedirectionMode::Overwrite,
-            paths: vec![],
-        };
-        let mut exp_redir = Redirections::from(stdout, stderr);
+        let expected = vec![r#"test"#.to_string()];
+        let mut exp_redir = vec![redirection(1, RedirectionOp::DupOutput, "2")];
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
         input = r#"echo test >&2"#;
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
         input = r#"echo test 2>&1"#;
-        expected = vec![r#"test"#.to_string()];
-        stdout = Stdout::new();
-        stderr = Stderr::new();
-        exp_redir = Redirections::from(stdout, stderr);
+        exp_redir = vec![redirection(2, RedirectionOp::DupOutput, "1")];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
+
+        input = r#"echo test &> out"#;
+        exp_redir = vec![redirection(1, RedirectionOp::OutputAll, "out")];
+        result = parse_input(input).unwrap();
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
+
+        input = r#"echo test&>>out"#;
+        exp_redir = vec![redirection(1, RedirectionOp::AppendAll, "out")];
+        result = parse_input(input).unwrap();
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
+    }
+
+    #[test]
+    fn redirection_03() {
+        let mut input = r#"cmd >file 2>&1"#;
+        let mut exp_redir = vec![
+            redirection(1, RedirectionOp::Output, "file"),
+            redirection(2, RedirectionOp::DupOutput, "1"),
+        ];
+        let mut result = parse_input(input).unwrap();
+        assert_eq!(vec!["cmd".to_string()], items(&result[0].first, 0));
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
+
+        input = r#"cmd 2>&1 >file"#;
+        exp_redir.reverse();
+        result = parse_input(input).unwrap();
+        assert_eq!(vec!["cmd".to_string()], items(&result[0].first, 0));
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
+
+        input = r#"cmd 3>out 9>>log 4<&0 5<&- 1>&- 12>x a2>y "3">z"#;
+        exp_redir = vec![
+            redirection(3, RedirectionOp::Output, "out"),
+            redirection(9, RedirectionOp::Append, "log"),
+            redirection(4, RedirectionOp::DupInput, "0"),
+            redirection(5, RedirectionOp::DupInput, "-"),
+            redirection(1, RedirectionOp::DupOutput, "-"),
+            redirection(12, RedirectionOp::Output, "x"),
+            redirection(1, RedirectionOp::Output, "y"),
+            redirection(1, RedirectionOp::Output, "z"),
+        ];
+        result = parse_input(input).unwrap();
+        assert_eq!(
+            vec!["cmd".to_string(), "a2".to_string(), "3".to_string()],
+            items(&result[0].first, 0)
+        );
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
     }
 
-    // todo name - count 09
     #[test]
     fn redirection_09() {
         let mut input = r#"echo test > q > w > e >> r >> t > y > u"#;
         let expected = vec![r#"test"#.to_string()];
-        let paths = ["q", "w", "e", "r", "t", "y", "u"]
+        let ops = [
+            RedirectionOp::Output,
+            RedirectionOp::Output,
+            RedirectionOp::Output,
+            RedirectionOp::Append,
+            RedirectionOp::Append,
+            RedirectionOp::Output,
+            RedirectionOp::Output,
+        ];
+        let targets = ["q", "w", "e", "r", "t", "y", "u"];
+        let mut exp_redir = targets
             .iter()
-            .map(PathBuf::from)
+            .zip(ops)
+            .map(|(target, op)| redirection(1, op, target))
             .collect::<Vec<_>>();
-        let mut stdout = Stdout {
-            kind: RedirectionMode::Overwrite,
-            paths: paths.clone(),
-        };
-        let mut stderr = Stderr::new();
-        let mut exp_redir = Redirections::from(stdout, stderr);
         let mut result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
 
-        input = r#"echo test > q > w > e >> r > t >> y >> u"#;
-        stdout = Stdout {
-            kind: RedirectionMode::Append,
-            paths,
-        };
-        stderr = Stderr::new();
-        exp_redir = Redirections::from(stdout, stderr);
+        input = r#"echo test 2> q 2>> w"#;
+        exp_redir = vec![
+            redirection(2, RedirectionOp::Output, "q"),
+            redirection(2, RedirectionOp::Append, "w"),
+        ];
         result = parse_input(input).unwrap();
-        assert_eq!(expected, result.0[1..]);
-        assert_eq!(exp_redir, result.1);
+        assert_eq!(expected, items(&result[0].first, 0)[1..]);
+        assert_eq!(exp_redir, redirections(&result[0].first, 0));
+    }
+
+    /// Creates a simple command without redirections out of the given unquoted items.
+    fn simple_command(items: &[&str]) -> Command {
+        Command::Simple(SimpleCommand {
+            words: items.iter().map(|item| Word::literal(item)).collect(),
+            ..SimpleCommand::default()
+        })
+    }
+
+    /// Creates a pipeline out of the given commands.
+    fn pipeline(commands: Vec<Command>) -> Pipeline {
+        Pipeline {
+            commands,
+            negated: false,
+        }
     }
 
-    #[ignore]
     #[test]
-    /// This can be handled in code by eagerly scanning the input for `> >`.
-    fn invalid_input_ignored() {
-        let expected = InvalidInputError {
-            reason: "shell: syntax error near unexpected token `>'\n".to_string(),
-        };
+    fn pipeline_01() {
+        let mut input = r#"echo test | cat"#;
+        let expected = pipeline(vec![
+            simple_command(&["echo", "test"]),
+            simple_command(&["cat"]),
+        ]);
+        let mut result = parse_input(input).unwrap();
+        assert_eq!(expected, result[0].first);
+
+        input = r#"echo test|cat"#;
+        result = parse_input(input).unwrap();
+        assert_eq!(expected, result[0].first);
+
+        input = r#"echo 'a | b' "c|d" e\|f | wc -c | cat"#;
+        result = parse_input(input).unwrap();
+        assert_eq!(3, result[0].first.commands.len());
+        assert_eq!(
+            vec!["echo", "a | b", "c|d", "e|f"],
+            items(&result[0].first, 0)
+        );
+        assert_eq!(vec!["wc", "-c"], items(&result[0].first, 1));
+        assert_eq!(vec!["cat"], items(&result[0].first, 2));
+    }
+
+    #[test]
+    fn pipeline_02() {
+        let input = r#"ls nonexistent 2> err | cat > out"#;
+        let result = parse_input(input).unwrap();
+        assert_eq!(vec!["ls", "nonexistent"], items(&result[0].first, 0));
+        assert_eq!(
+            vec![redirection(2, RedirectionOp::Output, "err")],
+            redirections(&result[0].first, 0)
+        );
+        assert_eq!(vec!["cat"], items(&result[0].first, 1));
+        assert_eq!(
+            vec![redirection(1, RedirectionOp::Output, "out")],
+            redirections(&result[0].first, 1)
+        );
+        assert_eq!("ls nonexistent 2>err | cat >out", result[0].to_string());
+    }
+
+    #[test]
+    fn list_01() {
+        let mut input = r#"echo a; echo b ;echo c;"#;
+        let expected = vec![
+            AndOrList {
+                first: pipeline(vec![simple_command(&["echo", "a"])]),
+                rest: vec![],
+                background: false,
+            },
+            AndOrList {
+                first: pipeline(vec![simple_command(&["echo", "b"])]),
+                rest: vec![],
+                background: false,
+            },
+            AndOrList {
+                first: pipeline(vec![simple_command(&["echo", "c"])]),
+                rest: vec![],
+                background: false,
+            },
+        ];
+        let mut result = parse_input(input).unwrap();
+        assert_eq!(expected, result);
+
+        input = "echo a\n\necho b\n echo c";
+        result = parse_input(input).unwrap();
+        assert_eq!(expected, result);
+
+        input = r#"echo 'a;' "b;" c\;"#;
+        result = parse_input(input).unwrap();
+        assert_eq!(1, result.len());
+        assert_eq!(vec!["echo", "a;", "b;", "c;"], items(&result[0].first, 0));
+
+        input = r#"   "#;
+        result = parse_input(input).unwrap();
+        assert!(result.is_empty());
+    }
 
-        let input = r#"echo test > > file"#;
-        let result = parse_input(input).unwrap_err();
+    #[test]
+    fn list_02() {
+        let mut input = r#"make && ./run || echo failed | cat; pwd"#;
+        let expected = vec![
+            AndOrList {
+                first: pipeline(vec![simple_command(&["make"])]),
+                rest: vec![
+                    (AndOrOp::And, pipeline(vec![simple_command(&["./run"])])),
+                    (
+                        AndOrOp::Or,
+                        pipeline(vec![
+                            simple_command(&["echo", "failed"]),
+                            simple_command(&["cat"]),
+                        ]),
+                    ),
+                ],
+                background: false,
+            },
+            AndOrList {
+                first: pipeline(vec![simple_command(&["pwd"])]),
+                rest: vec![],
+                background: false,
+            },
+        ];
+        let mut result = parse_input(input).unwrap();
         assert_eq!(expected, result);
+
+        input = "make &&\n ./run ||\n\n echo failed |\n cat\npwd";
+        result = parse_input(input).unwrap();
         assert_eq!(expected, result);
+
+        input = r#"true&&false||echo 'a&&b' "c||d""#;
+        result = parse_input(input).unwrap();
+        assert_eq!(vec!["true"], items(&result[0].first, 0));
+        assert_eq!(AndOrOp::And, result[0].rest[0].0);
+        assert_eq!(vec!["false"], items(&result[0].rest[0].1, 0));
+        assert_eq!(AndOrOp::Or, result[0].rest[1].0);
+        assert_eq!(vec!["echo", "a&&b", "c||d"], items(&result[0].rest[1].1, 0));
+
+        input = r#"ls 2>&1 && echo ok"#;
+        result = parse_input(input).unwrap();
+        assert_eq!(vec!["ls".to_string()], items(&result[0].first, 0));
+        assert_eq!(AndOrOp::And, result[0].rest[0].0);
+        assert_eq!(
+            pipeline(vec![simple_command(&["echo", "ok"])]),
+            result[0].rest[0].1
+        );
+    }
+
+    #[test]
+    fn background_01() {
+        let mut input = r#"sleep 1 & echo a&& echo b&"#;
+        let expected = vec![
+            AndOrList {
+                first: pipeline(vec![simple_command(&["sleep", "1"])]),
+                rest: vec![],
+                background: true,
+            },
+            AndOrList {
+                first: pipeline(vec![simple_command(&["echo", "a"])]),
+                rest: vec![(AndOrOp::And, pipeline(vec![simple_command(&["echo", "b"])]))],
+                background: true,
+            },
+        ];
+        let mut result = parse_input(input).unwrap();
+        assert_eq!(expected, result);
+
+        input = r#"sleep 1 | cat & pwd; echo 'a&' "b&" c\&"#;
+        result = parse_input(input).unwrap();
+        assert_eq!(3, result.len());
+        assert_eq!(
+            pipeline(vec![
+                simple_command(&["sleep", "1"]),
+                simple_command(&["cat"])
+            ]),
+            result[0].first
+        );
+        assert!(result[0].background);
+        assert!(!result[1].background);
+        assert_eq!(vec!["echo", "a&", "b&", "c&"], items(&result[2].first, 0));
+        assert!(!result[2].background);
+        assert_eq!("sleep 1 | cat", result[0].to_string());
+        assert_eq!(r#"echo 'a&' "b&" c\&"#, result[2].to_string());
+
+        input = r#"ls&>out"#;
+        result = parse_input(input).unwrap();
+        assert_eq!(vec!["ls".to_string()], items(&result[0].first, 0));
+        assert!(!result[0].background);
+    }
+
+    #[test]
+    fn input_redirection_01() {
+        let input = r#"cat < in1 0<in2 3<> "rw file" <<< 'a b' > out"#;
+        let result = parse_input(input).unwrap();
+        let cmd = &result[0].first;
+        assert_eq!(vec!["cat".to_string()], items(cmd, 0));
+        let expected = vec![
+            redirection(0, RedirectionOp::Input, "in1"),
+            redirection(0, RedirectionOp::Input, "in2"),
+            redirection(3, RedirectionOp::ReadWrite, "rw file"),
+            redirection(0, RedirectionOp::HereString, "a b"),
+            redirection(1, RedirectionOp::Output, "out"),
+        ];
+        assert_eq!(expected, redirections(cmd, 0));
+
+        let input = r#"wc -l<in|cat"#;
+        let result = parse_input(input).unwrap();
+        assert_eq!(2, result[0].first.commands.len());
+        assert_eq!(
+            vec!["wc".to_string(), "-l".to_string()],
+            items(&result[0].first, 0)
+        );
+        assert_eq!("in", redirections(&result[0].first, 0)[0].2);
+    }
+
+    #[test]
+    fn input_redirection_02() {
+        for input in ["cat <", "cat < | wc", "cat < < in", "cat < >out"] {
+            assert!(parse_input(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn here_document_01() {
+        let input = "cat <<EOF | wc -l; cat <<-'END'\nline $?\n  \\$x \\y\nEOF\n\tline $?\n\tEND";
+        let result = parse_input(input).unwrap();
+        assert_eq!(2, result.len());
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let body = Word {
+            parts: vec![WordPart::DoubleQuoted(vec![
+                WordPart::Literal("line ".to_string()),
+                parameter("?"),
+                WordPart::Literal("\n  ".to_string()),
+                WordPart::Escaped('$'),
+                WordPart::Literal("x \\y\n".to_string()),
+            ])],
+        };
+        assert_eq!(Some(&body), cmd.redirections[0].body.as_ref());
+        assert_eq!(simple_command(&["wc", "-l"]), result[0].first.commands[1]);
+        assert_eq!("cat <<EOF | wc -l", result[0].to_string());
+        let expected = vec![redirection(
+            0,
+            RedirectionOp::HereDocument { strip_tabs: true },
+            "line $?\n",
+        )];
+        assert_eq!(expected, redirections(&result[1].first, 0));
+    }
+
+    #[test]
+    fn here_document_02() {
+        for input in ["cat <<EOF", "cat <<EOF\nbody", "cat <<EOF\nbody\nEOF "] {
+            let result = parse_input(input).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        let result = parse_input("cat <<EOF\n\nEOF\necho a").unwrap();
+        assert_eq!("\n", redirections(&result[0].first, 0)[0].2);
+        assert_eq!(simple_command(&["echo", "a"]), result[1].first.commands[0]);
+
+        let result = parse_input("cat <<A <<B\na\nA\nb\nB").unwrap();
+        let bodies = redirections(&result[0].first, 0)
+            .into_iter()
+            .map(|(_, _, body)| body)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a\n", "b\n"], bodies);
+
+        let result = parse_input("cat <<").unwrap_err();
+        assert!(!result.incomplete);
+    }
+
+    #[test]
+    fn here_document_03() {
+        let input = "cat <<EOF\nline \\\ncont `echo bq` \\`x\\\\\nEOF";
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let body = Word {
+            parts: vec![WordPart::DoubleQuoted(vec![
+                WordPart::Literal("line cont ".to_string()),
+                WordPart::CommandSubstitution {
+                    program: "echo bq".to_string(),
+                    backquoted: true,
+                },
+                WordPart::Literal(" ".to_string()),
+                WordPart::Escaped('`'),
+                WordPart::Literal("x".to_string()),
+                WordPart::Escaped('\\'),
+                WordPart::Literal("\n".to_string()),
+            ])],
+        };
+        assert_eq!(Some(&body), cmd.redirections[0].body.as_ref());
+
+        let result = parse_input("cat <<EOF\n`echo\nEOF").unwrap_err();
+        assert!(!result.incomplete);
+    }
+
+    #[test]
+    fn last_status() {
+        let input = r#"echo $? "$?" '$?' \$? "\$?" x$?y"#;
+        let expected = vec![
+            vec![parameter("?")],
+            vec![WordPart::DoubleQuoted(vec![parameter("?")])],
+            vec![WordPart::SingleQuoted("$?".to_string())],
+            vec![WordPart::Escaped('$'), WordPart::Literal("?".to_string())],
+            vec![WordPart::DoubleQuoted(vec![
+                WordPart::Escaped('$'),
+                WordPart::Literal("?".to_string()),
+            ])],
+            vec![
+                WordPart::Literal("x".to_string()),
+                parameter("?"),
+                WordPart::Literal("y".to_string()),
+            ],
+        ];
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let parts = cmd.words[1..]
+            .iter()
+            .map(|word| word.parts.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(expected, parts);
+    }
+
+    #[test]
+    fn parameter_expansion_01() {
+        let input = r#"echo $HOME $1x $10 $_a1-b $$ $# "$@" $ x$ $/"#;
+        let expected = vec![
+            vec![parameter("HOME")],
+            vec![parameter("1"), WordPart::Literal("x".to_string())],
+            vec![parameter("1"), WordPart::Literal("0".to_string())],
+            vec![parameter("_a1"), WordPart::Literal("-b".to_string())],
+            vec![parameter("$")],
+            vec![parameter("#")],
+            vec![WordPart::DoubleQuoted(vec![parameter("@")])],
+            vec![WordPart::Literal("$".to_string())],
+            vec![WordPart::Literal("x$".to_string())],
+            vec![WordPart::Literal("$/".to_string())],
+        ];
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let parts = cmd.words[1..]
+            .iter()
+            .map(|word| word.parts.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(expected, parts);
+    }
+
+    #[test]
+    fn parameter_expansion_02() {
+        let input = r#"echo ${10} ${#} ${#x} ${##} ${x:-a b} ${x=$y} ${x:?"no x"} ${x+${y:-}} ${x%%.*} ${x#\*}"#;
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let ops = cmd.words[1..]
+            .iter()
+            .map(|word| match word.parts.as_slice() {
+                [WordPart::Parameter(expansion)] => (expansion.name.as_str(), &expansion.op),
+                _ => panic!("Expected a parameter expansion: {word}"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(("10", &ParameterOp::Value), ops[0]);
+        assert_eq!(("#", &ParameterOp::Value), ops[1]);
+        assert_eq!(("x", &ParameterOp::Length), ops[2]);
+        assert_eq!(("#", &ParameterOp::Length), ops[3]);
+        let word = Word::literal("a b");
+        let op = ParameterOp::UseDefault {
+            check_null: true,
+            word,
+        };
+        assert_eq!(("x", &op), ops[4]);
+        let word = Word {
+            parts: vec![parameter("y")],
+        };
+        let op = ParameterOp::AssignDefault {
+            check_null: false,
+            word,
+        };
+        assert_eq!(("x", &op), ops[5]);
+        assert!(
+            matches!(ops[6].1, ParameterOp::Error { check_null: true, word } if word.unquoted_text() == "no x")
+        );
+        assert!(matches!(
+            ops[7].1,
+            ParameterOp::UseAlternative {
+                check_null: false,
+                ..
+            }
+        ));
+        assert_eq!(
+            ("x", &ParameterOp::RemoveLargestSuffix(Word::literal(".*"))),
+            ops[8]
+        );
+        let word = Word {
+            parts: vec![WordPart::Escaped('*')],
+        };
+        assert_eq!(("x", &ParameterOp::RemoveSmallestPrefix(word)), ops[9]);
+
+        assert_eq!(input, result[0].to_string());
+    }
+
+    #[test]
+    fn parameter_expansion_03() {
+        for input in [
+            r#"echo "${x:-"a b"}" <<EOF${x}"#,
+            "echo ${",
+            "echo ${x",
+            "echo ${x:-a",
+            "echo \"${x}",
+        ] {
+            let result = parse_input(input).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        for input in [
+            "echo ${}",
+            "echo ${x!}",
+            "echo ${x:%a}",
+            "echo ${#x:-a}",
+            "echo ${!x}",
+            "cat <<EOF\n${x\nEOF",
+        ] {
+            let result = parse_input(input).unwrap_err();
+            assert!(!result.incomplete, "{input}");
+        }
+    }
+
+    #[test]
+    fn parameter_expansion_04() {
+        let input = r#"echo "${x:-'a'\}\b}" ${x:-'a'}"#;
+        let default = |parts| ParameterOp::UseDefault {
+            check_null: true,
+            word: Word { parts },
+        };
+        let expected = vec![
+            vec![WordPart::DoubleQuoted(vec![WordPart::Parameter(
+                ParameterExpansion {
+                    name: "x".to_string(),
+                    op: default(vec![
+                        WordPart::Literal("'a'".to_string()),
+                        WordPart::Escaped('}'),
+                        WordPart::Literal("\\b".to_string()),
+                    ]),
+                    braced: true,
+                },
+            )])],
+            vec![WordPart::Parameter(ParameterExpansion {
+                name: "x".to_string(),
+                op: default(vec![WordPart::SingleQuoted("a".to_string())]),
+                braced: true,
+            })],
+        ];
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let parts = cmd.words[1..]
+            .iter()
+            .map(|word| word.parts.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(expected, parts);
+    }
+
+    #[test]
+    fn command_substitution_01() {
+        let input = r#"echo $(echo a | wc -c) "x$(echo ")" $(echo "(b"))" `echo \`echo b\` \$c` "`echo \"d\"`""#;
+        let substitution = |program: &str, backquoted| WordPart::CommandSubstitution {
+            program: program.to_string(),
+            backquoted,
+        };
+        let expected = vec![
+            vec![substitution("echo a | wc -c", false)],
+            vec![WordPart::DoubleQuoted(vec![
+                WordPart::Literal("x".to_string()),
+                substitution(r#"echo ")" $(echo "(b")"#, false),
+            ])],
+            vec![substitution("echo `echo b` $c", true)],
+            vec![WordPart::DoubleQuoted(vec![substitution(
+                r#"echo "d""#,
+                true,
+            )])],
+        ];
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let parts = cmd.words[1..]
+            .iter()
+            .map(|word| word.parts.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(expected, parts);
+    }
+
+    #[test]
+    fn command_substitution_02() {
+        for input in [
+            "echo $(",
+            "echo $(echo a",
+            "echo $(echo ')",
+            "echo `echo",
+            "x=$(echo a\necho b",
+            "echo $(cat <<EOF)",
+        ] {
+            let result = parse_input(input).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        for input in ["echo $(echo a;;)", "echo `|`"] {
+            let result = parse_input(input).unwrap_err();
+            assert!(!result.incomplete, "{input}");
+        }
+    }
+
+    #[test]
+    fn command_substitution_03() {
+        let input =
+            "echo $(case x in x) echo y;; esac) $(echo a # )\n) $( (echo b) ) $(echo ${x:-)})";
+        let expected = vec![
+            "case x in x) echo y;; esac",
+            "echo a # )\n",
+            " (echo b) ",
+            "echo ${x:-)}",
+        ];
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let programs = cmd.words[1..]
+            .iter()
+            .map(|word| match word.parts.as_slice() {
+                [WordPart::CommandSubstitution { program, .. }] => program.as_str(),
+                _ => panic!("Expected a command substitution: {word}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(expected, programs);
+    }
+
+    #[test]
+    fn command_substitution_04() {
+        // Each level of nesting is split into tokens only once, so deep nesting is fast
+        let depth = 200;
+        let input = format!("echo {}a{}", "$(echo ".repeat(depth), ")".repeat(depth));
+        let result = parse_input(&input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let expected = format!(
+            "echo {}a{}",
+            "$(echo ".repeat(depth - 1),
+            ")".repeat(depth - 1)
+        );
+        assert_eq!(
+            vec![WordPart::CommandSubstitution {
+                program: expected,
+                backquoted: false,
+            }],
+            cmd.words[1].parts
+        );
+
+        let input =
+            "echo $(case x in (a) (echo b);; x) case y in y) echo $(echo c);; esac;; esac) d";
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        assert_eq!(3, cmd.words.len());
+    }
+
+    #[test]
+    fn arithmetic_01() {
+        let input = r#"echo $((1 + $x*2)) "$(( (1) ))" $((echo a) | (echo b))"#;
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        assert!(matches!(
+            cmd.words[3].parts[0],
+            WordPart::CommandSubstitution { .. }
+        ));
+
+        let input = r#"echo $((1 + $x*2)) "$(( (1) ))" $(( $(echo 1) ))"#;
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        let expected = vec![
+            vec![WordPart::Arithmetic(Word {
+                parts: vec![
+                    WordPart::Literal("1 + ".to_string()),
+                    parameter("x"),
+                    WordPart::Literal("*2".to_string()),
+                ],
+            })],
+            vec![WordPart::DoubleQuoted(vec![WordPart::Arithmetic(
+                Word::literal(" (1) "),
+            )])],
+            vec![WordPart::Arithmetic(Word {
+                parts: vec![
+                    WordPart::Literal(" ".to_string()),
+                    WordPart::CommandSubstitution {
+                        program: "echo 1".to_string(),
+                        backquoted: false,
+                    },
+                    WordPart::Literal(" ".to_string()),
+                ],
+            })],
+        ];
+        let parts = cmd.words[1..]
+            .iter()
+            .map(|word| word.parts.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(expected, parts);
+        assert_eq!(input, result[0].to_string());
+    }
+
+    #[test]
+    fn assignments_01() {
+        let input = r#"a=1 b= c="x y"=z 1a=2 cmd d=3 >file"#;
+        let result = parse_input(input).unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+
+        let names = cmd
+            .assignments
+            .iter()
+            .map(|assignment| assignment.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a", "b", "c"], names);
+        assert_eq!(Word::literal("1"), cmd.assignments[0].value);
+        assert!(cmd.assignments[1].value.parts.is_empty());
+        assert_eq!("x y=z", cmd.assignments[2].value.unquoted_text());
+        let words = cmd
+            .words
+            .iter()
+            .map(|word| word.unquoted_text())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["1a=2", "cmd", "d=3"], words);
+        assert_eq!(input, result[0].to_string());
+
+        let result = parse_input("x=$y 'z=1' \\w=2").unwrap();
+        let Command::Simple(cmd) = &result[0].first.commands[0] else {
+            panic!("Expected a simple command");
+        };
+        assert_eq!(1, cmd.assignments.len());
+        assert_eq!(2, cmd.words.len());
+    }
+
+    #[test]
+    fn if_clause_01() {
+        let input = "if true; then echo a; elif ! false\nthen echo b &\nelse\n\n echo c; fi > file";
+        let result = parse_input(input).unwrap();
+        assert_eq!(1, result.len());
+        let Command::Compound {
+            command: CompoundCommand::If(clause),
+            redirections,
+        } = &result[0].first.commands[0]
+        else {
+            panic!("Expected an if conditional");
+        };
+
+        assert_eq!(2, clause.branches.len());
+        assert_eq!(vec!["echo", "a"], items(&clause.branches[0].1[0].first, 0));
+        assert!(clause.branches[1].0[0].first.negated);
+        assert!(clause.branches[1].1[0].background);
+        assert_eq!(
+            vec!["echo", "c"],
+            items(&clause.else_body.as_ref().unwrap()[0].first, 0)
+        );
+        assert_eq!(1, redirections.len());
+        assert_eq!(
+            "if true; then echo a; elif ! false; then echo b & else echo c; fi >file",
+            result[0].to_string()
+        );
+
+        let result = parse_input("echo if then fi; if if a; then b; fi; then c; fi").unwrap();
+        assert_eq!(vec!["echo", "if", "then", "fi"], items(&result[0].first, 0));
+        assert!(matches!(
+            result[1].first.commands[0],
+            Command::Compound { .. }
+        ));
+    }
+
+    #[test]
+    fn if_clause_02() {
+        for input in [
+            "if",
+            "if true",
+            "if true; then",
+            "if true; then echo fi",
+            "if a\nthen b\nelse",
+        ] {
+            let result = parse_input(input).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        for (input, token) in [
+            ("fi", "fi"),
+            ("if then a; fi", "then"),
+            ("if a; then fi", "fi"),
+            ("if a; then b; else fi", "fi"),
+            ("if a; then b; fi c", "c"),
+            ("echo | then", "then"),
+            ("! ! true", "!"),
+        ] {
+            let result = parse_input(input).unwrap_err();
+            let expected = format!("syntax error near unexpected token `{token}'\n");
+            assert_eq!(expected, result.reason, "{input}");
+        }
+    }
+
+    #[test]
+    fn for_clause_01() {
+        let input =
+            "for i in a \"b c\" $x; do echo $i | cat; done > file\nfor j\ndo\n echo $j\ndone";
+        let result = parse_input(input).unwrap();
+        assert_eq!(2, result.len());
+        let Command::Compound {
+            command: CompoundCommand::For(clause),
+            redirections,
+        } = &result[0].first.commands[0]
+        else {
+            panic!("Expected a for loop");
+        };
+
+        assert_eq!("i", clause.name);
+        assert_eq!(3, clause.words.as_ref().unwrap().len());
+        assert_eq!(2, clause.body[0].first.commands.len());
+        assert_eq!(1, redirections.len());
+        assert_eq!(
+            "for i in a \"b c\" $x; do echo $i | cat; done >file",
+            result[0].to_string()
+        );
+        assert_eq!("for j; do echo $j; done", result[1].to_string());
+
+        let result = parse_input("for i; do echo; done; for in in in; do echo; done").unwrap();
+        assert_eq!("for i; do echo; done", result[0].to_string());
+        let Command::Compound {
+            command: CompoundCommand::For(clause),
+            ..
+        } = &result[1].first.commands[0]
+        else {
+            panic!("Expected a for loop");
+        };
+        assert_eq!("in", clause.name);
+        assert_eq!(1, clause.words.as_ref().unwrap().len());
+    }
+
+    #[test]
+    fn while_clause_01() {
+        let input = "while a && b\ndo c; done; until ! d; do e &\ndone 2>&1";
+        let result = parse_input(input).unwrap();
+        assert_eq!(2, result.len());
+        let Command::Compound {
+            command: CompoundCommand::While(clause),
+            ..
+        } = &result[0].first.commands[0]
+        else {
+            panic!("Expected a while loop");
+        };
+        assert!(!clause.until);
+        assert_eq!(1, clause.condition[0].rest.len());
+        assert_eq!(vec!["c"], items(&clause.body[0].first, 0));
+        assert_eq!("until ! d; do e & done 2>&1", result[1].to_string());
+    }
+
+    #[test]
+    fn invalid_loops() {
+        for input in [
+            "for",
+            "for i",
+            "for i in a b",
+            "for i in a b; do",
+            "while true",
+            "until a; do b",
+        ] {
+            let result = parse_input(input).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        for (input, token) in [
+            ("done", "done"),
+            ("for 1 in a; do b; done", "1"),
+            ("for i in a b do c; done", "done"),
+            ("for i in a; echo; done", "echo"),
+            ("for i do; done", ";"),
+            ("while do b; done", "do"),
+            ("while a; do done", "done"),
+        ] {
+            let result = parse_input(input).unwrap_err();
+            let expected = format!("syntax error near unexpected token `{token}'\n");
+            assert_eq!(expected, result.reason, "{input}");
+        }
+    }
+
+    #[test]
+    fn case_clause_01() {
+        let input = "case $1 in\n  (a | \"b c\") echo a;;\n  *.txt) echo b | cat\n    echo c ;;\n  x) ;;\n  *) echo d\nesac >file";
+        let result = parse_input(input).unwrap();
+        assert_eq!(1, result.len());
+        let Command::Compound {
+            command: CompoundCommand::Case(clause),
+            redirections,
+        } = &result[0].first.commands[0]
+        else {
+            panic!("Expected a case conditional");
+        };
+
+        assert_eq!(4, clause.items.len());
+        assert_eq!(2, clause.items[0].patterns.len());
+        assert_eq!(2, clause.items[1].body.len());
+        assert!(clause.items[2].body.is_empty());
+        assert_eq!(vec!["echo", "d"], items(&clause.items[3].body[0].first, 0));
+        assert_eq!(1, redirections.len());
+        assert_eq!(
+            "case $1 in a | \"b c\") echo a;; *.txt) echo b | cat; echo c;; x) ;; *) echo d;; esac >file",
+            result[0].to_string()
+        );
+
+        let result = parse_input("case esac in in) case x in esac;; esac").unwrap();
+        assert_eq!(
+            "case esac in in) case x in esac;; esac",
+            result[0].to_string()
+        );
+    }
+
+    #[test]
+    fn case_clause_02() {
+        for input in [
+            "case",
+            "case x",
+            "case x in",
+            "case x in a",
+            "case x in a)",
+            "case x in a) echo;;",
+            "case x in a|",
+        ] {
+            let result = parse_input(input).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        for (input, token) in [
+            ("esac", "esac"),
+            ("case x y", "y"),
+            ("case x in a b) ;; esac", "b"),
+            ("case x in a|) ;; esac", ")"),
+            ("case x in ) ;; esac", ")"),
+            ("case x in a) echo;; b) echo; esac c", "c"),
+            ("echo a;; echo b", ";;"),
+            ("if a; then b;; fi", ";;"),
+        ] {
+            let result = parse_input(input).unwrap_err();
+            let expected = format!("syntax error near unexpected token `{token}'\n");
+            assert_eq!(expected, result.reason, "{input}");
+        }
+    }
+
+    #[test]
+    fn function_definition_01() {
+        let input = "f() if a; then b; fi 2>/dev/null; g ( )\n\nfor i; do echo $i; done\ng x y";
+        let result = parse_input(input).unwrap();
+        assert_eq!(3, result.len());
+        let Command::Function(function) = &result[0].first.commands[0] else {
+            panic!("Expected a function definition");
+        };
+
+        assert_eq!("f", function.name);
+        assert!(matches!(function.body, CompoundCommand::If(_)));
+        assert_eq!(1, function.redirections.len());
+        assert_eq!("f() if a; then b; fi 2>/dev/null", result[0].to_string());
+        assert_eq!("g() for i; do echo $i; done", result[1].to_string());
+        assert_eq!(vec!["g", "x", "y"], items(&result[2].first, 0));
+
+        for input in ["f()", "f() \n", "f() if"] {
+            let result = parse_input(input).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        for (input, token) in [
+            ("f() echo", "echo"),
+            ("f(a) if a; then b; fi", "a"),
+            ("echo f()", "("),
+            ("x=1 f()", "("),
+            ("1f() if a; then b; fi", "("),
+            ("f() fi", "fi"),
+        ] {
+            let result = parse_input(input).unwrap_err();
+            let expected = format!("syntax error near unexpected token `{token}'\n");
+            assert_eq!(expected, result.reason, "{input}");
+        }
+    }
+
+    #[test]
+    fn grouping_01() {
+        let input = "(cd dir; ls) | wc -l; { echo a\n echo b & } >file; f() { (echo c); }";
+        let result = parse_input(input).unwrap();
+        assert_eq!(3, result.len());
+        let Command::Compound {
+            command: CompoundCommand::Subshell(list),
+            ..
+        } = &result[0].first.commands[0]
+        else {
+            panic!("Expected a subshell");
+        };
+        assert_eq!(2, list.len());
+        assert_eq!(2, result[0].first.commands.len());
+
+        let Command::Compound {
+            command: CompoundCommand::BraceGroup(list),
+            redirections,
+        } = &result[1].first.commands[0]
+        else {
+            panic!("Expected a brace group");
+        };
+        assert!(list[1].background);
+        assert_eq!(1, redirections.len());
+
+        assert_eq!("(cd dir; ls) | wc -l", result[0].to_string());
+        assert_eq!("{ echo a; echo b & } >file", result[1].to_string());
+        assert_eq!("f() { (echo c); }", result[2].to_string());
+
+        let result = parse_input("echo { } a}; {echo").unwrap();
+        assert_eq!(vec!["echo", "{", "}", "a}"], items(&result[0].first, 0));
+        assert_eq!(vec!["{echo"], items(&result[1].first, 0));
+    }
+
+    #[test]
+    fn grouping_02() {
+        for input in ["(", "(echo a", "(echo a;", "{", "{ echo a", "{ echo a }"] {
+            let result = parse_input(input).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        for (input, token) in [
+            ("( )", ")"),
+            ("{ }", "}"),
+            ("}", "}"),
+            ("echo a)", ")"),
+            ("(echo a) b", "b"),
+            ("{ echo a; } b", "b"),
+            ("(echo a; } )", "}"),
+        ] {
+            let result = parse_input(input).unwrap_err();
+            let expected = format!("syntax error near unexpected token `{token}'\n");
+            assert_eq!(expected, result.reason, "{input}");
+        }
+    }
+
+    #[test]
+    fn comments() {
+        let input = "echo a # b c\n# only a comment\necho d#e";
+        let result = parse_input(input).unwrap();
+        assert_eq!(2, result.len());
+        assert_eq!(vec!["echo", "a"], items(&result[0].first, 0));
+        assert_eq!(vec!["echo", "d#e"], items(&result[1].first, 0));
+    }
+
+    #[test]
+    fn invalid_input_redirection() {
+        let expected = InvalidInputError {
+            reason: "syntax error near unexpected token `>'\n".to_string(),
+            incomplete: false,
+        };
+
+        for input in [
+            "echo test > > file",
+            "echo test 2> >file",
+            "echo test >& >file",
+        ] {
+            let result = parse_input(input).unwrap_err();
+            assert_eq!(expected, result, "{input}");
+        }
     }
 
     #[test]
     fn invalid_input() {
         let mut expected = InvalidInputError {
             reason: "unmatched escape character\n".to_string(),
+            incomplete: true,
         };
 
         let mut input = r#"echo \"#;
@@ -1562,6 +2251,7 @@ mod tests {
 
         expected = InvalidInputError {
             reason: "unmatched single quotes\n".to_string(),
+            incomplete: true,
         };
 
         input = r#"echo '"#;
@@ -1578,6 +2268,7 @@ mod tests {
 
         expected = InvalidInputError {
             reason: "unmatched double quotes\n".to_string(),
+            incomplete: true,
         };
 
         input = r#"echo ""#;
@@ -1589,7 +2280,8 @@ mod tests {
         assert_eq!(expected, result);
 
         expected = InvalidInputError {
-            reason: "shell: syntax error near unexpected token `>'\n".to_string(),
+            reason: "syntax error near unexpected token `>'\n".to_string(),
+            incomplete: false,
         };
 
         input = r#"echo test >>> file"#;
@@ -1605,7 +2297,8 @@ mod tests {
         assert_eq!(expected, result);
 
         expected = InvalidInputError {
-            reason: "shell: syntax error near unexpected token `&'\n".to_string(),
+            reason: "syntax error near unexpected token `&'\n".to_string(),
+            incomplete: false,
         };
 
         input = r#"echo test &>& file"#;
@@ -1615,5 +2308,59 @@ mod tests {
         input = r#"echo test >&& file"#;
         result = parse_input(input).unwrap_err();
         assert_eq!(expected, result);
+
+        expected = InvalidInputError {
+            reason: "syntax error near unexpected token `|'\n".to_string(),
+            incomplete: false,
+        };
+
+        input = r#"| cat"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!(expected, result);
+
+        input = r#"echo test | | cat"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!(expected, result);
+
+        // More input may follow an operator
+        expected = InvalidInputError::incomplete("syntax error: unexpected end of file\n");
+
+        input = r#"echo test |"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!(expected, result);
+
+        input = r#"echo test &&"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!(expected, result);
+
+        input = r#"echo test ||"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!(expected, result);
+
+        expected = InvalidInputError {
+            reason: "syntax error near unexpected token `;'\n".to_string(),
+            incomplete: false,
+        };
+
+        input = r#"; echo test"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!(expected, result);
+
+        input = r#"echo a;; echo b"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!("syntax error near unexpected token `;;'\n", result.reason);
+
+        expected = InvalidInputError {
+            reason: "syntax error near unexpected token `&&'\n".to_string(),
+            incomplete: false,
+        };
+
+        input = r#"&& echo test"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!(expected, result);
+
+        input = r#"echo a; && echo b"#;
+        result = parse_input(input).unwrap_err();
+        assert_eq!(expected, result);
     }
 }
@@ -0,0 +1,431 @@
+//! Pattern matching notation
+//!
+//! Patterns are used for removing prefixes and suffixes in parameter expansion, for matching the word
+//! of a `case` conditional, and for pathname expansion, in which they are matched against the names of files.
+//!
+//! A pattern is a string in which `*` matches any string, `?` matches any single character,
+//! and a bracket expression, `[...]`, matches a single character from a set.
+//! A backslash quotes the next character, so that it matches only itself,
+//! which is how the quoted parts of a word are represented in a pattern.
+//!
+//! # References
+//!
+//! - [Pattern Matching Notation](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_13)
+//! - [Pattern Matching @ Bash Reference Manual](https://www.gnu.org/software/bash/manual/bash.html#Pattern-Matching)
+
+use std::fs;
+use std::path::Path;
+
+/// An element of a parsed pattern
+#[derive(Debug, PartialEq)]
+enum Token {
+    /// A character that matches only itself
+    Char(char),
+    /// `?`, which matches any single character
+    Any,
+    /// `*`, which matches any string, including the empty string
+    Star,
+    /// A bracket expression, which matches a single character from a set, or not from it, if it is `negated`
+    Bracket {
+        negated: bool,
+        items: Vec<BracketItem>,
+    },
+}
+
+/// An element of a bracket expression
+#[derive(Debug, PartialEq)]
+enum BracketItem {
+    /// A single character
+    Char(char),
+    /// A range of characters, e.g., `a-z`
+    Range(char, char),
+    /// A character class, e.g., `[:alpha:]`
+    Class(String),
+}
+
+/// Checks whether the whole `text` matches the `pattern`.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let tokens = parse(pattern);
+    let text = text.chars().collect::<Vec<_>>();
+
+    matches_tokens(&tokens, &text)
+}
+
+/// Checks whether the `pattern` contains any special characters, i.e., whether it can match
+/// anything other than itself.
+pub fn has_special_chars(pattern: &str) -> bool {
+    parse(pattern)
+        .iter()
+        .any(|token| !matches!(token, Token::Char(_)))
+}
+
+/// Removes the smallest or the `largest` prefix of `text` that matches the `pattern`, and returns the rest.
+pub fn remove_prefix<'a>(pattern: &str, text: &'a str, largest: bool) -> &'a str {
+    let tokens = parse(pattern);
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut ends = (0..=chars.len()).collect::<Vec<_>>();
+    if largest {
+        ends.reverse();
+    }
+
+    ends.into_iter()
+        .find(|&end| matches_tokens(&tokens, &chars[..end]))
+        .map_or(text, |end| &text[byte_offset(text, end)..])
+}
+
+/// Removes the smallest or the `largest` suffix of `text` that matches the `pattern`, and returns the rest.
+pub fn remove_suffix<'a>(pattern: &str, text: &'a str, largest: bool) -> &'a str {
+    let tokens = parse(pattern);
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut starts = (0..=chars.len()).collect::<Vec<_>>();
+    if !largest {
+        starts.reverse();
+    }
+
+    starts
+        .into_iter()
+        .find(|&start| matches_tokens(&tokens, &chars[start..]))
+        .map_or(text, |start| &text[..byte_offset(text, start)])
+}
+
+/// Finds the pathnames that match the `pattern`, and returns them sorted.
+///
+/// Each component of the pattern between slashes is matched against the names of files separately,
+/// so that `*` and `?` never match `/`. A leading `.` of a filename must be matched by a literal `.`,
+/// and a trailing `/` matches only directories.
+///
+/// # References
+/// - [Patterns Used for Filename Expansion](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_13_03)
+pub fn glob(pattern: &str) -> Vec<String> {
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec!["/".to_string()], rest),
+        None => (vec![String::new()], pattern),
+    };
+    let components = rest.split('/').collect::<Vec<_>>();
+
+    for (idx, component) in components.iter().enumerate() {
+        let last = idx + 1 == components.len();
+        let mut found = Vec::new();
+        for path in &paths {
+            if component.is_empty() {
+                if Path::new(path).is_dir() {
+                    found.push(format!("{path}/"));
+                }
+            } else if !has_special_chars(component) {
+                let path = join_path(path, &unescape(component));
+                if (last && fs::symlink_metadata(&path).is_ok()) || Path::new(&path).is_dir() {
+                    found.push(path);
+                }
+            } else {
+                found.extend(
+                    matching_names(path, component)
+                        .into_iter()
+                        .map(|name| join_path(path, &name))
+                        .filter(|path| last || Path::new(path).is_dir()),
+                );
+            }
+        }
+        paths = found;
+    }
+
+    paths.sort();
+    paths
+}
+
+/// Returns the names of the files in the directory `path`, or in the current directory if it is empty,
+/// that match the `pattern`.
+///
+/// Names that start with `.` match only if the pattern starts with `.`, and names that aren't valid UTF-8
+/// are skipped. The directory entries `.` and `..` are included, so `.*` matches them, as in other shells.
+fn matching_names(path: &str, pattern: &str) -> Vec<String> {
+    let dir = if path.is_empty() { "." } else { path };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let tokens = parse(pattern);
+
+    [".", ".."]
+        .into_iter()
+        .map(str::to_string)
+        .chain(entries.filter_map(|entry| entry.ok()?.file_name().into_string().ok()))
+        .filter(|name| !name.starts_with('.') || pattern.starts_with('.'))
+        .filter(|name| matches_tokens(&tokens, &name.chars().collect::<Vec<_>>()))
+        .collect()
+}
+
+/// Appends the filename `name` to the `path`.
+fn join_path(path: &str, name: &str) -> String {
+    match path.is_empty() || path.ends_with('/') {
+        true => format!("{path}{name}"),
+        false => format!("{path}/{name}"),
+    }
+}
+
+/// Removes the backslashes that quote characters from a `pattern` without special characters.
+fn unescape(pattern: &str) -> String {
+    parse(pattern)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Char(ch) => Some(ch),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Converts an index of a character of `text` into the byte offset of that character.
+fn byte_offset(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(offset, _)| offset)
+}
+
+/// Parses the `pattern` into tokens.
+///
+/// A `[` that doesn't start a valid bracket expression matches only itself.
+fn parse(pattern: &str) -> Vec<Token> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let token = match chars[idx] {
+            '\\' if idx + 1 < chars.len() => {
+                idx += 1;
+                Token::Char(chars[idx])
+            }
+            '?' => Token::Any,
+            '*' => Token::Star,
+            '[' => match parse_bracket(&chars[idx + 1..]) {
+                Some((token, len)) => {
+                    idx += len;
+                    token
+                }
+                None => Token::Char('['),
+            },
+            ch => Token::Char(ch),
+        };
+        tokens.push(token);
+        idx += 1;
+    }
+
+    tokens
+}
+
+/// Parses a bracket expression, whose opening `[` precedes `chars`.
+///
+/// Returns the parsed expression, and the number of characters that it takes up, including the closing `]`,
+/// or `None` if there is no closing `]`.
+fn parse_bracket(chars: &[char]) -> Option<(Token, usize)> {
+    let mut idx = 0;
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    if negated {
+        idx += 1;
+    }
+
+    let mut items = Vec::new();
+    let start = idx;
+
+    loop {
+        let ch = *chars.get(idx)?;
+        match ch {
+            // A `]` right at the start is an ordinary character.
+            ']' if idx > start => break,
+            '[' if chars.get(idx + 1) == Some(&':') => {
+                let rest = &chars[idx + 2..];
+                let end = rest.windows(2).position(|pair| pair == [':', ']'])?;
+                items.push(BracketItem::Class(rest[..end].iter().collect()));
+                idx += end + 4;
+                continue;
+            }
+            _ => {}
+        }
+
+        let (first, len) = match ch {
+            '\\' => (*chars.get(idx + 1)?, 2),
+            _ => (ch, 1),
+        };
+        idx += len;
+
+        if chars.get(idx) == Some(&'-') && chars.get(idx + 1).is_some_and(|&next| next != ']') {
+            let (last, len) = match chars[idx + 1] {
+                '\\' => (*chars.get(idx + 2)?, 3),
+                next => (next, 2),
+            };
+            idx += len;
+            items.push(BracketItem::Range(first, last));
+        } else {
+            items.push(BracketItem::Char(first));
+        }
+    }
+
+    Some((Token::Bracket { negated, items }, idx + 1))
+}
+
+/// Checks whether the whole `text` matches the pattern `tokens`.
+///
+/// After a mismatch, the matching backtracks to the most recent `*`, which then takes one more character.
+fn matches_tokens(tokens: &[Token], text: &[char]) -> bool {
+    let (mut token_idx, mut text_idx) = (0, 0);
+    // The position right after the most recent `*`, and the position in the text that it matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while text_idx < text.len() {
+        let matched = match tokens.get(token_idx) {
+            Some(Token::Star) => {
+                token_idx += 1;
+                backtrack = Some((token_idx, text_idx));
+                continue;
+            }
+            Some(Token::Char(ch)) => *ch == text[text_idx],
+            Some(Token::Any) => true,
+            Some(Token::Bracket { negated, items }) => {
+                items.iter().any(|item| item.matches(text[text_idx])) != *negated
+            }
+            None => false,
+        };
+
+        if matched {
+            token_idx += 1;
+            text_idx += 1;
+        } else if let Some((after_star, star_end)) = backtrack {
+            token_idx = after_star;
+            text_idx = star_end + 1;
+            backtrack = Some((after_star, star_end + 1));
+        } else {
+            return false;
+        }
+    }
+
+    tokens[token_idx..]
+        .iter()
+        .all(|token| *token == Token::Star)
+}
+
+impl BracketItem {
+    /// Checks whether the character `ch` matches this item.
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            Self::Char(item) => *item == ch,
+            Self::Range(first, last) => (*first..=*last).contains(&ch),
+            Self::Class(class) => match class.as_str() {
+                "alnum" => ch.is_alphanumeric(),
+                "alpha" => ch.is_alphabetic(),
+                "blank" => ch == ' ' || ch == '\t',
+                "cntrl" => ch.is_control(),
+                "digit" => ch.is_ascii_digit(),
+                "graph" => ch.is_ascii_graphic(),
+                "lower" => ch.is_lowercase(),
+                "print" => ch.is_ascii_graphic() || ch == ' ',
+                "punct" => ch.is_ascii_punctuation(),
+                "space" => ch.is_whitespace(),
+                "upper" => ch.is_uppercase(),
+                "xdigit" => ch.is_ascii_hexdigit(),
+                _ => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob, has_special_chars, matches, remove_prefix, remove_suffix};
+    use std::fs;
+
+    #[test]
+    fn matches_01() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("*.rs", ".rs"));
+        assert!(!matches("*.rs", "main.rsx"));
+        assert!(matches("m?in.*", "main.rs"));
+        assert!(matches("*a*b*c*", "xxaxxbxxcxx"));
+        assert!(!matches("*a*b*c*", "xxaxxcxxbxx"));
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn matches_02() {
+        assert!(matches("[abc]x", "bx"));
+        assert!(!matches("[!abc]x", "bx"));
+        assert!(matches("[^abc]x", "dx"));
+        assert!(matches("[a-c][0-9]", "c7"));
+        assert!(matches("[]a]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("[[:upper:]][[:digit:]]", "A1"));
+        assert!(!matches("[[:upper:]]", "a"));
+        assert!(matches("[ab", "[ab"));
+        assert!(matches(r"\*\?\[", "*?["));
+        assert!(!matches(r"\*", "a"));
+    }
+
+    #[test]
+    fn has_special_chars_01() {
+        assert!(has_special_chars("*.rs"));
+        assert!(has_special_chars("[ab]"));
+        assert!(!has_special_chars(r"\*.rs"));
+        assert!(!has_special_chars("[ab"));
+    }
+
+    #[test]
+    fn remove_01() {
+        let path = "/usr/local/lib.tar.gz";
+        assert_eq!("usr/local/lib.tar.gz", remove_prefix("*/", path, false));
+        assert_eq!("lib.tar.gz", remove_prefix("*/", path, true));
+        assert_eq!("/usr/local/lib.tar", remove_suffix(".*", path, false));
+        assert_eq!("/usr/local/lib", remove_suffix(".*", path, true));
+        assert_eq!(path, remove_suffix("x*", path, true));
+        assert_eq!("", remove_prefix("*", path, true));
+        assert_eq!(path, remove_prefix("*", path, false));
+    }
+
+    #[test]
+    fn glob_01() {
+        let dir = std::env::temp_dir().join(format!("posix-shell-glob-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src/bin")).unwrap();
+        for file in [
+            "src/main.rs",
+            "src/lib.rs",
+            "src/.hidden.rs",
+            "src/bin/a.rs",
+            "README.md",
+        ] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let dir = dir.to_str().unwrap();
+
+        assert_eq!(
+            vec![format!("{dir}/src/lib.rs"), format!("{dir}/src/main.rs")],
+            glob(&format!("{dir}/src/*.rs"))
+        );
+        assert_eq!(
+            vec![format!("{dir}/src/.hidden.rs")],
+            glob(&format!("{dir}/src/.*.rs"))
+        );
+        assert_eq!(
+            vec![
+                format!("{dir}/src/."),
+                format!("{dir}/src/.."),
+                format!("{dir}/src/.hidden.rs")
+            ],
+            glob(&format!("{dir}/src/.*"))
+        );
+        assert_eq!(
+            vec![format!("{dir}/src/..")],
+            glob(&format!("{dir}/src/.?"))
+        );
+        assert_eq!(
+            vec![format!("{dir}/src/bin/a.rs")],
+            glob(&format!("{dir}/*/*/?.rs"))
+        );
+        assert_eq!(vec![format!("{dir}/src/")], glob(&format!("{dir}/*/")));
+        assert_eq!(
+            vec![format!("{dir}/README.md")],
+            glob(&format!("{dir}/[[:upper:]]*"))
+        );
+        assert!(glob(&format!("{dir}/src/[!lm]*.rs")).is_empty());
+        assert!(glob(&format!("{dir}/src/\\*.rs")).is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -4,371 +4,330 @@
 //!
 //! Takes user input, parses it and calls the appropriate command or program handlers.
 //!
+//! Also runs scripts, whose commands are read from a file instead, and command strings, which are given with `-c`,
+//! depending on the [`Invocation`] of the shell.
+//!
 //! # References
 //!
 //! - [REPL @ Wikipedia](https://en.wikipedia.org/wiki/Read%E2%80%93eval%E2%80%93print_loop)
 //! - [Bash Reference Manual](https://www.gnu.org/software/bash/manual/html_node/)
 
-use crate::cmd::{run_program, Output};
+use crate::ast::List;
 use crate::constants::{
-    Handler, COMMANDS, DEBUG, FAILED_FLUSH_TO_STDERR, FAILED_FLUSH_TO_STDOUT,
-    FAILED_READ_LINE, FAILED_WRITE_TO_STDERR, FAILED_WRITE_TO_STDOUT, HANDLERS, PROMPT, TEST,
+    CONTINUATION_PROMPT, DEBUG, FAILED_FLUSH_TO_STDOUT, FAILED_READ_LINE, FAILED_WRITE_TO_STDERR,
+    FAILED_WRITE_TO_STDOUT, PROMPT, STATUS_NOT_EXECUTABLE, STATUS_NOT_FOUND, STATUS_USAGE,
 };
-use crate::parse::{parse_input, RedirectionMode, Redirections};
-use crate::test_to_break_or_continue;
-use std::collections::HashMap;
-use std::fs::{File, OpenOptions};
-use std::io::{self, Stderr, Stdout, Write};
-use std::iter::zip;
-use std::path::PathBuf;
-use std::{fs, mem};
-
-/// The main shell loop.
-pub fn repl() {
+use crate::errors::InvalidInputError;
+use crate::exec::run_list;
+use crate::jobs::JobControl;
+use crate::parse::parse_input;
+use crate::shell::{Jump, Shell};
+use std::fs;
+use std::io::{self, ErrorKind, IsTerminal, Stderr, Stdout, Write};
+
+/// Usage of the shell, which is printed in case of invalid command-line arguments
+const USAGE: &str =
+    "usage: posix-shell [-c command_string [name [arg...]] | -s [arg...] | script [arg...]]\n";
+
+/// How the shell was invoked, which follows from its command-line arguments
+///
+/// # References
+/// - [sh](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/sh.html)
+#[derive(Debug, PartialEq)]
+pub enum Invocation {
+    /// Commands are read from the standard input, with the arguments as the positional parameters,
+    /// which is the case without arguments, or with the option `-s`
+    Stdin { args: Vec<String> },
+    /// The commands of the command string are run, which is the case with the option `-c`,
+    /// with the optional name as `$0`, and with the arguments as the positional parameters
+    CommandString {
+        command: String,
+        name: Option<String>,
+        args: Vec<String>,
+    },
+    /// The script at the path is run, with the arguments as the positional parameters
+    Script { path: String, args: Vec<String> },
+}
+
+impl Invocation {
+    /// Parses the command-line arguments of the shell, without the name of the shell itself.
+    ///
+    /// The options come first, and they end at the first argument that isn't an option, or after `--`.
+    ///
+    /// # Errors
+    /// - Returns the error message, followed by the usage, in case of an invalid option,
+    ///   or in case the command string is missing after `-c`.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut command_string = false;
+        let mut stdin = false;
+        let mut operands = args;
+
+        while let Some((arg, rest)) = operands.split_first() {
+            match arg.as_str() {
+                "-c" => command_string = true,
+                "-s" => stdin = true,
+                "--" => {
+                    operands = rest;
+                    break;
+                }
+                option if option.starts_with('-') && option.len() > 1 => {
+                    return Err(format!("shell: {option}: invalid option\n{USAGE}"));
+                }
+                _ => break,
+            }
+            operands = rest;
+        }
+
+        if command_string {
+            let Some((command, operands)) = operands.split_first() else {
+                return Err(format!("shell: -c: option requires an argument\n{USAGE}"));
+            };
+            let (name, operands) = match operands.split_first() {
+                Some((name, operands)) => (Some(name.clone()), operands),
+                None => (None, operands),
+            };
+            return Ok(Self::CommandString {
+                command: command.clone(),
+                name,
+                args: operands.to_vec(),
+            });
+        }
+
+        match operands.split_first() {
+            Some((path, operands)) if !stdin => Ok(Self::Script {
+                path: path.clone(),
+                args: operands.to_vec(),
+            }),
+            _ => Ok(Self::Stdin {
+                args: operands.to_vec(),
+            }),
+        }
+    }
+}
+
+/// The main shell loop, which reads commands from the standard input, with the `args` as the positional parameters.
+///
+/// The shell is interactive if its standard input is a terminal, in which case it prints the prompts.
+/// Otherwise, it reads the commands silently, e.g., from a pipe, as in `echo pwd | posix-shell`.
+///
+/// Returns the exit status of the last command at the end of input, which is the exit status of the shell.
+pub fn repl(args: Vec<String>) -> i32 {
     get_debug();
-    get_test();
-    eprintln!("DEBUG = {:?}, TEST = {:?}", DEBUG, TEST); // todo rem
 
-    let stdin = io::stdin();
     let mut stdout = io::stdout();
     let mut stderr = io::stderr();
 
+    let mut shell = Shell::new();
+    shell.interactive = io::stdin().is_terminal();
+    shell.job_control = JobControl::init();
+    shell.positional = args;
+
     loop {
+        // Notify the user about the jobs that have completed or stopped
+        shell.jobs.update();
+        write!(stderr, "{}", shell.jobs.notifications()).expect(FAILED_WRITE_TO_STDERR);
+
         // Print prompt
-        stdout.write_all(PROMPT).expect(FAILED_WRITE_TO_STDOUT);
-        stdout.flush().expect(FAILED_FLUSH_TO_STDOUT);
+        if shell.interactive {
+            stdout.write_all(PROMPT).expect(FAILED_WRITE_TO_STDOUT);
+            stdout.flush().expect(FAILED_FLUSH_TO_STDOUT);
+        }
 
         // Wait for user input
         let mut input = String::new();
-        stdin.read_line(&mut input).expect(FAILED_READ_LINE);
-
-        let input = input.trim();
-
-        if input.is_empty() {
-            test_to_break_or_continue!();
-            // test_to_break_or_continue!(TEST.get().is_some_and(|&test| test));
-            // todo rem
-            // if TEST.get().is_some_and(|&test| test) {
-            //     break;
-            // } else {
-            //     continue;
-            // }
-            // continue;
+        if read_line(&mut input).expect(FAILED_READ_LINE) == 0 {
+            // End of input, e.g., `Ctrl-D` on an empty line
+            if shell.interactive {
+                writeln!(stderr, "exit").expect(FAILED_WRITE_TO_STDERR);
+            }
+            return shell.last_status;
+        }
+
+        if input.trim().is_empty() {
+            continue;
         }
 
-        parse_input_and_handle_cmds(&mut stdout, &mut stderr, input);
+        parse_input_and_handle_cmds(&mut shell, &mut stdout, &mut stderr, &input);
+        if let Some(Jump::Exit(status)) = shell.jump {
+            return status;
+        }
     }
 }
 
-/// Parses user input and calls the appropriate command or program handler
-fn parse_input_and_handle_cmds(stdout: &mut Stdout, stderr: &mut Stderr, input: &str) {
-    let handlers = get_handlers();
+/// Runs the script at `path` non-interactively, with the `args` as its positional parameters,
+/// and returns the exit status of its last command, which is the exit status of the shell.
+///
+/// The special parameter `$0` is the `path`. The script is parsed as a whole before it is run,
+/// so none of it is run in case of a syntax error, in which case the exit status is [`STATUS_USAGE`].
+/// A `#!` line at the start of the script is a comment, so the script can be run directly,
+/// if the line names the shell.
+///
+/// The exit status is [`STATUS_NOT_FOUND`] if the script doesn't exist, or [`STATUS_NOT_EXECUTABLE`]
+/// if it can't be read.
+///
+/// # References
+/// - [Shell Scripts](https://www.gnu.org/software/bash/manual/bash.html#Shell-Scripts)
+pub fn run_script(path: &str, args: &[String]) -> i32 {
+    get_debug();
 
-    let (items, redirections) = match parse_input(input) {
-        Ok(items) => items,
-        Err(error) => {
-            write!(stderr, "{error}").expect(FAILED_WRITE_TO_STDERR);
-            return;
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("shell: {path}: {err}");
+            return match err.kind() {
+                ErrorKind::NotFound => STATUS_NOT_FOUND,
+                _ => STATUS_NOT_EXECUTABLE,
+            };
         }
     };
 
-    let items = items
-        .iter()
-        .map(|item| item.as_str())
-        .collect::<Vec<&str>>();
+    let mut shell = Shell::new();
+    shell.name = path.to_string();
+    shell.positional = args.to_vec();
 
-    let cmd = items[0].trim();
-    let args = if items.len() > 1 { &items[1..] } else { &[] };
-
-    let mut output = match handlers.get(cmd) {
-        Some(&handler) => handler(args),
-        None => run_program(cmd, args),
-    };
+    run_non_interactive(&mut shell, &input)
+}
 
-    // mem::swap(&mut output.stdout, &mut output.stderr); // TODO
+/// Runs the `command` string non-interactively, with the `args` as the positional parameters,
+/// and returns the exit status of its last command, which is the exit status of the shell.
+///
+/// The special parameter `$0` is the `name`, if it is given, or the name of the shell otherwise.
+/// The command string is parsed as a whole before it is run, so none of it is run in case of a syntax error,
+/// in which case the exit status is [`STATUS_USAGE`].
+pub fn run_command_string(command: &str, name: Option<String>, args: Vec<String>) -> i32 {
+    get_debug();
 
-    if DEBUG.get().is_some_and(|&debug| debug) {
-        eprintln!("cmd: {cmd:?}");
-        eprintln!("args: {args:?}");
-        eprintln!("redirections: {redirections:?}");
-        eprintln!("output: {output}");
-        eprintln!();
+    let mut shell = Shell::new();
+    if let Some(name) = name {
+        shell.name = name;
     }
+    shell.positional = args;
 
-    // TODO rem
-    // for redirect in redirections {
-    //     handle_redirection(stdout, stderr, redirect, &output);
-    // }
-
-    handle_redirections(stdout, stderr, redirections, output);
+    run_non_interactive(&mut shell, command)
+}
 
-    stdout.flush().expect(FAILED_FLUSH_TO_STDOUT);
-    stderr.flush().expect(FAILED_FLUSH_TO_STDERR);
+/// Parses the whole `input`, and runs the resulting list of commands without job control,
+/// and returns the exit status of the last command, or [`STATUS_USAGE`] in case of invalid input.
+///
+/// The exit status is `1` if the shell exits early because of a fatal expansion error.
+fn run_non_interactive(shell: &mut Shell, input: &str) -> i32 {
+    match parse_input(input) {
+        Ok(list) => {
+            run_list(shell, list);
+            match shell.jump {
+                Some(Jump::Exit(status)) => status,
+                _ => shell.last_status,
+            }
+        }
+        Err(error) => {
+            eprint!("{error}");
+            STATUS_USAGE
+        }
+    }
 }
 
-/// Handle redirections
-fn handle_redirections(
+/// Parses user input and runs the resulting list of commands
+///
+/// While the input is incomplete, e.g., when the body of a here-document is expected,
+/// or a quoted string continues on the next line, more lines are read,
+/// and an interactive user is prompted for them with the [`CONTINUATION_PROMPT`].
+///
+/// Invalid input sets the exit status to [`STATUS_USAGE`], as it does in other shells.
+fn parse_input_and_handle_cmds(
+    shell: &mut Shell,
     stdout: &mut Stdout,
     stderr: &mut Stderr,
-    redirections: Redirections,
-    output: Output,
+    input: &str,
 ) {
-    let (mut stdout_redir, mut stderr_redir) = (redirections.stdout, redirections.stderr);
-    let (mut stdout_data, mut stderr_data) = output.get();
-
-    eprintln!(
-        "01a stdout_redir: {:?}, stderr_redir: {:?}",
-        &stdout_redir, &stderr_redir
-    );
-    eprintln!(
-        "01b stdout_data: {:?}, stderr_data: {:?}",
-        String::from_utf8_lossy(&stdout_data),
-        String::from_utf8_lossy(&stderr_data)
-    );
-
-    let mut stdout_targets = stdout_redir.clone().paths; // todo rem clone()
-    let mut stderr_targets = stderr_redir.clone().paths; // todo rem clone()
-
-    // if stdout_redir.kind != RedirectionMode::None
-    //     && !stdout_targets.is_empty()
-    //     && stdout_targets
-    //         .last()
-    //         .expect("Expected last stdout target")
-    //         .clone()
-    //         .into_os_string()
-    //         .is_empty()
-    // if stdout_redir.kind != RedirectionMode::None && stdout_targets.is_empty() {
-    if stdout_redir.kind != RedirectionMode::None
-        && (stdout_targets.is_empty()
-            || !stdout_targets.is_empty()
-                && stdout_targets
-                    .last()
-                    .expect("Expected last stdout target")
-                    .clone()
-                    .into_os_string()
-                    .is_empty())
-    {
-        // TODO: Should I also swap here?!
-        eprintln!("here: stdout_redir");
-        stdout_redir.kind = RedirectionMode::None;
-    }
-
-    // if stderr_redir.kind != RedirectionMode::None
-    //     && !stderr_targets.is_empty()
-    //     && stderr_targets
-    //         .last()
-    //         .expect("Expected last stderr target")
-    //         .clone()
-    //         .into_os_string()
-    //         .is_empty()
-    // if stderr_redir.kind != RedirectionMode::None && stderr_targets.is_empty() {
-    if stderr_redir.kind != RedirectionMode::None
-        && (stderr_targets.is_empty()
-            || !stderr_targets.is_empty()
-                && stderr_targets
-                    .last()
-                    .expect("Expected last stderr target")
-                    .clone()
-                    .into_os_string()
-                    .is_empty())
-    {
-        // TODO: This is probably good, so don't touch it.
-        eprintln!("Swap stderr_redir!");
-        mem::swap(&mut stdout_data, &mut stderr_data);
-        mem::swap(&mut stdout_targets, &mut stderr_targets);
-        stdout_redir.kind = stderr_redir.kind;
-        stderr_redir.kind = RedirectionMode::None;
-    }
-
-    eprintln!("02a stdout_redir: {stdout_redir:?}, stderr_redir: {stderr_redir:?}");
-    eprintln!(
-        "02b stdout_data: {:?}, stderr_data: {:?}",
-        String::from_utf8_lossy(&stdout_data),
-        String::from_utf8_lossy(&stderr_data)
-    );
-
-    stdout_targets = stdout_redir.paths;
-    stderr_targets = stderr_redir.paths;
-
-    eprintln!("STDOUT");
-    // let stdout_targets = stdout_redir.paths.expect("Expected targets"); todo remove
-    // let stdout_targets = stdout_redir.paths; //.unwrap_or_default(); // todo clean up
-    // eprintln!("* {stdout_targets:?}, {}", stdout_targets.len()); // todo remove
-
-    // Truncate all but the last target file to zero size.
-    for target in &stdout_targets[..stdout_targets.len().saturating_sub(1)] {
-        eprintln!("HERE 1a");
-        eprintln!("* {}", target.display()); // todo remove
-        if target.clone().into_os_string().is_empty() {
-            eprintln!("HERE 2a");
-            continue;
-        }
-        if let Err(err) = File::create(target) {
-            eprintln!("{err}: Failed to create the file '{}'", &target.display());
+    let interactive = shell.interactive;
+    let next_line = || {
+        if interactive {
+            stdout
+                .write_all(CONTINUATION_PROMPT)
+                .expect(FAILED_WRITE_TO_STDOUT);
+            stdout.flush().expect(FAILED_FLUSH_TO_STDOUT);
         }
-    }
-    // eprintln!("--------"); // todo remove
-
-    match stdout_redir.kind {
-        RedirectionMode::None => stdout
-            .write_all(&stdout_data)
-            .expect(FAILED_WRITE_TO_STDOUT),
-        RedirectionMode::Overwrite => {
-            // let last_stdout_target = stdout_targets.last().expect("Expected last stdout target");
-            // write_redirected(&stdout_data, last_stdout_target, false);
-
-            if let Some(last_stdout_target) = stdout_targets.last() {
-                write_redirected(&stdout_data, last_stdout_target, false);
-            }
-        }
-        RedirectionMode::Append => {
-            // let last_stdout_target = stdout_targets.last().expect("Expected last stdout target");
-            // write_redirected(&stdout_data, last_stdout_target, true);
 
-            if let Some(last_stdout_target) = stdout_targets.last() {
-                write_redirected(&stdout_data, last_stdout_target, true);
-            }
+        let mut line = String::new();
+        match read_line(&mut line).expect(FAILED_READ_LINE) {
+            0 => None,
+            _ => Some(line),
         }
-    }
-
-    eprintln!("STDERR");
-    // let stderr_targets = stderr_redir.paths.expect("Expected targets"); todo remove
-    // let stderr_targets = stderr_redir.paths; //.unwrap_or_default(); // todo clean up
+    };
 
-    // Truncate all but the last target file to zero size.
-    for target in &stderr_targets[..stderr_targets.len().saturating_sub(1)] {
-        eprintln!("HERE 1b");
-        eprintln!("* {}", target.display()); // todo remove
-        if target.clone().into_os_string().is_empty() {
-            eprintln!("HERE 2b");
-            continue;
-        }
-        if let Err(err) = File::create(target) {
-            eprintln!("{err}: Failed to create the file '{}'", &target.display());
+    match parse_lines(input, next_line) {
+        Ok(list) => run_list(shell, list),
+        Err(error) => {
+            write!(stderr, "{error}").expect(FAILED_WRITE_TO_STDERR);
+            shell.last_status = STATUS_USAGE;
         }
     }
+}
 
-    // eprintln!("HERE 3: {:?}", stderr_targets.last().expect("Expected a target"));
-    // let last_stderr_target = stderr_targets.last().expect("Expected last stderr target");
-    // stderr
-    //     .write_all(&stderr_data)
-    //     .expect(FAILED_WRITE_TO_STDERR);
-
-    match stderr_redir.kind {
-        RedirectionMode::None => stderr
-            .write_all(&stderr_data)
-            .expect(FAILED_WRITE_TO_STDERR),
-        RedirectionMode::Overwrite => {
-            // let last_stderr_target = stderr_targets.last().expect("Expected last stderr target");
-            // write_redirected(&stderr_data, last_stderr_target, false);
-
-            if let Some(last_stderr_target) = stderr_targets.last() {
-                write_redirected(&stderr_data, last_stderr_target, false);
-            }
-        }
-        RedirectionMode::Append => {
-            // let last_stderr_target = stderr_targets.last().expect("Expected last stderr target");
-            // write_redirected(&stderr_data, last_stderr_target, true);
+/// Parses the `input` line, and while it is incomplete, appends the lines that `next_line` returns to it,
+/// until it is complete, or until there are no more lines.
+///
+/// The lines are joined with newlines, so that the newlines in quoted strings are preserved,
+/// and an escaped newline continues the line.
+///
+/// # Errors
+/// - Returns [`InvalidInputError`] in case of invalid input, or in case the input is still incomplete
+///   when there are no more lines.
+fn parse_lines(
+    input: &str,
+    mut next_line: impl FnMut() -> Option<String>,
+) -> Result<List, InvalidInputError> {
+    let mut input = strip_newline(input).to_string();
 
-            if let Some(last_stderr_target) = stderr_targets.last() {
-                write_redirected(&stderr_data, last_stderr_target, true);
+    loop {
+        match parse_input(&input) {
+            Err(error) if error.incomplete => {
+                let Some(line) = next_line() else {
+                    return Err(error);
+                };
+                input.push('\n');
+                input.push_str(strip_newline(&line));
             }
+            result => return result,
         }
     }
+}
 
-    // TODO remove
-    // match redirect {
-    //     Redirect::None => {
-    //         stdout
-    //             .write_all(&output.stdout)
-    //             .expect(FAILED_WRITE_TO_STDOUT);
-    //         stderr
-    //             .write_all(&output.stderr)
-    //             .expect(FAILED_WRITE_TO_STDERR);
-    //     }
-    //     Redirect::Stdout(target) => {
-    //         stderr
-    //             .write_all(&output.stderr)
-    //             .expect(FAILED_WRITE_TO_STDERR);
-    //         write_redirected(&output.stdout, &target, false);
-    //     }
-    //     Redirect::Stderr(target) => {
-    //         stdout
-    //             .write_all(&output.stdout)
-    //             .expect(FAILED_WRITE_TO_STDOUT);
-    //         write_redirected(&output.stderr, &target, false);
-    //     }
-    //     Redirect::AppendStdout(target) => {
-    //         stderr
-    //             .write_all(&output.stderr)
-    //             .expect(FAILED_WRITE_TO_STDERR);
-    //         write_redirected(&output.stdout, &target, true);
-    //     }
-    //     Redirect::AppendStderr(target) => {
-    //         stdout
-    //             .write_all(&output.stdout)
-    //             .expect(FAILED_WRITE_TO_STDOUT);
-    //         write_redirected(&output.stderr, &target, true);
-    //     }
-    //     Redirect::CombinedStdout(target) => {
-    //         write_redirected(&output.stdout, &target, false);
-    //         write_redirected(&output.stderr, &target, true);
-    //     }
-    //     Redirect::AppendCombinedStdout(target) => {
-    //         write_redirected(&output.stdout, &target, true);
-    //         write_redirected(&output.stderr, &target, true);
-    //     }
-    //     Redirect::CombinedStderr(target) | Redirect::AppendCombinedStderr(target) => {
-    //         stdout
-    //             .write_all(&output.stdout)
-    //             .expect(FAILED_WRITE_TO_STDOUT);
-    //         stderr
-    //             .write_all(&output.stderr)
-    //             .expect(FAILED_WRITE_TO_STDERR);
-    //         if !target.is_empty() {
-    //             if let Err(err) = File::create(&target) {
-    //                 eprintln!("{err}: Failed to create the file '{target}'");
-    //             }
-    //         }
-    //     }
-    // }
+/// Strips the line terminator, `\n` or `\r\n`, from the end of the `line`, if it has one.
+fn strip_newline(line: &str) -> &str {
+    line.strip_suffix('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .unwrap_or(line)
 }
 
-/// Helper for writing redirected output to the given target file
+/// Reads a line from the standard input, including its newline, if there is one, and appends it to `line`.
 ///
-/// # References
-/// - [Redirecting Output](https://www.gnu.org/software/bash/manual/bash.html#Redirecting-Output)
-/// - [Appending Redirected Output](https://www.gnu.org/software/bash/manual/bash.html#Appending-Redirected-Output)
-fn write_redirected(output: &[u8], target: &PathBuf, append: bool) {
-    if !append {
-        if let Err(err) = fs::write(target, output) {
-            eprintln!("{err}: Failed to write to file '{}'", target.display());
-        }
-    } else {
-        let mut file = match OpenOptions::new().append(true).create(true).open(target) {
-            Ok(file) => file,
-            Err(err) => {
-                eprintln!("{err}: Failed to open the file '{}'", target.display());
-                return;
-            }
-        };
-        if let Err(err) = file.write_all(output) {
-            eprintln!("{err}: Failed to append to file '{}'", target.display());
+/// Unlike [`Stdin::read_line`](io::Stdin::read_line), the line is read one byte at a time,
+/// directly from the file descriptor, so that the standard input is left positioned right after the line,
+/// and the commands that the shell runs can read the rest of the input, e.g., in `echo 'cat; echo b' | posix-shell`.
+/// Invalid UTF-8 sequences are replaced by `U+FFFD`.
+///
+/// Returns the number of bytes that were read, which is zero at the end of input.
+///
+/// # Errors
+/// - Returns [`io::Error`] in case the standard input can't be read.
+fn read_line(line: &mut String) -> io::Result<usize> {
+    let mut bytes = Vec::new();
+    let mut byte = 0_u8;
+
+    while bytes.last() != Some(&b'\n') {
+        match unsafe { libc::read(libc::STDIN_FILENO, (&raw mut byte).cast(), 1) } {
+            0 => break,
+            -1 => match io::Error::last_os_error() {
+                err if err.kind() == ErrorKind::Interrupted => continue,
+                err => return Err(err),
+            },
+            _ => bytes.push(byte),
         }
-        if let Err(err) = file.flush() {
-            eprintln!("{err}: Failed to flush the file '{}'", target.display());
-        };
     }
-}
-
-/// Builds a table of command handlers and returns it
-fn get_handlers<'a>() -> HashMap<&'a str, Handler> {
-    let pairs: [(&str, Handler); COMMANDS.len()] = zip(COMMANDS, HANDLERS)
-        .collect::<Vec<_>>()
-        .try_into()
-        .expect("Failed to convert vector to array");
 
-    HashMap::from(pairs)
+    line.push_str(&String::from_utf8_lossy(&bytes));
+    Ok(bytes.len())
 }
 
 /// Copies the value of the environment variable `DEBUG`, if it exists, to the global variable [`DEBUG`],
@@ -390,21 +349,146 @@ fn get_debug() {
     DEBUG.get_or_init(|| debug);
 }
 
-/// Copies the value of the environment variable `TEST`, if it exists, to the global variable [`TEST`],
-/// and if it doesn't exist, sets the global variable [`TEST`] to `false`.
-///
-/// If the environment variable `TEST` exists, it must hold string `true` for the global variable [`TEST`]
-/// to be set; otherwise, the global variable [`TEST`] will be reset.
-///
-/// This is only considered during **compile time**, and **not** during run time.
-fn get_test() {
-    let key: Option<&'static str> = option_env!("TEST");
+#[cfg(test)]
+mod tests {
+    use super::{Invocation, parse_lines, run_script};
+    use crate::constants::{STATUS_NOT_FOUND, STATUS_USAGE};
+    use crate::parse::parse_input;
+    use std::{env, fs, process};
 
-    let debug: bool = if let Some(key) = key {
-        key.trim().parse().unwrap_or_default()
-    } else {
-        false
-    };
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// Returns the path of a temporary file, whose `name` is unique to the test.
+    fn temp_path(name: &str) -> String {
+        let path = env::temp_dir().join(format!("posix-shell-test-{}-{name}", process::id()));
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn invocation_01() {
+        let expected = Invocation::Stdin { args: vec![] };
+        assert_eq!(Ok(expected), Invocation::parse(&[]));
+
+        let expected = Invocation::Script {
+            path: "script.sh".to_string(),
+            args: args(&["-c", "b"]),
+        };
+        assert_eq!(
+            Ok(expected),
+            Invocation::parse(&args(&["script.sh", "-c", "b"]))
+        );
+
+        let expected = Invocation::CommandString {
+            command: "echo $1".to_string(),
+            name: Some("name".to_string()),
+            args: args(&["a", "b"]),
+        };
+        let result = Invocation::parse(&args(&["-c", "echo $1", "name", "a", "b"]));
+        assert_eq!(Ok(expected), result);
+
+        let expected = Invocation::CommandString {
+            command: "-x".to_string(),
+            name: None,
+            args: vec![],
+        };
+        assert_eq!(Ok(expected), Invocation::parse(&args(&["-c", "--", "-x"])));
+
+        let expected = Invocation::Stdin {
+            args: args(&["a", "-b"]),
+        };
+        assert_eq!(Ok(expected), Invocation::parse(&args(&["-s", "a", "-b"])));
+    }
+
+    #[test]
+    fn invocation_02() {
+        for (input, expected) in [
+            (args(&["-c"]), "shell: -c: option requires an argument\n"),
+            (
+                args(&["-s", "-c"]),
+                "shell: -c: option requires an argument\n",
+            ),
+            (args(&["-x", "script.sh"]), "shell: -x: invalid option\n"),
+        ] {
+            let result = Invocation::parse(&input).unwrap_err();
+            assert!(result.starts_with(expected), "{input:?}");
+            assert!(result.ends_with(super::USAGE), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn parse_lines_01() {
+        for (input, lines, expected) in [
+            ("echo \"a  \n", vec!["b\"\n"], "echo \"a  \nb\""),
+            ("echo a \\\n", vec!["b\n"], "echo a b"),
+            ("x=$(echo a\n", vec!["echo b)\n"], "x=$(echo a\necho b)"),
+            ("echo 'a\r\n", vec!["", "b'\n"], "echo 'a\n\nb'"),
+            (
+                "cat <<EOF\n",
+                vec!["`echo a`\n", "EOF\n"],
+                "cat <<EOF\n`echo a`\nEOF",
+            ),
+            ("echo a &&\n", vec!["\n", "echo b\n"], "echo a &&\n\necho b"),
+            ("echo a |\n", vec!["cat\n"], "echo a |\ncat"),
+        ] {
+            let mut lines = lines
+                .into_iter()
+                .map(str::to_string)
+                .chain(["rest".to_string()]);
+            let result = parse_lines(input, || lines.next());
+            assert_eq!(parse_input(expected), result, "{input}");
+            // The lines after the complete input are left for the commands
+            assert_eq!(Some("rest".to_string()), lines.next(), "{input}");
+        }
+    }
 
-    TEST.get_or_init(|| debug);
+    #[test]
+    fn parse_lines_02() {
+        for input in [
+            "echo 'a",
+            "echo \"a",
+            "echo a \\",
+            "echo $(echo a",
+            "echo a ||",
+        ] {
+            let result = parse_lines(input, || None).unwrap_err();
+            assert!(result.incomplete, "{input}");
+        }
+
+        let mut lines = ["b\" ;;\n".to_string()].into_iter();
+        let result = parse_lines("echo \"a\n", || lines.next()).unwrap_err();
+        assert!(!result.incomplete);
+    }
+
+    #[test]
+    fn run_script_01() {
+        let path = temp_path("params.sh");
+        let contents = format!(
+            "#!/usr/local/bin/posix-shell\ncase \"$0:$#:$1:$2\" in\n  \"{path}:2:a b:c\") ;;\n  *) false ;;\nesac\n"
+        );
+        fs::write(&path, contents).unwrap();
+        assert_eq!(0, run_script(&path, &args(&["a b", "c"])));
+        assert_eq!(1, run_script(&path, &args(&["a b"])));
+
+        // The exit status is that of the last command
+        fs::write(&path, "false\nwait\n").unwrap();
+        assert_eq!(0, run_script(&path, &[]));
+        fs::write(&path, "wait\nfalse\n").unwrap();
+        assert_eq!(1, run_script(&path, &[]));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn run_script_02() {
+        let path = temp_path("missing.sh");
+        assert_eq!(STATUS_NOT_FOUND, run_script(&path, &[]));
+
+        // Nothing is run in case of a syntax error
+        let marker = temp_path("marker");
+        fs::write(&path, format!("echo x > {marker}\nif true; then\n")).unwrap();
+        assert_eq!(STATUS_USAGE, run_script(&path, &[]));
+        assert!(fs::metadata(&marker).is_err());
+        fs::remove_file(path).unwrap();
+    }
 }
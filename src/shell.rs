@@ -0,0 +1,72 @@
+//! Shell state
+//!
+//! The state of the shell that persists between commands.
+
+use crate::ast::FunctionDefinition;
+use crate::jobs::{JobControl, JobTable};
+use crate::vars::Variables;
+use std::collections::HashMap;
+use std::env;
+
+/// The state of the shell
+#[derive(Debug, Default)]
+pub struct Shell {
+    /// The exit status of the most recently executed pipeline,
+    /// which is the value of the special parameter `$?`
+    pub last_status: i32,
+    /// The jobs that are running in the background or are stopped
+    pub jobs: JobTable,
+    /// The job control state; `None` if job control is disabled,
+    /// which is the case in non-interactive shells and in subshells
+    pub job_control: Option<JobControl>,
+    /// The shell variables
+    pub variables: Variables,
+    /// The positional parameters, `$1`, `$2`, and so on
+    pub positional: Vec<String>,
+    /// The name of the shell, which is the value of the special parameter `$0`
+    pub name: String,
+    /// The process ID of the shell, which is the value of the special parameter `$$`;
+    /// subshells keep the process ID of the shell that they were forked from
+    pub pid: libc::pid_t,
+    /// The process ID of the most recent background job, which is the value of the special parameter `$!`
+    pub last_background_pid: Option<libc::pid_t>,
+    /// The number of loops that are currently running in the current function, or outside of functions,
+    /// which is the limit of `break` and `continue`
+    pub loop_depth: usize,
+    /// The pending `break`, `continue` or `return`, which stops running commands until the loop
+    /// or the function that it applies to is reached
+    pub jump: Option<Jump>,
+    /// The functions that have been defined, by name
+    pub functions: HashMap<String, FunctionDefinition>,
+    /// The number of functions that are currently running, which `return` and `local` require to be positive
+    pub function_depth: usize,
+    /// Whether the shell is interactive, i.e., it reads commands from a terminal, in which case it prints prompts
+    pub interactive: bool,
+}
+
+/// A `break` or a `continue`, with the number of enclosing loops that it applies to, or a `return`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Jump {
+    /// Exits from the `n`th enclosing loop
+    Break(usize),
+    /// Continues with the next iteration of the `n`th enclosing loop
+    Continue(usize),
+    /// Returns from the function that is running, with the exit status
+    Return(i32),
+    /// Exits from the shell, with the exit status, e.g., after a fatal expansion error in a non-interactive shell
+    Exit(i32),
+}
+
+impl Shell {
+    /// Constructs a new instance in the initial state.
+    ///
+    /// The shell variables are initialized from the environment of the shell process.
+    pub fn new() -> Self {
+        Self {
+            variables: Variables::from_env(),
+            name: env::args().next().unwrap_or_default(),
+            pid: unsafe { libc::getpid() },
+            ..Self::default()
+        }
+    }
+}
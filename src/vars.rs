@@ -0,0 +1,178 @@
+//! Shell variables
+//!
+//! The shell keeps its own table of variables, which is initialized from the environment of the shell process.
+//! Only the exported variables are passed in the environment of the commands that the shell runs,
+//! while the other variables are local to the shell.
+//!
+//! # References
+//!
+//! - [Shell Variables](https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html#tag_18_05_03)
+//! - [Shell Parameters @ Bash Reference Manual](https://www.gnu.org/software/bash/manual/bash.html#Shell-Parameters)
+
+use std::collections::HashMap;
+use std::env;
+
+/// A shell variable
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Variable {
+    /// The value of the variable, or `None` if it is unset, but has attributes, e.g., after `export name`
+    pub value: Option<String>,
+    /// Whether the variable is exported, i.e., passed in the environment of the commands that the shell runs
+    pub exported: bool,
+    /// Whether the variable is read-only, i.e., it can't be assigned or unset
+    pub readonly: bool,
+}
+
+/// The table of shell variables
+#[derive(Clone, Debug, Default)]
+pub struct Variables {
+    /// The variables by name
+    vars: HashMap<String, Variable>,
+    /// The scopes of the functions that are running, innermost last, each with the variables
+    /// that were made local in it, and their saved states from before that
+    scopes: Vec<Vec<(String, Option<Variable>)>>,
+}
+
+impl Variables {
+    /// Constructs a new table out of the environment of the shell process, whose variables are all exported.
+    pub fn from_env() -> Self {
+        let vars = env::vars()
+            .filter(|(name, _)| is_name(name))
+            .map(|(name, value)| {
+                let var = Variable {
+                    value: Some(value),
+                    exported: true,
+                    readonly: false,
+                };
+                (name, var)
+            })
+            .collect();
+
+        Self {
+            vars,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Gets the value of the variable `name`, or `None` if it isn't set.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name)?.value.as_deref()
+    }
+
+    /// Sets the `value` of the variable `name`, which keeps its attributes if it already exists.
+    ///
+    /// # Errors
+    /// - Returns the error message in case the variable is read-only.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let var = self.vars.entry(name.to_string()).or_default();
+        if var.readonly {
+            return Err(format!("{name}: readonly variable"));
+        }
+        var.value = Some(value.to_string());
+
+        Ok(())
+    }
+
+    /// Unsets the variable `name`, which also removes its attributes.
+    ///
+    /// # Errors
+    /// - Returns the error message in case the variable is read-only.
+    pub fn unset(&mut self, name: &str) -> Result<(), String> {
+        if self.vars.get(name).is_some_and(|var| var.readonly) {
+            return Err(format!("{name}: cannot unset: readonly variable"));
+        }
+        self.vars.remove(name);
+
+        Ok(())
+    }
+
+    /// Returns a copy of the variable `name`, including its attributes, or `None` if it doesn't exist,
+    /// which can be restored later.
+    pub fn save(&self, name: &str) -> Option<Variable> {
+        self.vars.get(name).cloned()
+    }
+
+    /// Restores the variable `name` to its `saved` state, regardless of its attributes.
+    pub fn restore(&mut self, name: &str, saved: Option<Variable>) {
+        match saved {
+            Some(var) => self.vars.insert(name.to_string(), var),
+            None => self.vars.remove(name),
+        };
+    }
+
+    /// Starts a new scope of local variables, when a function is called.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    /// Ends the innermost scope of local variables, when a function returns,
+    /// and restores the variables that were made local in it.
+    pub fn pop_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for (name, var) in scope.into_iter().rev() {
+            self.restore(&name, var);
+        }
+    }
+
+    /// Makes the variable `name` local to the innermost scope, so that its current state is restored
+    /// when the scope ends. It has no effect outside of functions, or if the variable is local already.
+    pub fn make_local(&mut self, name: &str) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if !scope.iter().any(|(local, _)| local == name) {
+            scope.push((name.to_string(), self.vars.get(name).cloned()));
+        }
+    }
+
+    /// Marks the variable `name` as exported, even if it isn't set.
+    pub fn export(&mut self, name: &str) {
+        self.vars.entry(name.to_string()).or_default().exported = true;
+    }
+
+    /// Marks the variable `name` as read-only, even if it isn't set.
+    pub fn set_readonly(&mut self, name: &str) {
+        self.vars.entry(name.to_string()).or_default().readonly = true;
+    }
+
+    /// Returns the names and the values of the exported variables that are set,
+    /// which make up the environment of the commands that the shell runs.
+    pub fn exported(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars.iter().filter_map(|(name, var)| match var {
+            Variable {
+                value: Some(value),
+                exported: true,
+                ..
+            } => Some((name.as_str(), value.as_str())),
+            _ => None,
+        })
+    }
+
+    /// Returns the names of the variables and the variables themselves, sorted by name.
+    pub fn sorted(&self) -> Vec<(&str, &Variable)> {
+        let mut vars = self
+            .vars
+            .iter()
+            .map(|(name, var)| (name.as_str(), var))
+            .collect::<Vec<_>>();
+        vars.sort_by_key(|&(name, _)| name);
+
+        vars
+    }
+}
+
+/// Checks whether `name` is a valid variable name, which consists of alphanumeric characters and underscores,
+/// and which doesn't start with a digit.
+///
+/// # References
+/// - [Name](https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/V1_chap03.html#tag_03_235)
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|ch| ch == '_' || ch.is_ascii_alphabetic())
+        && chars.all(|ch| ch == '_' || ch.is_ascii_alphanumeric())
+}
@@ -3,12 +3,7 @@
 # Fail immediately if any command has a non-zero exit status, or if a variable hasn't been defined.
 set -eu
 
-# Used in code to customize behaviour for testing.
-# Don't change this, as that will lead to an infinite main (repl) loop!
-# This is required in this script to be `true`.
-export TEST=true
-
-# Build project with the above `TEST` value.
+# Build the project.
 # Inside the `run.sh` script, `DEBUG` can be changed freely.
 echo "Building the shell for testing..."
 ./run.sh &